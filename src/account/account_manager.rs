@@ -1,12 +1,31 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use crate::account::balance::Balance;
+use crate::account::context::Context;
+use crate::account::equity::{EquityCurve, PriceGraph};
+use crate::account::margin::{MarginEvent, MarginPosition};
+use crate::account::order::{Order, OrderSide, OrderType};
+use crate::account::order_manager::OrderManager;
+use crate::account::order_request::OrderRequest;
+use crate::account::position::{AccountMode, FundingPayment, Position};
+use crate::account::rate_limiter::{RateLimit, RateLimiter, RequestType};
+use crate::account::orderbook::OrderBook;
+use crate::account::symbol_info::{OrderRejection, SymbolInfo};
+use crate::candles::candle::Candle;
 use crate::client_account::ClientAccount;
+use crate::decimal::{Decimal, DEFAULT_SCALE};
+use crate::account::trade::TradeSide;
+use crate::account::trader::Trader;
 use crate::account::trading_manager::TradingManager;
+use crate::candles::candle_history::CandleHistory;
 
 pub struct AccountManager<Account: ClientAccount> {
     trade_counter: u64,
+    order_counter: u64,
 
+    // number of syncs in which the balance of each symbol changed, counted
+    // over the last frequent_balance_update_interval
     balance_update_counts: HashMap<String, u64>,
+    balance_update_window_start: u64,
 
     // 10 * 60 * 1000
     balance_expiration_time: u64,
@@ -14,9 +33,17 @@ pub struct AccountManager<Account: ClientAccount> {
     // 15 * 60 * 1000
     frequent_balance_update_interval: u64,
 
+    // symbols updated at least this many times within the interval above
+    // have their balances expire after frequent_balance_expiration_time
+    frequent_balance_update_threshold: u64,
+    frequent_balance_expiration_time: u64,
+
     last_balance_sync: u64,
 
-    balances: Vec<Balance>,
+    balances: HashMap<String, Balance>,
+
+    // open futures positions by symbol, synced along with the balances
+    positions: HashMap<String, Position>,
 
     account: Account,
     account_hash: u64,
@@ -24,9 +51,35 @@ pub struct AccountManager<Account: ClientAccount> {
     margin_reserve_factor: f64,
     margin_reserve_factor_pct: f64,
 
-    latest_prices: HashMap<String, Vec<f64>>,
+    // margin levels (reserve over the value of the shorted quantity) that
+    // trigger a margin call and a forced close
+    margin_call_level: f64,
+    maintenance_margin_level: f64,
+
+    // percentage of the closed amount charged on liquidations
+    liquidation_fee: f64,
+
+    // symbols whose position is below the margin call level
+    margin_called: HashSet<String>,
+
+    latest_prices: HashMap<String, Decimal>,
+
+    // markets the latest prices belong to, to value assets through them
+    price_graph: PriceGraph,
+
+    // equity in the reference asset, recorded per candle
+    equity_curve: Option<EquityCurve>,
 
     trading_managers: HashMap<String, Vec<TradingManager>>,
+
+    // submitted orders by order id, along with their attachments
+    open_orders: HashMap<String, Order>,
+
+    order_manager: Option<OrderManager>,
+
+    symbol_info: HashMap<String, SymbolInfo>,
+
+    rate_limiter: RateLimiter,
 }
 
 impl <Account: ClientAccount> AccountManager<Account> {
@@ -38,15 +91,22 @@ impl <Account: ClientAccount> AccountManager<Account> {
     ) -> AccountManager<Account> {
         AccountManager {
             trade_counter: 0,
+            order_counter: 0,
 
             balance_update_counts: HashMap::new(),
+            balance_update_window_start: 0,
 
             balance_expiration_time,
             frequent_balance_update_interval,
 
+            frequent_balance_update_threshold: 3,
+            frequent_balance_expiration_time: balance_expiration_time / 4,
+
             last_balance_sync: 0,
 
-            balances: Vec::new(),
+            balances: HashMap::new(),
+
+            positions: HashMap::new(),
 
             account,
             account_hash: 0,
@@ -54,11 +114,1333 @@ impl <Account: ClientAccount> AccountManager<Account> {
             margin_reserve_factor,
             margin_reserve_factor_pct: margin_reserve_factor * 100.0,
 
+            margin_call_level: 1.25,
+            maintenance_margin_level: 1.1,
+
+            liquidation_fee: 0.0,
+
+            margin_called: HashSet::new(),
+
             latest_prices: HashMap::new(),
 
+            price_graph: PriceGraph::new(),
+
+            equity_curve: None,
+
             trading_managers: HashMap::new(),
 
-            signal_repository: SignalRepository::new(),
+            open_orders: HashMap::new(),
+
+            order_manager: None,
+
+            symbol_info: HashMap::new(),
+
+            rate_limiter: RateLimiter::new(),
+        }
+    }
+
+    pub fn account(&self) -> &Account {
+        &self.account
+    }
+
+    pub fn account_mut(&mut self) -> &mut Account {
+        &mut self.account
+    }
+
+    pub fn account_hash(&self) -> u64 {
+        self.account_hash
+    }
+
+    pub fn trade_counter(&self) -> u64 {
+        self.trade_counter
+    }
+
+    pub fn balances(&self) -> &HashMap<String, Balance> {
+        &self.balances
+    }
+
+    pub fn balance(&self, symbol: &str) -> Option<&Balance> {
+        self.balances.get(symbol)
+    }
+
+    pub fn account_mode(&self) -> AccountMode {
+        self.account.account_mode()
+    }
+
+    pub fn positions(&self) -> &HashMap<String, Position> {
+        &self.positions
+    }
+
+    pub fn position(&self, symbol: &str) -> Option<&Position> {
+        self.positions.get(symbol)
+    }
+
+    /// Funding paid or received by futures positions since the last call.
+    pub fn funding_payments(&mut self) -> Vec<FundingPayment> {
+        self.account.funding_payments()
+    }
+
+    pub fn balance_update_counts(&self) -> &HashMap<String, u64> {
+        &self.balance_update_counts
+    }
+
+    pub fn balance_expiration_time(&self) -> u64 {
+        self.balance_expiration_time
+    }
+
+    pub fn frequent_balance_update_interval(&self) -> u64 {
+        self.frequent_balance_update_interval
+    }
+
+    pub fn frequent_balance_update_threshold(&self) -> u64 {
+        self.frequent_balance_update_threshold
+    }
+
+    pub fn set_frequent_balance_update_threshold(&mut self, frequent_balance_update_threshold: u64) {
+        self.frequent_balance_update_threshold = frequent_balance_update_threshold;
+    }
+
+    pub fn frequent_balance_expiration_time(&self) -> u64 {
+        self.frequent_balance_expiration_time
+    }
+
+    pub fn set_frequent_balance_expiration_time(&mut self, frequent_balance_expiration_time: u64) {
+        self.frequent_balance_expiration_time = frequent_balance_expiration_time;
+    }
+
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
+
+    pub fn rate_limiter_mut(&mut self) -> &mut RateLimiter {
+        &mut self.rate_limiter
+    }
+
+    /// Limits balance updates sent to the account to `max_balance_updates`
+    /// per `frequent_balance_update_interval`. Updates over the limit are
+    /// skipped, keeping the cached balances.
+    pub fn set_max_balance_updates(&mut self, max_balance_updates: u64) {
+        self.rate_limiter.set_balance_limit(Some(RateLimit::new(
+            max_balance_updates,
+            self.frequent_balance_update_interval,
+        )));
+    }
+
+    // waits for the rate limiter to allow a request to the account, false
+    // if it must be skipped
+    fn acquire(&mut self, request: RequestType, always: bool) -> bool {
+        self.rate_limiter.acquire(request, always).is_ok()
+    }
+
+    // stops requests for as long as the account was asked to
+    fn check_retry_after(&mut self) {
+        if let Some(retry_after) = self.account.retry_after() {
+            self.rate_limiter.back_off(retry_after);
+        }
+    }
+
+    pub fn last_balance_sync(&self) -> u64 {
+        self.last_balance_sync
+    }
+
+    /// Symbols whose balance changed often enough in the current interval to
+    /// be refreshed more frequently.
+    pub fn frequently_updated_symbols(&self) -> Vec<&str> {
+        let mut symbols: Vec<&str> = self.balance_update_counts
+            .iter()
+            .filter(|(_, count)| **count >= self.frequent_balance_update_threshold)
+            .map(|(symbol, _)| symbol.as_str())
+            .collect();
+
+        symbols.sort_unstable();
+
+        symbols
+    }
+
+    /// Age at which the cached balance of a symbol expires, shorter for
+    /// symbols whose balance changes often.
+    pub fn balance_expiration(&self, symbol: &str) -> u64 {
+        let update_counts = self.balance_update_counts
+            .get(symbol)
+            .copied()
+            .unwrap_or(0);
+
+        if update_counts >= self.frequent_balance_update_threshold {
+            self.frequent_balance_expiration_time
+        } else {
+            self.balance_expiration_time
+        }
+    }
+
+    /// True if the cached balance of a symbol is too old to be relied on at
+    /// `now`.
+    pub fn balance_expired(&self, symbol: &str, now: u64) -> bool {
+        self.last_balance_sync == 0
+            || now.saturating_sub(self.last_balance_sync) >= self.balance_expiration(symbol)
+    }
+
+    /// True if any of the cached balances is too old to be relied on at
+    /// `now`.
+    pub fn balances_expired(&self, now: u64) -> bool {
+        self.last_balance_sync == 0
+            || now.saturating_sub(self.last_balance_sync) >= self.balance_expiration_time
+            || self.balances.keys().any(|symbol| self.balance_expired(symbol, now))
+    }
+
+    /// Updates the balances from the account if any of the cached ones
+    /// expired. Returns true if the account was queried, false if they were
+    /// still valid or the rate limiter skipped the update.
+    pub fn sync_balances(&mut self, now: u64) -> bool {
+        self.balances_expired(now) && self.update_balances(now, false)
+    }
+
+    /// Updates the balances from the account if the cached balance of
+    /// `symbol` expired, regardless of the others. Returns true if the
+    /// account was queried.
+    pub fn sync_balance(&mut self, symbol: &str, now: u64) -> bool {
+        self.balance_expired(symbol, now) && self.update_balances(now, false)
+    }
+
+    /// Updates the balances from the account regardless of their age, e.g.
+    /// after an order filled. Returns false if the rate limiter skipped the
+    /// update.
+    pub fn refresh_balances(&mut self, now: u64) -> bool {
+        self.update_balances(now, true)
+    }
+
+    fn update_balances(&mut self, now: u64, force: bool) -> bool {
+        if now.saturating_sub(self.balance_update_window_start) >= self.frequent_balance_update_interval {
+            self.balance_update_counts.clear();
+            self.balance_update_window_start = now;
+        }
+
+        if !self.acquire(RequestType::Balances, false) {
+            return false;
+        }
+
+        let balances = self.account.update_balances(force);
+
+        self.check_retry_after();
+
+        for (symbol, mut balance) in balances {
+            let previous = self.balances.get(&symbol);
+
+            let changed = match previous {
+                Some(previous) => balance.has_changed(previous),
+                None => false,
+            };
+
+            let mut update_counts = previous
+                .map(|previous| previous.balance_update_counts())
+                .unwrap_or(0);
+
+            if changed {
+                update_counts += 1;
+
+                *self.balance_update_counts
+                    .entry(symbol.clone())
+                    .or_insert(0) += 1;
+            }
+
+            balance.set_balance_update_counts(update_counts);
+
+            self.balances.insert(symbol, balance);
+        }
+
+        self.positions = self.account.update_positions();
+
+        self.last_balance_sync = now;
+
+        true
+    }
+
+    pub fn margin_reserve_factor(&self) -> f64 {
+        self.margin_reserve_factor
+    }
+
+    pub fn margin_reserve_factor_pct(&self) -> f64 {
+        self.margin_reserve_factor_pct
+    }
+
+    pub fn margin_call_level(&self) -> f64 {
+        self.margin_call_level
+    }
+
+    pub fn set_margin_call_level(&mut self, margin_call_level: f64) {
+        self.margin_call_level = margin_call_level;
+    }
+
+    pub fn maintenance_margin_level(&self) -> f64 {
+        self.maintenance_margin_level
+    }
+
+    pub fn set_maintenance_margin_level(&mut self, maintenance_margin_level: f64) {
+        self.maintenance_margin_level = maintenance_margin_level;
+    }
+
+    pub fn liquidation_fee(&self) -> f64 {
+        self.liquidation_fee
+    }
+
+    pub fn set_liquidation_fee(&mut self, liquidation_fee: f64) {
+        self.liquidation_fee = liquidation_fee;
+    }
+
+    pub fn latest_prices(&self) -> &HashMap<String, Decimal> {
+        &self.latest_prices
+    }
+
+    pub fn latest_price(&self, symbol: &str) -> Option<Decimal> {
+        self.latest_prices.get(symbol).copied()
+    }
+
+    pub fn update_latest_price(&mut self, symbol: &str, price: Decimal) {
+        self.latest_prices.insert(symbol.to_string(), price);
+    }
+
+    /// Updates the latest price of a market and registers it, so assets can
+    /// be valued through it.
+    pub fn update_market_price(&mut self, assets_symbol: &str, funds_symbol: &str, price: Decimal) {
+        self.price_graph.add_market(assets_symbol, funds_symbol);
+
+        self.update_latest_price(&format!("{}{}", assets_symbol, funds_symbol), price);
+    }
+
+    pub fn add_market(&mut self, assets_symbol: &str, funds_symbol: &str) {
+        self.price_graph.add_market(assets_symbol, funds_symbol);
+    }
+
+    pub fn price_graph(&self) -> &PriceGraph {
+        &self.price_graph
+    }
+
+    /// Converts an amount between two assets at the latest prices, going
+    /// through intermediate markets when there is no direct one.
+    pub fn convert(&self, amount: Decimal, from: &str, to: &str) -> Option<Decimal> {
+        self.price_graph.convert(amount, from, to, &self.latest_prices)
+    }
+
+    /// Value of all synced balances in `reference_symbol`: free and locked
+    /// amounts, margin and unrealized profit of futures positions, minus
+    /// what was shorted. Assets that can't be converted are left out, see
+    /// `unvalued_assets`.
+    pub fn equity(&self, reference_symbol: &str) -> Decimal {
+        self.balances
+            .values()
+            .filter_map(|balance| self.convert(balance.net_total(), balance.symbol(), reference_symbol))
+            .sum()
+    }
+
+    /// Assets with a balance that have no route to `reference_symbol`.
+    pub fn unvalued_assets(&self, reference_symbol: &str) -> Vec<&str> {
+        let mut assets: Vec<&str> = self.balances
+            .values()
+            .filter(|balance| !balance.net_total().is_zero())
+            .filter(|balance| self.convert(Decimal::ONE, balance.symbol(), reference_symbol).is_none())
+            .map(|balance| balance.symbol())
+            .collect();
+
+        assets.sort_unstable();
+
+        assets
+    }
+
+    /// Starts recording the equity curve in the given reference asset.
+    pub fn set_reference_symbol(&mut self, reference_symbol: &str) {
+        self.equity_curve = Some(EquityCurve::new(reference_symbol.to_string()));
+    }
+
+    pub fn equity_curve(&self) -> Option<&EquityCurve> {
+        self.equity_curve.as_ref()
+    }
+
+    /// Adds the current equity to the equity curve at the close of the
+    /// candle, if a reference asset is set.
+    pub fn record_equity(&mut self, candle: &Candle) -> Option<Decimal> {
+        let reference_symbol = self.equity_curve.as_ref()?.reference_symbol().to_string();
+
+        let equity = self.equity(&reference_symbol);
+
+        if let Some(equity_curve) = self.equity_curve.as_mut() {
+            equity_curve.record(candle.close_time, equity);
+        }
+
+        Some(equity)
+    }
+
+    /// Value in funds of what is held of an asset, plus the remaining
+    /// amount of open buys of it.
+    pub fn asset_exposure(&self, assets_symbol: &str, funds_symbol: &str) -> Decimal {
+        let symbol = format!("{}{}", assets_symbol, funds_symbol);
+
+        let held = match (self.balances.get(assets_symbol), self.latest_price(&symbol)) {
+            (Some(balance), Some(price)) => (balance.total() * price).max(Decimal::ZERO),
+            _ => Decimal::ZERO,
+        };
+
+        let pending: Decimal = self.open_orders
+            .values()
+            .filter(|order| order.order_request.symbol() == symbol)
+            .filter(|order| order.order_request.is_long_buy() && !order.is_finalized())
+            .map(|order| order.get_remaining_quantity() * order.order_request.price())
+            .sum();
+
+        held + pending
+    }
+
+    /// Short positions found in the synced balances, valued at the latest
+    /// price of their symbol. Positions without a known price are skipped.
+    pub fn margin_positions(&self) -> Vec<MarginPosition> {
+        let mut positions = vec![];
+
+        for (funds_symbol, funds) in self.balances.iter() {
+            for (assets_symbol, margin_reserve) in funds.margin_reserves.iter() {
+                let shorted = match self.balances.get(assets_symbol) {
+                    Some(assets) if assets.shorted.is_positive() => assets.shorted,
+                    _ => continue,
+                };
+
+                let price = match self.latest_price(&format!("{}{}", assets_symbol, funds_symbol)) {
+                    Some(price) => price,
+                    None => continue,
+                };
+
+                positions.push(MarginPosition::new(
+                    assets_symbol.clone(),
+                    funds_symbol.clone(),
+                    shorted,
+                    price,
+                    *margin_reserve,
+                ));
+            }
+        }
+
+        positions.sort_by_key(|position| position.symbol());
+
+        positions
+    }
+
+    /// Compares the margin level of every short position with the margin
+    /// call and maintenance levels. A margin call is emitted once when a
+    /// position crosses below the margin call level, and positions below the
+    /// maintenance level are closed at market, paying the liquidation fee.
+    ///
+    /// The open orders of a position are cancelled before it is closed, so
+    /// that they neither hold on to its funds nor reopen it later. They are
+    /// reported as finalized by the next `update_open_orders`.
+    ///
+    /// Futures positions are liquidated by the exchange itself, those
+    /// liquidations are reported here as well.
+    pub fn check_margin_levels(&mut self, now: u64) -> Vec<MarginEvent> {
+        let mut events = vec![];
+        let mut liquidated = false;
+
+        for order in self.account.liquidations() {
+            self.cancel_symbol_orders(&order.order_request.symbol());
+
+            let position = MarginPosition::new(
+                order.order_request.assets_symbol().to_string(),
+                order.order_request.funds_symbol().to_string(),
+                order.executed_quantity(),
+                order.average_price(),
+                Decimal::ZERO,
+            );
+
+            events.push(MarginEvent::Liquidation(position, Box::new(order)));
+
+            liquidated = true;
+        }
+
+        for position in self.margin_positions() {
+            let symbol = position.symbol();
+
+            if position.margin_level() < self.maintenance_margin_level {
+                let mut order_request = OrderRequest::new(
+                    position.assets_symbol().to_string(),
+                    position.funds_symbol().to_string(),
+                    OrderSide::Buy,
+                    TradeSide::SHORT,
+                    now,
+                    None,
+                );
+
+                order_request.set_order_type(OrderType::Market);
+                order_request.set_quantity(position.shorted());
+
+                self.cancel_symbol_orders(&symbol);
+
+                let liquidation_fee = Decimal::from_f64(self.liquidation_fee, DEFAULT_SCALE).unwrap_or(Decimal::ZERO);
+
+                self.acquire(RequestType::Order, true);
+
+                let order = self.account.liquidate(order_request, liquidation_fee);
+
+                self.margin_called.remove(&symbol);
+
+                events.push(MarginEvent::Liquidation(position, Box::new(order)));
+
+                liquidated = true;
+            } else if position.margin_level() < self.margin_call_level {
+                if self.margin_called.insert(symbol) {
+                    events.push(MarginEvent::MarginCall(position));
+                }
+            } else {
+                self.margin_called.remove(&symbol);
+            }
+        }
+
+        if liquidated {
+            self.refresh_balances(now);
+        }
+
+        events
+    }
+
+    // cancels the open orders of a symbol and their attachments, keeping
+    // them until they are reported as finalized
+    fn cancel_symbol_orders(&mut self, symbol: &str) {
+        let order_ids: Vec<String> = self.open_orders
+            .values()
+            .filter(|order| order.order_request.symbol() == symbol)
+            .map(|order| order.order_id())
+            .collect();
+
+        for order_id in order_ids {
+            if let Some(order) = self.cancel_order(&order_id) {
+                self.open_orders.insert(order_id, order);
+            }
+        }
+    }
+
+    pub fn trading_managers(&self) -> &HashMap<String, Vec<TradingManager>> {
+        &self.trading_managers
+    }
+
+    pub fn open_orders(&self) -> &HashMap<String, Order> {
+        &self.open_orders
+    }
+
+    pub fn order_manager(&self) -> Option<&OrderManager> {
+        self.order_manager.as_ref()
+    }
+
+    pub fn set_order_manager(&mut self, order_manager: OrderManager) {
+        self.order_manager = Some(order_manager);
+    }
+
+    pub fn symbol_info(&self, symbol: &str) -> Option<&SymbolInfo> {
+        self.symbol_info.get(symbol)
+    }
+
+    /// Context of a trader for its strategies and monitors.
+    pub fn context<'a>(
+        &'a self,
+        trader: &'a Trader,
+        order_book: Option<&'a OrderBook>,
+        candle_history: &'a CandleHistory,
+    ) -> Context<'a> {
+        Context::new(
+            trader,
+            &self.balances,
+            self.symbol_info.get(&trader.symbol()),
+            order_book,
+            candle_history,
+        )
+    }
+
+    pub fn add_symbol_info(&mut self, symbol_info: SymbolInfo) {
+        self.symbol_info.insert(
+            symbol_info.symbol().to_string(),
+            symbol_info,
+        );
+    }
+
+    /// Rounds an order request to the trading rules of its symbol and
+    /// validates it. Requests for symbols without registered rules are only
+    /// checked for a usable price and quantity.
+    pub fn adjust_order_request(
+        &self,
+        order_request: &mut OrderRequest,
+    ) -> Result<(), OrderRejection> {
+        match self.symbol_info.get(&order_request.symbol()) {
+            Some(symbol_info) => symbol_info.adjust(order_request),
+            None => SymbolInfo::new(
+                order_request.symbol(),
+                Decimal::ZERO,
+                Decimal::ZERO,
+                Decimal::ZERO,
+                Decimal::ZERO,
+                Decimal::ZERO,
+                0,
+            ).validate(order_request),
+        }
+    }
+
+    /// Margin reserve a short sell keeps locked in the funds balance until it
+    /// is covered: the proceeds of the sale times the margin reserve factor.
+    pub fn margin_reserve_for(
+        &self,
+        order_request: &OrderRequest,
+    ) -> Decimal {
+        if !order_request.is_short_sell() {
+            return Decimal::ZERO;
+        }
+
+        order_request.total_order_amount()
+            * Decimal::from_f64(self.margin_reserve_factor, DEFAULT_SCALE).unwrap_or(Decimal::ONE)
+    }
+
+    /// Checks that the free funds cover the margin a short sell adds on top
+    /// of its proceeds. Funds that were never synced are not checked.
+    fn check_margin_reserve(
+        &self,
+        order_request: &OrderRequest,
+    ) -> Result<(), OrderRejection> {
+        if !order_request.is_short_sell() || self.account_mode() == AccountMode::Futures {
+            return Ok(());
+        }
+
+        let funds = match self.balances.get(order_request.funds_symbol()) {
+            Some(funds) => funds,
+            None => return Ok(()),
+        };
+
+        let required = self.margin_reserve_for(order_request) - order_request.total_order_amount();
+
+        if funds.free < required {
+            return Err(OrderRejection::InsufficientMargin {
+                required,
+                available: funds.free,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn next_trade_id(&mut self) -> u64 {
+        self.trade_counter += 1;
+        self.trade_counter
+    }
+
+    fn next_order_id(&mut self) -> u64 {
+        self.order_counter += 1;
+        self.order_counter
+    }
+
+    /// Submits an order to the account. Requests attached to it (take profit
+    /// and stop loss) are kept dormant on the returned order and only
+    /// submitted once it fills.
+    ///
+    /// The request is rounded to the symbol's trading rules first, and never
+    /// reaches the account if it doesn't satisfy them or if it is a short
+    /// sell the free funds can't cover the margin of. Orders the rate limiter
+    /// can't send soon enough are rejected as well.
+    pub fn submit_order(
+        &mut self,
+        mut order_request: OrderRequest,
+    ) -> Result<Order, OrderRejection> {
+        self.adjust_order_request(&mut order_request)?;
+        self.check_margin_reserve(&order_request)?;
+
+        if let Err(retry_after) = self.rate_limiter.acquire(RequestType::Order, false) {
+            return Err(OrderRejection::RateLimited { retry_after });
+        }
+
+        Ok(self.place_order(order_request))
+    }
+
+    // sends a request that passed the checks of submit_order to the account
+    fn place_order(
+        &mut self,
+        mut order_request: OrderRequest,
+    ) -> Order {
+        self.price_graph.add_market(
+            order_request.assets_symbol(),
+            order_request.funds_symbol(),
+        );
+
+        let attached_requests = order_request.attached_order_requests();
+
+        order_request.set_attached_order_requests(vec![]);
+
+        let mut order = self.account.execute_order(order_request);
+
+        self.check_retry_after();
+
+        for attached_request in attached_requests {
+            let attachment = Order::new_from_order_request(
+                self.next_order_id(),
+                attached_request,
+            );
+
+            order.attach(attachment);
+        }
+
+        self.open_orders.insert(
+            order.order_id(),
+            order.clone(),
+        );
+
+        order
+    }
+
+    /// Cancels an open order along with any of its attachments that were not
+    /// filled yet.
+    pub fn cancel_order(
+        &mut self,
+        order_id: &str,
+    ) -> Option<Order> {
+        let mut order = self.open_orders.remove(order_id)?;
+
+        if !order.is_finalized() {
+            self.acquire(RequestType::Cancel, true);
+            self.account.cancel(&mut order);
+        }
+
+        if let Some(order_manager) = self.order_manager.as_mut() {
+            order_manager.order_finalized(&order);
+        }
+
+        for attachment in order.attachments.iter_mut() {
+            if attachment.is_dormant() {
+                attachment.order_request.cancel();
+                attachment.cancel();
+            } else if !attachment.is_finalized() {
+                self.acquire(RequestType::Cancel, true);
+                self.account.cancel(attachment);
+            }
+        }
+
+        self.check_retry_after();
+
+        order.update_attachments();
+
+        Some(order)
+    }
+
+    /// Polls the status of every open order and drives their attachments:
+    /// dormant attachments are resized to the quantity executed by their
+    /// parent and submitted together as an OCO group once it is finalized,
+    /// and when one attachment fills or is cancelled its siblings are
+    /// cancelled.
+    ///
+    /// Returns the orders that are done, i.e. finalized along with all of
+    /// their attachments.
+    pub fn update_open_orders(&mut self) -> Vec<Order> {
+        let order_ids: Vec<String> = self.open_orders
+            .keys()
+            .cloned()
+            .collect();
+
+        let mut finalized = vec![];
+
+        for order_id in order_ids {
+            let mut order = match self.open_orders.remove(&order_id) {
+                Some(order) => order,
+                None => continue,
+            };
+
+            self.update_order(&mut order);
+
+            let done = order.is_finalized()
+                && order.attachments()
+                    .iter()
+                    .all(|attachment| attachment.is_finalized());
+
+            if done {
+                if let Some(order_manager) = self.order_manager.as_mut() {
+                    order_manager.order_finalized(&order);
+                }
+
+                finalized.push(order);
+            } else {
+                self.open_orders.insert(order_id, order);
+            }
+        }
+
+        finalized
+    }
+
+    /// Cancels limit orders the order manager considers stale and resubmits
+    /// their remaining quantity at an updated price. Each new request links
+    /// back to the order it replaces through `resubmitted_from`.
+    ///
+    /// Orders are only cancelled once their replacement passed the trading
+    /// rules, margin and rate limits. If the account still rejects the
+    /// replacement, what is left of the original is placed again at its
+    /// price.
+    ///
+    /// Returns the orders that were resubmitted.
+    pub fn resubmit_stale_orders(
+        &mut self,
+        candle: &Candle,
+        order_book: Option<&OrderBook>,
+    ) -> Vec<Order> {
+        let mut order_manager = match self.order_manager.take() {
+            Some(order_manager) => order_manager,
+            None => return vec![],
+        };
+
+        let order_ids: Vec<String> = self.open_orders
+            .keys()
+            .cloned()
+            .collect();
+
+        let mut resubmitted = vec![];
+
+        for order_id in order_ids {
+            let order = &self.open_orders[&order_id];
+
+            if !order_manager.is_stale(order, candle) {
+                continue;
+            }
+
+            let price = match order_manager.resubmission_price(order, candle, order_book) {
+                Some(price) => price,
+                None => continue,
+            };
+
+            let mut order_request = order.order_request.clone();
+
+            order_request.set_price(price);
+            order_request.set_quantity(order.get_remaining_quantity());
+            order_request.update_time(candle.close_time);
+            order_request.set_resubmitted_from(order);
+            order_request.set_attached_order_requests(
+                order.attachments()
+                    .iter()
+                    .filter(|attachment| attachment.is_dormant())
+                    .map(|attachment| attachment.order_request.clone())
+                    .collect(),
+            );
+
+            // keep the order open if what is left can't be resubmitted
+            if self.adjust_order_request(&mut order_request).is_err()
+                || self.check_margin_reserve(&order_request).is_err()
+                || !self.acquire(RequestType::Order, false)
+            {
+                continue;
+            }
+
+            let mut order = self.open_orders.remove(&order_id).unwrap();
+
+            self.acquire(RequestType::Cancel, true);
+            self.account.cancel(&mut order);
+            self.check_retry_after();
+
+            // it may have filled further before the cancellation went through
+            let remaining_quantity = order.get_remaining_quantity();
+
+            order_request.set_quantity(remaining_quantity);
+
+            if !order.is_cancelled() || self.adjust_order_request(&mut order_request).is_err() {
+                self.open_orders.insert(order_id, order);
+
+                continue;
+            }
+
+            order_manager.order_finalized(&order);
+
+            let mut original = order.order_request.clone();
+
+            original.set_quantity(remaining_quantity);
+            original.set_attached_order_requests(order_request.attached_order_requests());
+
+            // the cancelled order keeps whatever it filled, its attachments
+            // get submitted for that part on the next update
+            self.open_orders.insert(order_id, order);
+
+            let replacement = self.place_order(order_request);
+
+            if replacement.is_cancelled() && replacement.executed_quantity().is_zero() {
+                self.open_orders.remove(&replacement.order_id());
+
+                // counted as new, so that it isn't stale right away
+                original.update_time(candle.close_time);
+
+                self.acquire(RequestType::Order, true);
+                self.place_order(original);
+            } else {
+                resubmitted.push(replacement);
+            }
+        }
+
+        self.order_manager = Some(order_manager);
+
+        resubmitted
+    }
+
+    fn update_order(
+        &mut self,
+        order: &mut Order,
+    ) {
+        if !order.is_finalized() {
+            // polled again on the next update if over the rate limit
+            if !self.acquire(RequestType::OrderStatus, false) {
+                return;
+            }
+
+            self.account.update_order_status(order);
+            self.check_retry_after();
+        }
+
+        order.update_attachments();
+
+        if !order.is_finalized() {
+            return;
+        }
+
+        if order.executed_quantity().is_zero() {
+            // nothing was bought or sold, there is no position to protect
+            for attachment in order.attachments.iter_mut() {
+                if attachment.is_dormant() {
+                    attachment.order_request.cancel();
+                    attachment.cancel();
+                }
+            }
+
+            return;
+        }
+
+        // dormant attachments are submitted together, only one of them may
+        // fill
+        let mut submitted = vec![];
+        let mut order_requests = vec![];
+
+        for (i, attachment) in order.attachments.iter_mut().enumerate() {
+            if !attachment.is_dormant() {
+                continue;
+            }
+
+            let mut order_request = attachment.order_request.clone();
+
+            order_request.set_quantity(attachment.quantity());
+            order_request.activate();
+
+            // e.g. a partial fill too small to be sold on its own
+            if self.adjust_order_request(&mut order_request).is_err() {
+                attachment.order_request.cancel();
+                attachment.cancel();
+
+                continue;
+            }
+
+            submitted.push(i);
+            order_requests.push(order_request);
+        }
+
+        if !order_requests.is_empty() {
+            // protects a position, sent however long it takes
+            self.acquire(RequestType::Order, true);
+
+            let orders = self.account.execute_oco(order_requests);
+
+            if orders.len() == submitted.len() {
+                for (i, mut attachment) in submitted.into_iter().zip(orders) {
+                    attachment.parent = order.attachments[i].parent.take();
+                    order.attachments[i] = attachment;
+                }
+            } else {
+                // the account didn't answer for every leg, so which order is
+                // which leg can't be told: none of them is kept
+                for mut placed in orders {
+                    if !placed.is_finalized() {
+                        self.acquire(RequestType::Cancel, true);
+                        self.account.cancel(&mut placed);
+                    }
+                }
+
+                for i in submitted {
+                    order.attachments[i].order_request.cancel();
+                    order.attachments[i].cancel();
+                }
+            }
+        }
+
+        for attachment in order.attachments.iter_mut() {
+            if !attachment.is_finalized() && self.acquire(RequestType::OrderStatus, false) {
+                self.account.update_order_status(attachment);
+            }
+        }
+
+        // once one attachment filled or was cancelled the others can't
+        // stay, accounts without OCO orders leave them open
+        let closed = order.attachments
+            .iter()
+            .any(|attachment| attachment.is_finalized());
+
+        if closed {
+            for sibling in order.attachments.iter_mut() {
+                if !sibling.is_finalized() {
+                    self.acquire(RequestType::Cancel, true);
+                    self.account.cancel(sibling);
+                }
+            }
+        }
+
+        self.check_retry_after();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::order::OrderStatus;
+    use crate::account::order_manager::ResubmissionPrice;
+    use crate::simulation::simulated_account::SimulatedAccount;
+    use crate::trading_fees::PercentageFee;
+
+    // exchange whose orders only fill when the test reports it
+    #[derive(Default)]
+    struct Exchange {
+        balances: HashMap<String, Balance>,
+
+        orders: HashMap<String, Order>,
+        order_counter: u64,
+
+        // orders priced above are rejected, as by a price band
+        reject_above: Option<Decimal>,
+
+        // legs of an OCO answered for, as by a truncated response
+        oco_legs: Option<usize>,
+    }
+
+    impl Exchange {
+        fn set_amount(&mut self, symbol: &str, free: i64) {
+            let mut balance = Balance::new(symbol.to_string());
+
+            balance.free(Decimal::from(free));
+
+            self.balances.insert(symbol.to_string(), balance);
+        }
+
+        fn report(&mut self, order_id: &str, executed_quantity: i64, status: OrderStatus) {
+            let order = self.orders.get_mut(order_id).unwrap();
+
+            order.set_executed_quantity(Decimal::from(executed_quantity));
+            order.set_average_price(order.price());
+            order.set_status(status);
+        }
+    }
+
+    impl ClientAccount for Exchange {
+        fn execute_order(&mut self, order_details: OrderRequest) -> Order {
+            self.order_counter += 1;
+
+            let mut order = Order::new_from_order_request(self.order_counter, order_details);
+
+            if self.reject_above.map(|price| order.price() > price).unwrap_or(false) {
+                order.set_status(OrderStatus::Cancelled);
+            }
+
+            self.orders.insert(order.order_id(), order.clone());
+
+            order
+        }
+
+        fn execute_oco(&mut self, order_details: Vec<OrderRequest>) -> Vec<Order> {
+            let mut orders: Vec<Order> = order_details
+                .into_iter()
+                .map(|order_request| self.execute_order(order_request))
+                .collect();
+
+            if let Some(oco_legs) = self.oco_legs {
+                orders.truncate(oco_legs);
+            }
+
+            orders
+        }
+
+        fn update_balances(&mut self, _force: bool) -> HashMap<String, Balance> {
+            self.balances.clone()
+        }
+
+        fn update_order_status(&mut self, order: &mut Order) {
+            if let Some(reported) = self.orders.get(&order.order_id()) {
+                order.set_status(reported.status());
+                order.set_executed_quantity(reported.executed_quantity());
+                order.set_average_price(reported.average_price());
+            }
+        }
+
+        fn cancel(&mut self, order: &mut Order) {
+            if let Some(reported) = self.orders.get_mut(&order.order_id()) {
+                reported.cancel();
+            }
+
+            self.update_order_status(order);
         }
     }
-}
\ No newline at end of file
+
+    fn candle(time: u64, open: f64, high: f64, low: f64, close: f64) -> Candle {
+        Candle::new(time, time + 59_999, open, high, low, close, 1.0)
+    }
+
+    fn limit_buy(quantity: i64) -> OrderRequest {
+        let mut order_request = OrderRequest::new(
+            "BTC".to_string(),
+            "USDT".to_string(),
+            OrderSide::Buy,
+            TradeSide::LONG,
+            0,
+            None,
+        );
+
+        order_request.set_price(Decimal::from(100));
+        order_request.set_quantity(Decimal::from(quantity));
+
+        order_request
+    }
+
+    // buys at 100 with a stop loss at 95 and a take profit at 110
+    fn bracket(quantity: i64) -> OrderRequest {
+        let mut order_request = limit_buy(quantity);
+
+        order_request.attach_to_percentage_change(OrderType::Limit, -5.0);
+        order_request.attach_to_percentage_change(OrderType::Limit, 10.0);
+
+        order_request
+    }
+
+    fn statuses(order: &Order) -> Vec<OrderStatus> {
+        order.attachments()
+            .iter()
+            .map(|attachment| attachment.status())
+            .collect()
+    }
+
+    #[test]
+    fn submits_brackets_once_filled() {
+        let mut account = SimulatedAccount::new(PercentageFee::new(Decimal::ZERO));
+
+        account.set_amount("USDT", Decimal::from(1000));
+
+        let mut account_manager = AccountManager::new(account, 0, 60_000, 1.5);
+
+        let order_id = account_manager.submit_order(bracket(2)).unwrap().order_id();
+
+        assert!(account_manager.open_orders()[&order_id].attachments().iter().all(|attachment| attachment.is_dormant()));
+
+        account_manager.account_mut().accept_candle("BTCUSDT", &candle(0, 100.0, 101.0, 99.0, 100.0));
+
+        assert!(account_manager.update_open_orders().is_empty());
+
+        // both legs are live and lock the 2 BTC bought once
+        assert_eq!(statuses(&account_manager.open_orders()[&order_id]), vec![OrderStatus::New, OrderStatus::New]);
+        assert_eq!(account_manager.account().balance("BTC").unwrap().locked, Decimal::from(2));
+
+        account_manager.account_mut().accept_candle("BTCUSDT", &candle(60_000, 105.0, 111.0, 104.0, 110.0));
+
+        let finalized = account_manager.update_open_orders();
+
+        assert_eq!(finalized.len(), 1);
+        assert_eq!(statuses(&finalized[0]), vec![OrderStatus::Cancelled, OrderStatus::Filled]);
+
+        let btc = account_manager.account().balance("BTC").unwrap();
+
+        assert_eq!(btc.free + btc.locked, Decimal::ZERO);
+        assert_eq!(account_manager.account().balance("USDT").unwrap().free, Decimal::from(1020));
+    }
+
+    #[test]
+    fn sizes_brackets_to_partial_fills() {
+        let mut account_manager = AccountManager::new(Exchange::default(), 0, 60_000, 1.5);
+
+        account_manager.submit_order(bracket(2)).unwrap();
+
+        account_manager.account_mut().report("1", 1, OrderStatus::PartiallyFilled);
+
+        assert!(account_manager.update_open_orders().is_empty());
+        assert!(account_manager.open_orders()["1"].attachments().iter().all(|attachment| attachment.is_dormant()));
+
+        // the rest of the order is cancelled, the legs protect what filled
+        account_manager.account_mut().report("1", 1, OrderStatus::Cancelled);
+
+        assert!(account_manager.update_open_orders().is_empty());
+
+        let order = &account_manager.open_orders()["1"];
+
+        assert_eq!(statuses(order), vec![OrderStatus::New, OrderStatus::New]);
+        assert!(order.attachments().iter().all(|attachment| attachment.order_request.quantity() == Decimal::ONE));
+
+        // the exchange doesn't cancel the stop loss itself
+        account_manager.account_mut().report("3", 1, OrderStatus::Filled);
+
+        let finalized = account_manager.update_open_orders();
+
+        assert_eq!(finalized.len(), 1);
+        assert_eq!(statuses(&finalized[0]), vec![OrderStatus::Cancelled, OrderStatus::Filled]);
+    }
+
+    #[test]
+    fn rejects_brackets_the_account_does_not_answer_for() {
+        let exchange = Exchange { oco_legs: Some(1), ..Exchange::default() };
+
+        let mut account_manager = AccountManager::new(exchange, 0, 60_000, 1.5);
+
+        account_manager.submit_order(bracket(2)).unwrap();
+
+        account_manager.account_mut().report("1", 2, OrderStatus::Filled);
+
+        let finalized = account_manager.update_open_orders();
+
+        assert_eq!(finalized.len(), 1);
+        assert_eq!(statuses(&finalized[0]), vec![OrderStatus::Cancelled, OrderStatus::Cancelled]);
+
+        // the leg it did answer for isn't left open
+        assert_eq!(account_manager.account().orders["2"].status(), OrderStatus::Cancelled);
+    }
+
+    #[test]
+    fn expires_balances_per_symbol() {
+        let mut exchange = Exchange::default();
+
+        exchange.set_amount("BTC", 1);
+        exchange.set_amount("USDT", 100);
+
+        let mut account_manager = AccountManager::new(exchange, 1000, 10_000, 1.5);
+
+        assert!(account_manager.sync_balances(1));
+        assert!(!account_manager.sync_balances(500));
+
+        for i in 0..3 {
+            account_manager.account_mut().set_amount("BTC", i + 2);
+            account_manager.refresh_balances(600 + i as u64);
+        }
+
+        assert_eq!(account_manager.frequently_updated_symbols(), vec!["BTC"]);
+        assert_eq!(account_manager.balance_expiration("BTC"), 250);
+
+        // BTC changes often and expires first, USDT is still valid
+        assert!(account_manager.balance_expired("BTC", 852));
+        assert!(!account_manager.balance_expired("USDT", 852));
+        assert!(!account_manager.sync_balance("USDT", 852));
+        assert!(account_manager.sync_balance("BTC", 852));
+        assert_eq!(account_manager.last_balance_sync(), 852);
+
+        account_manager.rate_limiter_mut().set_clock(|| 0, |_| {});
+        account_manager.rate_limiter_mut().set_max_delay(0);
+        account_manager.set_max_balance_updates(1);
+
+        assert!(account_manager.refresh_balances(900));
+
+        // over the limit, the cached balances are kept
+        assert!(account_manager.balances_expired(2000));
+        assert!(!account_manager.sync_balances(2000));
+        assert_eq!(account_manager.last_balance_sync(), 900);
+    }
+
+    #[test]
+    fn resubmits_what_is_left_of_stale_orders() {
+        let mut account_manager = AccountManager::new(Exchange::default(), 0, 60_000, 1.5);
+
+        account_manager.set_order_manager(OrderManager::new(0, 2, 3, 10.0, ResubmissionPrice::LastClose, 0.0));
+
+        account_manager.submit_order(limit_buy(2)).unwrap();
+
+        assert!(account_manager.resubmit_stale_orders(&candle(0, 100.0, 102.0, 100.0, 102.0), None).is_empty());
+        assert_eq!(account_manager.order_manager().unwrap().ticks_open("1"), 1);
+
+        // half of it fills before the cancellation reaches the exchange
+        account_manager.account_mut().report("1", 1, OrderStatus::PartiallyFilled);
+
+        let resubmitted = account_manager.resubmit_stale_orders(&candle(60_000, 102.0, 103.0, 102.0, 103.0), None);
+
+        assert_eq!(resubmitted.len(), 1);
+        assert_eq!(resubmitted[0].order_id(), "2");
+        assert_eq!(resubmitted[0].quantity(), Decimal::ONE);
+        assert_eq!(resubmitted[0].price(), Decimal::from(103));
+        assert_eq!(resubmitted[0].order_request.resubmitted_from(), Some("1"));
+        assert_eq!(resubmitted[0].order_request.resubmission_count(), 1);
+        assert_eq!(resubmitted[0].order_request.first_submission_price(), Decimal::from(100));
+
+        let original = &account_manager.open_orders()["1"];
+
+        assert!(original.is_cancelled());
+        assert_eq!(original.executed_quantity(), Decimal::ONE);
+        assert_eq!(account_manager.order_manager().unwrap().ticks_open("1"), 0);
+
+        // the replacement at 105 is rejected, the order is placed again at 103
+        account_manager.account_mut().reject_above = Some(Decimal::from(103));
+
+        for i in 2..4 {
+            assert!(account_manager.resubmit_stale_orders(&candle(i * 60_000, 105.0, 105.0, 105.0, 105.0), None).is_empty());
+        }
+
+        assert!(!account_manager.open_orders().contains_key("3"));
+
+        let placed_again = &account_manager.open_orders()["4"];
+
+        assert_eq!(placed_again.price(), Decimal::from(103));
+        assert_eq!(placed_again.quantity(), Decimal::ONE);
+        assert!(!placed_again.is_finalized());
+
+        account_manager.resubmit_stale_orders(&candle(240_000, 105.0, 105.0, 105.0, 105.0), None);
+
+        assert_eq!(account_manager.order_manager().unwrap().ticks_open("4"), 1);
+
+        account_manager.cancel_order("4");
+
+        assert_eq!(account_manager.order_manager().unwrap().ticks_open("4"), 0);
+    }
+
+    #[test]
+    fn calls_margin_and_liquidates_below_thresholds() {
+        let mut account = SimulatedAccount::new(PercentageFee::new(Decimal::ZERO));
+
+        account.set_amount("USDT", Decimal::from(1000));
+
+        let mut account_manager = AccountManager::new(account, 0, 60_000, 1.5);
+
+        let mut short = limit_buy(1);
+        short.side = OrderSide::Sell;
+        short.trade_side = TradeSide::SHORT;
+
+        account_manager.submit_order(short).unwrap();
+
+        let price = |account_manager: &mut AccountManager<SimulatedAccount>, time: u64, price: f64| {
+            account_manager.account_mut().accept_candle("BTCUSDT", &candle(time, price, price, price, price));
+            account_manager.update_latest_price("BTCUSDT", Decimal::from_f64(price, DEFAULT_SCALE).unwrap());
+            account_manager.refresh_balances(time);
+            account_manager.check_margin_levels(time)
+        };
+
+        // 150 of reserve for 1 BTC sold at 100
+        assert!(price(&mut account_manager, 0, 100.0).is_empty());
+        account_manager.update_open_orders();
+
+        let mut cover = limit_buy(1);
+        cover.trade_side = TradeSide::SHORT;
+        cover.set_price(Decimal::from(80));
+
+        let cover_id = account_manager.submit_order(cover).unwrap().order_id();
+
+        // at the margin call level of 1.25
+        assert!(price(&mut account_manager, 60_000, 120.0).is_empty());
+
+        let events = price(&mut account_manager, 120_000, 125.0);
+
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], MarginEvent::MarginCall(_)));
+        assert!((events[0].position().margin_level() - 1.2).abs() < 1e-9);
+
+        // called once until it recovers
+        assert!(price(&mut account_manager, 180_000, 130.0).is_empty());
+        assert!(price(&mut account_manager, 240_000, 110.0).is_empty());
+        assert_eq!(price(&mut account_manager, 300_000, 125.0).len(), 1);
+
+        // below the maintenance level of 1.1
+        let events = price(&mut account_manager, 360_000, 140.0);
+
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], MarginEvent::Liquidation(_, _)));
+        assert!(account_manager.margin_positions().is_empty());
+        assert_eq!(account_manager.balance("BTC").unwrap().shorted, Decimal::ZERO);
+
+        // the cover would have reopened a position once reached
+        assert!(account_manager.open_orders()[&cover_id].is_cancelled());
+        assert_eq!(account_manager.update_open_orders().len(), 1);
+        assert!(price(&mut account_manager, 420_000, 70.0).is_empty());
+        assert!(account_manager.balance("BTC").unwrap().free.is_zero());
+    }
+}
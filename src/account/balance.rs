@@ -1,91 +1,200 @@
 use std::collections::{HashMap, HashSet};
+use crate::decimal::{Decimal, DEFAULT_SCALE};
 
+#[derive(Clone, Debug)]
 pub struct Balance {
     symbol: String,
 
-    pub free: f64,
-    pub locked: f64,
-    pub shorted: f64,
+    // number of decimals amounts of this asset are kept at
+    scale: u32,
+
+    pub free: Decimal,
+    pub locked: Decimal,
+    pub shorted: Decimal,
 
     pub trading_locked: bool,
 
-    pub margin_reserves: HashMap<String, f64>,
+    pub margin_reserves: HashMap<String, Decimal>,
     pub shorted_asset_symbols: HashSet<String>,
 
+    // margin assigned to the futures positions settled in this asset, by
+    // symbol, and their unrealized profit or loss at the mark price
+    pub position_margins: HashMap<String, Decimal>,
+    pub unrealized_profit_loss: Decimal,
+
     balance_update_counts: u64,
 }
 
 impl Balance {
-    fn new(symbol: String) -> Balance {
+    pub fn new(symbol: String) -> Balance {
+        Balance::with_scale(
+            symbol,
+            DEFAULT_SCALE,
+        )
+    }
+
+    pub fn with_scale(symbol: String, scale: u32) -> Balance {
         Balance {
             symbol,
 
-            free: 0.0,
-            locked: 0.0,
-            shorted: 0.0,
+            scale,
+
+            free: Decimal::ZERO,
+            locked: Decimal::ZERO,
+            shorted: Decimal::ZERO,
 
             trading_locked: false,
 
             margin_reserves: HashMap::new(),
             shorted_asset_symbols: HashSet::new(),
 
+            position_margins: HashMap::new(),
+            unrealized_profit_loss: Decimal::ZERO,
+
             balance_update_counts: 0,
         }
     }
 
-    fn free(
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn balance_update_counts(&self) -> u64 {
+        self.balance_update_counts
+    }
+
+    pub fn set_balance_update_counts(&mut self, balance_update_counts: u64) {
+        self.balance_update_counts = balance_update_counts;
+    }
+
+    /// True if the amounts held differ from another balance of the asset.
+    pub fn has_changed(&self, other: &Balance) -> bool {
+        self.free != other.free
+            || self.locked != other.locked
+            || self.shorted != other.shorted
+            || self.margin_reserves != other.margin_reserves
+            || self.position_margins != other.position_margins
+    }
+
+    pub fn free(
         &mut self,
-        free: f64,
+        free: Decimal,
     ) {
-        self.free = free;
+        self.free = free.truncate(self.scale);
     }
 
-    fn locked(
+    pub fn locked(
         &mut self,
-        locked: f64,
+        locked: Decimal,
     ) {
-        self.locked = locked;
+        self.locked = locked.truncate(self.scale);
     }
 
-    fn shorted(
+    pub fn shorted(
         &mut self,
-        shorted: f64,
+        shorted: Decimal,
     ) {
-        self.shorted = shorted;
+        self.shorted = shorted.truncate(self.scale);
     }
 
-    fn total(
+    pub fn total(
         &self,
-    ) -> f64 {
+    ) -> Decimal {
         self.free + self.locked
     }
 
-    fn margin_reserve(
+    pub fn margin_reserve(
         &mut self,
         asset_symbol: String,
-        margin_reserve: f64,
+        margin_reserve: Decimal,
     ) {
-        if margin_reserve <= 0.0 {
+        if !margin_reserve.is_positive() {
             self.margin_reserves.remove(
                 &asset_symbol,
             );
         } else {
             self.margin_reserves.insert(
                 asset_symbol,
-                margin_reserve,
+                margin_reserve.truncate(self.scale),
             );
         }
     }
 
-    fn lock_trading(
+    pub fn position_margin(
         &mut self,
+        symbol: String,
+        position_margin: Decimal,
     ) {
-        self.trading_locked = true;
+        if !position_margin.is_positive() {
+            self.position_margins.remove(
+                &symbol,
+            );
+        } else {
+            self.position_margins.insert(
+                symbol,
+                position_margin.truncate(self.scale),
+            );
+        }
+    }
+
+    pub fn unrealized_profit_loss(
+        &mut self,
+        unrealized_profit_loss: Decimal,
+    ) {
+        self.unrealized_profit_loss = unrealized_profit_loss.truncate(self.scale);
+    }
+
+    /// Total plus the margin of futures positions and their unrealized
+    /// profit or loss.
+    pub fn margin_balance(
+        &self,
+    ) -> Decimal {
+        self.total()
+            + self.position_margins.values().copied().sum::<Decimal>()
+            + self.unrealized_profit_loss
+    }
+
+    /// What the balance is worth in its own asset: the margin balance plus
+    /// the margin reserves of short sells, minus what was borrowed.
+    pub fn net_total(
+        &self,
+    ) -> Decimal {
+        self.margin_balance()
+            + self.margin_reserves.values().copied().sum::<Decimal>()
+            - self.shorted
     }
 
-    fn unlock_trading(
+    pub fn lock_trading(
         &mut self,
     ) {
         self.trading_locked = true;
     }
+
+    pub fn unlock_trading(
+        &mut self,
+    ) {
+        self.trading_locked = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn never_rounds_amounts_up() {
+        let mut balance = Balance::with_scale("BTC".to_string(), 2);
+
+        balance.free("0.129".parse().unwrap());
+        balance.shorted("0.125".parse().unwrap());
+        balance.unrealized_profit_loss("-0.125".parse().unwrap());
+
+        assert_eq!(balance.free, "0.12".parse().unwrap());
+        assert_eq!(balance.shorted, "0.12".parse().unwrap());
+        assert_eq!(balance.unrealized_profit_loss, "-0.12".parse().unwrap());
+    }
 }
@@ -1 +1,89 @@
-pub struct Context {}
+use std::collections::{HashMap, VecDeque};
+use crate::account::balance::Balance;
+use crate::account::orderbook::OrderBook;
+use crate::account::symbol_info::SymbolInfo;
+use crate::account::trade::Trade;
+use crate::account::trader::Trader;
+use crate::candles::candle::Candle;
+use crate::candles::candle_history::CandleHistory;
+
+/// What strategies and monitors can look at besides the candle they are
+/// given: the trader of the symbol and its open trades, the balances of
+/// the account, the rules and latest order book of the symbol and the
+/// recent candles.
+pub struct Context<'a> {
+    trader: &'a Trader,
+
+    balances: &'a HashMap<String, Balance>,
+
+    symbol_info: Option<&'a SymbolInfo>,
+    order_book: Option<&'a OrderBook>,
+
+    candle_history: &'a CandleHistory,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(
+        trader: &'a Trader,
+        balances: &'a HashMap<String, Balance>,
+        symbol_info: Option<&'a SymbolInfo>,
+        order_book: Option<&'a OrderBook>,
+        candle_history: &'a CandleHistory,
+    ) -> Context<'a> {
+        Context {
+            trader,
+
+            balances,
+
+            symbol_info,
+            order_book,
+
+            candle_history,
+        }
+    }
+
+    pub fn trader(&self) -> &Trader {
+        self.trader
+    }
+
+    pub fn open_trades(&self) -> &Vec<Trade> {
+        self.trader.trades()
+    }
+
+    pub fn balances(&self) -> &HashMap<String, Balance> {
+        self.balances
+    }
+
+    pub fn balance(&self, symbol: &str) -> Option<&Balance> {
+        self.balances.get(symbol)
+    }
+
+    pub fn assets(&self) -> Option<&Balance> {
+        self.balance(self.trader.trading_manager().assets_symbol())
+    }
+
+    pub fn funds(&self) -> Option<&Balance> {
+        self.balance(self.trader.trading_manager().funds_symbol())
+    }
+
+    pub fn symbol_info(&self) -> Option<&SymbolInfo> {
+        self.symbol_info
+    }
+
+    pub fn order_book(&self) -> Option<&OrderBook> {
+        self.order_book
+    }
+
+    pub fn candle_history(&self) -> &CandleHistory {
+        self.candle_history
+    }
+
+    pub fn latest_candle(&self) -> Option<&Candle> {
+        self.candle_history.latest()
+    }
+
+    /// Closed candles of an interval added to the history, oldest first.
+    pub fn candles(&self, interval: u64) -> Option<&VecDeque<Candle>> {
+        self.candle_history.window(interval)
+    }
+}
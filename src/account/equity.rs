@@ -0,0 +1,192 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::decimal::Decimal;
+
+/// Markets the account knows the latest price of, used to value assets in a
+/// reference asset they may not trade against directly.
+#[derive(Clone, Debug, Default)]
+pub struct PriceGraph {
+    // symbol -> (assets symbol, funds symbol)
+    markets: HashMap<String, (String, String)>,
+}
+
+impl PriceGraph {
+    pub fn new() -> PriceGraph {
+        PriceGraph {
+            markets: HashMap::new(),
+        }
+    }
+
+    pub fn add_market(&mut self, assets_symbol: &str, funds_symbol: &str) {
+        self.markets.insert(
+            format!("{}{}", assets_symbol, funds_symbol),
+            (assets_symbol.to_string(), funds_symbol.to_string()),
+        );
+    }
+
+    pub fn markets(&self) -> &HashMap<String, (String, String)> {
+        &self.markets
+    }
+
+    /// Converts an amount of `from` into `to` through the shortest chain of
+    /// markets with a price, e.g. ETH to USDT through ETHBTC and BTCUSDT
+    /// when there is no ETHUSDT price.
+    pub fn convert(
+        &self,
+        amount: Decimal,
+        from: &str,
+        to: &str,
+        prices: &HashMap<String, Decimal>,
+    ) -> Option<Decimal> {
+        if from == to {
+            return Some(amount);
+        }
+
+        // asset -> (neighbour, symbol, true if the asset is the base)
+        let mut edges: HashMap<&str, Vec<(&str, &str, bool)>> = HashMap::new();
+
+        let mut symbols: Vec<&String> = self.markets.keys().collect();
+
+        symbols.sort();
+
+        for symbol in symbols {
+            match prices.get(symbol) {
+                Some(price) if price.is_positive() => (),
+                _ => continue,
+            }
+
+            let (assets_symbol, funds_symbol) = &self.markets[symbol];
+
+            edges.entry(assets_symbol.as_str())
+                .or_default()
+                .push((funds_symbol.as_str(), symbol.as_str(), true));
+
+            edges.entry(funds_symbol.as_str())
+                .or_default()
+                .push((assets_symbol.as_str(), symbol.as_str(), false));
+        }
+
+        let mut visited: HashSet<&str> = HashSet::new();
+        let mut queue: VecDeque<(&str, Decimal)> = VecDeque::new();
+
+        visited.insert(from);
+        queue.push_back((from, amount));
+
+        while let Some((asset, amount)) = queue.pop_front() {
+            for (neighbour, symbol, is_base) in edges.get(asset).into_iter().flatten() {
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+
+                let price = prices[*symbol];
+
+                let converted = if *is_base {
+                    amount * price
+                } else {
+                    amount / price
+                };
+
+                if *neighbour == to {
+                    return Some(converted);
+                }
+
+                queue.push_back((neighbour, converted));
+            }
+        }
+
+        None
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EquityPoint {
+    pub time: u64,
+    pub equity: Decimal,
+}
+
+/// Equity of the account over time, in its reference asset.
+#[derive(Clone, Debug)]
+pub struct EquityCurve {
+    reference_symbol: String,
+    points: Vec<EquityPoint>,
+}
+
+impl EquityCurve {
+    pub fn new(reference_symbol: String) -> EquityCurve {
+        EquityCurve {
+            reference_symbol,
+            points: vec![],
+        }
+    }
+
+    pub fn reference_symbol(&self) -> &str {
+        &self.reference_symbol
+    }
+
+    pub fn points(&self) -> &Vec<EquityPoint> {
+        &self.points
+    }
+
+    pub fn last(&self) -> Option<&EquityPoint> {
+        self.points.last()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Adds a point, replacing the last one if it has the same time, e.g.
+    /// when several symbols close a candle at once.
+    pub fn record(&mut self, time: u64, equity: Decimal) {
+        if let Some(last) = self.points.last_mut() {
+            if last.time == time {
+                last.equity = equity;
+
+                return;
+            }
+        }
+
+        self.points.push(EquityPoint { time, equity });
+    }
+
+    /// Return of each point over the previous one, as a ratio.
+    pub fn returns(&self) -> Vec<f64> {
+        self.points
+            .windows(2)
+            .map(|pair| {
+                let previous = pair[0].equity.to_f64();
+
+                if previous == 0.0 {
+                    0.0
+                } else {
+                    pair[1].equity.to_f64() / previous - 1.0
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn converts_through_intermediate_markets() {
+        let mut price_graph = PriceGraph::new();
+
+        price_graph.add_market("ETH", "BTC");
+        price_graph.add_market("BTC", "USDT");
+
+        let mut prices = HashMap::new();
+
+        prices.insert("ETHBTC".to_string(), d("0.05"));
+        prices.insert("BTCUSDT".to_string(), d("40000"));
+
+        assert_eq!(price_graph.convert(d("2"), "ETH", "USDT", &prices), Some(d("4000")));
+        assert_eq!(price_graph.convert(d("4000"), "USDT", "ETH", &prices), Some(d("2")));
+        assert_eq!(price_graph.convert(d("1"), "ETH", "EUR", &prices), None);
+    }
+}
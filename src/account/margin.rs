@@ -0,0 +1,95 @@
+use crate::account::order::Order;
+use crate::decimal::Decimal;
+
+/// State of a short position: the borrowed quantity of an asset against the
+/// margin reserve kept for it in the funds balance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MarginPosition {
+    assets_symbol: String,
+    funds_symbol: String,
+
+    shorted: Decimal,
+    price: Decimal,
+
+    margin_reserve: Decimal,
+
+    // margin reserve over the current value of the shorted quantity
+    margin_level: f64,
+}
+
+impl MarginPosition {
+    pub fn new(
+        assets_symbol: String,
+        funds_symbol: String,
+        shorted: Decimal,
+        price: Decimal,
+        margin_reserve: Decimal,
+    ) -> MarginPosition {
+        let value = shorted * price;
+
+        let margin_level = if value.is_positive() {
+            (margin_reserve / value).to_f64()
+        } else {
+            f64::INFINITY
+        };
+
+        MarginPosition {
+            assets_symbol,
+            funds_symbol,
+
+            shorted,
+            price,
+
+            margin_reserve,
+
+            margin_level,
+        }
+    }
+
+    pub fn assets_symbol(&self) -> &str {
+        &self.assets_symbol
+    }
+
+    pub fn funds_symbol(&self) -> &str {
+        &self.funds_symbol
+    }
+
+    pub fn symbol(&self) -> String {
+        format!("{}{}", self.assets_symbol, self.funds_symbol)
+    }
+
+    pub fn shorted(&self) -> Decimal {
+        self.shorted
+    }
+
+    pub fn price(&self) -> Decimal {
+        self.price
+    }
+
+    pub fn margin_reserve(&self) -> Decimal {
+        self.margin_reserve
+    }
+
+    pub fn margin_level(&self) -> f64 {
+        self.margin_level
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum MarginEvent {
+    /// The margin level of the position dropped below the margin call level.
+    MarginCall(MarginPosition),
+
+    /// The position dropped below the maintenance level and was closed with
+    /// the given order.
+    Liquidation(MarginPosition, Box<Order>),
+}
+
+impl MarginEvent {
+    pub fn position(&self) -> &MarginPosition {
+        match self {
+            MarginEvent::MarginCall(position) => position,
+            MarginEvent::Liquidation(position, _) => position,
+        }
+    }
+}
@@ -2,9 +2,19 @@ pub mod account_manager;
 pub mod balance;
 pub mod client;
 pub mod context;
+pub mod equity;
+pub mod margin;
 pub mod order;
+pub mod order_manager;
 pub mod order_request;
 pub mod orderbook;
+pub mod position;
+pub mod position_sizing;
+pub mod rate_limiter;
+pub mod strategy_runner;
+pub mod symbol_info;
 pub mod trade;
+pub mod trade_export;
+pub mod trade_switch;
 pub mod trader;
 pub mod trading_manager;
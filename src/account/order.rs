@@ -2,6 +2,7 @@ use crate::account::order_request::OrderRequest;
 use crate::account::trade::{Trade, TradeSide};
 use std::cmp::Ordering;
 use std::time::{SystemTime, UNIX_EPOCH};
+use crate::decimal::Decimal;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum OrderSide {
@@ -33,6 +34,7 @@ pub enum TriggerCondition {
 }
 
 impl TriggerCondition {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<TriggerCondition> {
         match s {
             "SL" => Some(TriggerCondition::StopLoss),
@@ -50,7 +52,7 @@ impl TriggerCondition {
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct Order {
     pub order_request: OrderRequest,
 
@@ -61,18 +63,18 @@ pub struct Order {
 
     pub status: OrderStatus,
 
-    pub executed_quantity: f64,
-    pub fees_paid: f64,
-    pub average_price: f64,
+    pub executed_quantity: Decimal,
+    pub fees_paid: Decimal,
+    pub average_price: Decimal,
 
     pub attachments: Vec<Order>,
 
-    pub parent: Option<Order>,
+    pub parent: Option<Box<Order>>,
 
-    pub partial_fill_price: f64,
-    pub partial_fill_quantity: f64,
+    pub partial_fill_price: Decimal,
+    pub partial_fill_quantity: Decimal,
 
-    pub trade: Option<Trade>,
+    pub trade_id: Option<u64>,
 }
 
 impl Order {
@@ -102,14 +104,14 @@ impl Order {
             order_id: format!("{}", id),
             processed: false,
             status: OrderStatus::New,
-            executed_quantity: 0.0,
-            fees_paid: 0.0,
-            average_price: 0.0,
+            executed_quantity: Decimal::ZERO,
+            fees_paid: Decimal::ZERO,
+            average_price: Decimal::ZERO,
             attachments: vec![],
             parent: None,
-            partial_fill_price: 0.0,
-            partial_fill_quantity: 0.0,
-            trade: None,
+            partial_fill_price: Decimal::ZERO,
+            partial_fill_quantity: Decimal::ZERO,
+            trade_id: None,
         }
     }
 
@@ -117,14 +119,18 @@ impl Order {
         id: u64,
         order_request: OrderRequest,
     ) -> Order {
-        Order::new(
+        let mut order = Order::new(
             id,
             order_request.assets_symbol(),
             order_request.funds_symbol(),
-            order_request.side().clone(),
-            order_request.trade_side().clone(),
+            *order_request.side(),
+            *order_request.trade_side(),
             order_request.time(),
-        )
+        );
+
+        order.order_request = order_request;
+
+        order
     }
 
     pub fn order_id(&self) -> String {
@@ -135,23 +141,27 @@ impl Order {
         self.order_id = order_id;
     }
 
-    pub fn executed_quantity(&self) -> f64 {
-        self.executed_quantity.clone()
+    pub fn executed_quantity(&self) -> Decimal {
+        self.executed_quantity
     }
 
-    pub fn set_executed_quantity(&mut self, executed_quantity: f64) {
+    pub fn set_executed_quantity(&mut self, executed_quantity: Decimal) {
         self.executed_quantity = executed_quantity;
     }
 
-    pub fn total_order_amount_at_average_price(&self) -> f64 {
-        if self.average_price {
-            self.get_price() * self.get_quantity()
+    pub fn total_order_amount_at_average_price(&self) -> Decimal {
+        if self.average_price.is_zero() {
+            self.price() * self.quantity()
         } else {
-            self.averagePrice * self.get_quantity()
+            self.average_price * self.quantity()
         }
     }
 
-    pub fn set_price(&mut self, price: f64) {
+    pub fn price(&self) -> Decimal {
+        self.order_request.price()
+    }
+
+    pub fn set_price(&mut self, price: Decimal) {
         self.order_request
             .set_price(
                 price,
@@ -159,7 +169,7 @@ impl Order {
     }
 
     pub fn status(&self) -> OrderStatus {
-        self.status.clone()
+        self.status
     }
 
     pub fn set_status(&mut self, status: OrderStatus) {
@@ -178,19 +188,19 @@ impl Order {
         self.status == OrderStatus::Cancelled
     }
 
-    pub fn fees_paid(&self) -> f64 {
+    pub fn fees_paid(&self) -> Decimal {
         self.fees_paid
     }
 
-    pub fn set_fees_paid(&mut self, fees_paid: f64) {
+    pub fn set_fees_paid(&mut self, fees_paid: Decimal) {
         self.fees_paid = fees_paid;
     }
 
-    pub fn average_price(&self) -> f64 {
+    pub fn average_price(&self) -> Decimal {
         self.average_price
     }
 
-    pub fn set_average_price(&mut self, average_price: f64) {
+    pub fn set_average_price(&mut self, average_price: Decimal) {
         self.average_price = average_price;
     }
 
@@ -198,27 +208,60 @@ impl Order {
         &self.attachments
     }
 
-    pub fn parent(&self) -> &Option<Order> {
-        &self.parent
+    pub fn attachments_mut(&mut self) -> &mut Vec<Order> {
+        &mut self.attachments
     }
 
-    pub fn parent_order_id(&self) -> Option<String> {
-        match &self.parent {
-            Some(parent) => Some(parent.order_id()),
-            _ => None,
+    /// Attaches a child order (take profit or stop loss) to this order. The
+    /// child keeps a snapshot of its parent, which is refreshed by
+    /// `update_attachments` whenever the parent changes.
+    pub fn attach(&mut self, mut attachment: Order) {
+        attachment.parent = Some(Box::new(self.snapshot()));
+
+        self.attachments.push(attachment);
+    }
+
+    pub fn update_attachments(&mut self) {
+        let snapshot = self.snapshot();
+
+        for attachment in self.attachments.iter_mut() {
+            attachment.parent = Some(Box::new(snapshot.clone()));
         }
     }
 
-    pub fn quantity(&self) -> f64 {
-        let out = self.order_request.quantity();
+    fn snapshot(&self) -> Order {
+        let mut snapshot = self.clone();
+
+        snapshot.attachments = vec![];
+        snapshot.parent = None;
+
+        snapshot
+    }
+
+    /// An attachment is dormant until its parent fills and it is submitted.
+    pub fn is_dormant(&self) -> bool {
+        !self.order_request.is_active()
+            && !self.order_request.is_cancelled()
+    }
+
+    pub fn parent(&self) -> Option<&Order> {
+        self.parent.as_deref()
+    }
+
+    pub fn parent_order_id(&self) -> Option<String> {
+        self.parent
+            .as_ref()
+            .map(|parent| parent.order_id())
+    }
 
-        if self.parent.is_some() {
-            let parent = self.parent.unwrap();
+    pub fn quantity(&self) -> Decimal {
+        let out = self.order_request.quantity();
 
-            if parent.is_finalized() {
-                let p = self.executed_quantity();
+        if let Some(parent) = &self.parent {
+            if parent.is_finalized() || parent.status() == OrderStatus::PartiallyFilled {
+                let p = parent.executed_quantity();
 
-                if out > p || p == 0.0 {
+                if out > p || p.is_zero() {
                     return p;
                 }
             }
@@ -227,34 +270,33 @@ impl Order {
         out
     }
 
-    pub fn set_quantity(&mut self, quantity: f64) {
+    pub fn set_quantity(&mut self, quantity: Decimal) {
         self.order_request.set_quantity(quantity);
     }
 
     pub fn has_partial_fill_details(&self) -> bool {
-        self.partial_fill_quantity != 0.0
-            && self.partial_fill_quantity > 0.0
+        self.partial_fill_quantity.is_positive()
     }
 
     pub fn clear_partial_fill_details(&mut self) {
-        self.partial_fill_quantity = 0.0;
-        self.partial_fill_price = 0.0;
+        self.partial_fill_quantity = Decimal::ZERO;
+        self.partial_fill_price = Decimal::ZERO;
     }
 
-    pub fn get_partial_fill_total_price(&self) -> f64 {
+    pub fn get_partial_fill_total_price(&self) -> Decimal {
         self.partial_fill_quantity
             * self.partial_fill_price
     }
 
-    pub fn get_partial_fill_price(&self) -> f64 {
+    pub fn get_partial_fill_price(&self) -> Decimal {
         self.partial_fill_price
     }
 
-    pub fn get_partial_fill_quantity(&self) -> f64 {
+    pub fn get_partial_fill_quantity(&self) -> Decimal {
         self.partial_fill_quantity
     }
 
-    pub fn set_partial_fill_details(&mut self, fill_price: f64, filled_quantity: f64) {
+    pub fn set_partial_fill_details(&mut self, fill_price: Decimal, filled_quantity: Decimal) {
         self.partial_fill_price = fill_price;
         self.partial_fill_quantity = filled_quantity;
     }
@@ -264,11 +306,11 @@ impl Order {
     }
 
     pub fn set_trade(&mut self, trade: &Trade) {
-        self.trade = Some(trade.clone());
+        self.trade_id = Some(trade.id());
     }
 
-    pub fn trade(&self) -> Option<&Trade> {
-        self.trade.as_ref()
+    pub fn trade_id(&self) -> Option<u64> {
+        self.trade_id
     }
 
     pub fn get_time_elapsed(&self) -> u64 {
@@ -292,12 +334,12 @@ impl Order {
         now - close_time
     }
 
-    pub fn get_remaining_quantity(&self) -> f64 {
+    pub fn get_remaining_quantity(&self) -> Decimal {
         self.quantity() - self.executed_quantity()
     }
 
-    pub fn get_total_traded_amount(&self) -> f64 {
-        self.executed_quantity() * self.get_price()
+    pub fn get_total_traded_amount(&self) -> Decimal {
+        self.executed_quantity() * self.price()
     }
 
     pub fn is_finalized(&self) -> bool {
@@ -306,11 +348,11 @@ impl Order {
     }
 
     pub fn get_filled_ratio(&self) -> f64 {
-        if self.quantity() == 0.0 {
+        if self.quantity().is_zero() {
             return 0.0;
         }
 
-        self.executed_quantity() / self.quantity()
+        (self.executed_quantity() / self.quantity()).to_f64()
     }
 
     pub fn side_description(&self) -> String {
@@ -332,11 +374,17 @@ impl PartialEq for Order {
     }
 }
 
+impl Eq for Order {}
+
 impl PartialOrd for Order {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Option::from(
-            self.id
-                .cmp(&other.id),
-        )
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Order {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id
+            .cmp(&other.id)
     }
 }
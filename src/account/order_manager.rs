@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use crate::account::order::{Order, OrderType};
+use crate::account::orderbook::OrderBook;
+use crate::candles::candle::Candle;
+use crate::decimal::{Decimal, DEFAULT_SCALE};
+
+/// Where the price of a resubmitted order comes from.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ResubmissionPrice {
+    /// Best bid for buys and best ask for sells, falling back to the last
+    /// close when no order book is available.
+    BestBidAsk,
+    LastClose,
+}
+
+/// Decides when an unfilled limit order has been left open for too long and
+/// at which price the remaining quantity should be resubmitted.
+///
+/// Times are in milliseconds, same as candle times.
+pub struct OrderManager {
+    max_time_open: u64,
+    max_ticks_open: u64,
+
+    max_retries: u32,
+
+    // percentage away from the price of the first submission
+    max_price_deviation: f64,
+
+    price_source: ResubmissionPrice,
+
+    // percentage added to buys and taken from sells
+    price_offset: f64,
+
+    ticks: HashMap<String, u64>,
+}
+
+impl OrderManager {
+    pub fn new(
+        max_time_open: u64,
+        max_ticks_open: u64,
+        max_retries: u32,
+        max_price_deviation: f64,
+        price_source: ResubmissionPrice,
+        price_offset: f64,
+    ) -> OrderManager {
+        OrderManager {
+            max_time_open,
+            max_ticks_open,
+
+            max_retries,
+            max_price_deviation,
+
+            price_source,
+            price_offset,
+
+            ticks: HashMap::new(),
+        }
+    }
+
+    pub fn max_time_open(&self) -> u64 {
+        self.max_time_open
+    }
+
+    pub fn max_ticks_open(&self) -> u64 {
+        self.max_ticks_open
+    }
+
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    pub fn max_price_deviation(&self) -> f64 {
+        self.max_price_deviation
+    }
+
+    pub fn price_source(&self) -> ResubmissionPrice {
+        self.price_source
+    }
+
+    pub fn price_offset(&self) -> f64 {
+        self.price_offset
+    }
+
+    /// Ticks counted so far for an open order.
+    pub fn ticks_open(&self, order_id: &str) -> u64 {
+        self.ticks
+            .get(order_id)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Counts one more tick for the order and returns true if it has been
+    /// open for longer than allowed, either in time or in ticks.
+    pub fn is_stale(
+        &mut self,
+        order: &Order,
+        candle: &Candle,
+    ) -> bool {
+        if order.is_finalized() || *order.order_request.order_type() != OrderType::Limit {
+            self.ticks.remove(&order.order_id());
+
+            return false;
+        }
+
+        let ticks = self.ticks
+            .entry(order.order_id())
+            .or_insert(0);
+
+        *ticks += 1;
+
+        let elapsed = candle.close_time.saturating_sub(order.order_request.time());
+
+        (self.max_time_open > 0 && elapsed >= self.max_time_open)
+            || (self.max_ticks_open > 0 && *ticks >= self.max_ticks_open)
+    }
+
+    /// Price to resubmit the order at, or `None` when the order should not
+    /// be chased anymore.
+    pub fn resubmission_price(
+        &self,
+        order: &Order,
+        candle: &Candle,
+        order_book: Option<&OrderBook>,
+    ) -> Option<Decimal> {
+        if order.order_request.resubmission_count() >= self.max_retries {
+            return None;
+        }
+
+        let book_price = order_book.and_then(|order_book| {
+            if order.order_request.is_buy() {
+                order_book.best_bid()
+            } else {
+                order_book.best_ask()
+            }
+        });
+
+        let close = Decimal::from_f64(candle.close, DEFAULT_SCALE)?;
+
+        let reference = match self.price_source {
+            ResubmissionPrice::BestBidAsk => book_price.unwrap_or(close),
+            ResubmissionPrice::LastClose => close,
+        };
+
+        let offset = Decimal::from_f64(self.price_offset, DEFAULT_SCALE)? / Decimal::from(100);
+
+        let price = if order.order_request.is_buy() {
+            reference * (Decimal::ONE + offset)
+        } else {
+            reference * (Decimal::ONE - offset)
+        };
+
+        let original_price = order.order_request.first_submission_price();
+
+        if original_price.is_positive() {
+            let deviation = ((price / original_price) - Decimal::ONE).abs().to_f64() * 100.0;
+
+            if deviation > self.max_price_deviation {
+                return None;
+            }
+        }
+
+        Some(price)
+    }
+
+    pub fn order_finalized(&mut self, order: &Order) {
+        self.ticks.remove(&order.order_id());
+    }
+}
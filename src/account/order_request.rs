@@ -1,10 +1,9 @@
-use std::collections::{HashMap, HashSet};
-
 use super::order::{OrderSide, TriggerCondition};
 use super::trade::TradeSide;
 use crate::account::order::{OrderType, Order};
+use crate::decimal::{Decimal, DEFAULT_SCALE};
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct OrderRequest {
     pub assets_symbol: String,
     pub funds_symbol: String,
@@ -17,14 +16,18 @@ pub struct OrderRequest {
 
     pub time: u64,
 
-    pub price: f64,
-    pub quantity: f64,
+    pub price: Decimal,
+    pub quantity: Decimal,
 
     pub order_type: OrderType,
 
-    pub resubmitted_from: Option<Order>,
+    // id of the order this request replaces, the number of orders replaced
+    // before it and the price of the first of them
+    pub resubmitted_from: Option<String>,
+    pub resubmission_count: u32,
+    pub first_submission_price: Decimal,
 
-    pub trigger_price: f64,
+    pub trigger_price: Decimal,
     pub trigger_condition: TriggerCondition,
 
     pub attached_requests: Vec<OrderRequest>,
@@ -37,9 +40,9 @@ impl OrderRequest {
         side: OrderSide,
         trade_side: TradeSide,
         time: u64,
-        resubmitted_from: Option<Order>,
+        resubmitted_from: Option<&Order>,
     ) -> OrderRequest {
-        OrderRequest {
+        let mut order_request = OrderRequest {
             assets_symbol,
             funds_symbol,
 
@@ -51,18 +54,26 @@ impl OrderRequest {
             active: true,
             cancelled: false,
 
-            price: 0.0,
-            quantity: 0.0,
+            price: Decimal::ZERO,
+            quantity: Decimal::ZERO,
 
             order_type: OrderType::Limit,
 
-            trigger_price: 0.0,
+            trigger_price: Decimal::ZERO,
             trigger_condition: TriggerCondition::None,
 
             attached_requests: vec![],
 
-            resubmitted_from,
+            resubmitted_from: None,
+            resubmission_count: 0,
+            first_submission_price: Decimal::ZERO,
+        };
+
+        if let Some(order) = resubmitted_from {
+            order_request.set_resubmitted_from(order);
         }
+
+        order_request
     }
 
     pub fn assets_symbol(&self) -> &str {
@@ -73,23 +84,23 @@ impl OrderRequest {
         &self.funds_symbol
     }
 
-    pub fn symbol(&self) -> &str {
-        self.assets_symbol() + self.funds_symbol()
+    pub fn symbol(&self) -> String {
+        format!("{}{}", self.assets_symbol, self.funds_symbol)
     }
 
-    pub fn price(&self) -> f64 {
+    pub fn price(&self) -> Decimal {
         self.price
     }
 
-    pub fn set_price(&mut self, price: f64) {
+    pub fn set_price(&mut self, price: Decimal) {
         self.price = price;
     }
 
-    pub fn quantity(&self) -> f64 {
+    pub fn quantity(&self) -> Decimal {
         self.quantity
     }
 
-    pub fn set_quantity(&mut self, quantity: f64) {
+    pub fn set_quantity(&mut self, quantity: Decimal) {
         self.quantity = quantity;
     }
 
@@ -106,10 +117,10 @@ impl OrderRequest {
     }
 
     pub fn set_order_type(&mut self, order_type: OrderType) {
-        &self.order_type = *order_type
+        self.order_type = order_type;
     }
 
-    pub fn total_order_amount(&self) -> f64 {
+    pub fn total_order_amount(&self) -> Decimal {
         self.price * self.quantity
     }
 
@@ -118,7 +129,7 @@ impl OrderRequest {
     }
 
     pub fn is_cancelled(&self) -> bool {
-        self.cancelled == true
+        self.cancelled
     }
 
     pub fn cancel(&mut self) {
@@ -129,8 +140,28 @@ impl OrderRequest {
         self.resubmitted_from.is_some()
     }
 
-    pub fn orginal_order(&self) -> &Option<Order>{
-        &self.resubmitted_from
+    pub fn resubmitted_from(&self) -> Option<&str> {
+        self.resubmitted_from.as_deref()
+    }
+
+    /// Links the request to the order it replaces.
+    pub fn set_resubmitted_from(&mut self, order: &Order) {
+        self.resubmitted_from = Some(order.order_id());
+        self.resubmission_count = order.order_request.resubmission_count() + 1;
+        self.first_submission_price = order.order_request.first_submission_price();
+    }
+
+    pub fn resubmission_count(&self) -> u32 {
+        self.resubmission_count
+    }
+
+    /// Price of the first order in a chain of resubmissions.
+    pub fn first_submission_price(&self) -> Decimal {
+        if self.is_resubmission() {
+            self.first_submission_price
+        } else {
+            self.price
+        }
     }
 
     pub fn is_short(&self) -> bool {
@@ -171,32 +202,30 @@ impl OrderRequest {
 
     pub fn attached_order_requests(&self) -> Vec<OrderRequest> {
         self.attached_requests
-            .iter()
-            .cloned()
-            .collect()
+            .to_vec()
     }
 
     pub fn trigger_condition(&self) -> &TriggerCondition {
         &self.trigger_condition
     }
 
-    pub fn trigger_price(&self) -> f64 {
+    pub fn trigger_price(&self) -> Decimal {
         self.trigger_price
     }
 
     pub fn set_trigger_condition(
         &mut self,
         trigger_condition: TriggerCondition,
-        trigger_price: f64,
+        trigger_price: Decimal,
     ) {
         self.trigger_condition = trigger_condition;
         self.trigger_price = trigger_price;
 
         self.active =
             self.trigger_condition == TriggerCondition::None
-                || self.trigger_price == 0.0;
+                || self.trigger_price.is_zero();
 
-        if self.price == 0.0 && self.trigger_price != 0.0 {
+        if self.price.is_zero() && !self.trigger_price.is_zero() {
             self.price = trigger_price;
         }
     }
@@ -212,7 +241,7 @@ impl OrderRequest {
     pub fn attach_order_request(
         &mut self,
         order_type: OrderType,
-        price: f64,
+        price: Decimal,
     ) {
         let mut attachment = OrderRequest::new(
             self.assets_symbol.clone(),
@@ -221,7 +250,7 @@ impl OrderRequest {
                 OrderSide::Buy => OrderSide::Sell,
                 OrderSide::Sell => OrderSide::Buy,
             },
-            self.trade_side.clone(),
+            self.trade_side,
             self.time,
             None,
         );
@@ -240,14 +269,14 @@ impl OrderRequest {
             order_type,
         );
 
-        if diff < 0.0 {
+        if diff.is_negative() {
             attachment.set_trigger_condition(
                 TriggerCondition::StopLoss,
                 attachment.price(),
             );
         }
 
-        if diff >= 0.0 {
+        if !diff.is_negative() {
             attachment.set_trigger_condition(
                 TriggerCondition::StopGain,
                 attachment.price(),
@@ -264,16 +293,21 @@ impl OrderRequest {
         order_type: OrderType,
         percentage_change: f64,
     ) {
+        let percentage_change = match Decimal::from_f64(percentage_change, DEFAULT_SCALE) {
+            Some(percentage_change) => percentage_change,
+            None => return,
+        };
+
         self.attach_order_request(
             order_type,
-            self.price * (1.0 + (percentage_change / 100)),
+            self.price * (Decimal::ONE + percentage_change / Decimal::from(100)),
         );
     }
 
     pub fn attach_to_price_change(
         &mut self,
         order_type: OrderType,
-        price_change: f64,
+        price_change: Decimal,
     ) {
         self.attach_order_request(
             order_type,
@@ -1,20 +1,21 @@
-use std::collections::{BTreeMap, HashMap};
-use crate::client_account::ClientAccount;
+use std::collections::BTreeMap;
+use crate::decimal::Decimal;
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct OrderBook {
     symbol: String,
 
     depth: u64,
 
-    bids: BTreeMap<f64, f64>,
-    asks: BTreeMap<f64, f64>,
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
 
-    client_account: Box<dyn ClientAccount>,
+    // id of the last update of the exchange the book includes
+    last_update_id: u64,
 }
 
 impl OrderBook {
-    fn new(
-        client_account: impl ClientAccount,
+    pub fn new(
         symbol: String,
         depth: u64,
     ) -> OrderBook {
@@ -25,93 +26,94 @@ impl OrderBook {
             bids: BTreeMap::new(),
             asks: BTreeMap::new(),
 
-            client_account: Box::new(client_account)
+            last_update_id: 0,
         }
     }
 
-    fn add_bid(
+    pub fn add_bid(
         &mut self,
-        price: f64,
-        quantity: f64,
+        price: Decimal,
+        quantity: Decimal,
     ) {
-        self.bids.put(
+        self.bids.insert(
             price,
             quantity,
         );
 
-        while self.bids.size() > self.depth {
-            self.bids.remove(self.bids.lastKey());
+        // bids are kept from the highest price down, drop the lowest
+        while self.bids.len() as u64 > self.depth {
+            self.bids.pop_first();
         }
     }
 
-    fn add_ask(
+    pub fn add_ask(
         &mut self,
-        price: f64,
-        quantity: f64,
+        price: Decimal,
+        quantity: Decimal,
     ) {
-        self.asks.put(
+        self.asks.insert(
             price,
             quantity,
         );
 
-        while self.asks.size() > self.depth {
-            self.asks.remove(self.asks.firstKey());
+        // asks are kept from the lowest price up, drop the highest
+        while self.asks.len() as u64 > self.depth {
+            self.asks.pop_last();
         }
     }
 
-    fn get_average_ask_amount_by_depth(&self, depth: u64) -> f64 {
-        let mut total_amount = 0.0;
-        let mut total_quantity = 0.0;
+    pub fn remove_bid(&mut self, price: Decimal) {
+        self.bids.remove(&price);
+    }
 
-        let mut i = 0;
+    pub fn remove_ask(&mut self, price: Decimal) {
+        self.asks.remove(&price);
+    }
 
-        for (price, quantity) in self.asks.iter() {
-            if i >= depth {
-                break;
-            }
+    pub fn get_average_ask_amount_by_depth(&self, depth: u64) -> Decimal {
+        let mut total_amount = Decimal::ZERO;
+        let mut total_quantity = Decimal::ZERO;
 
-            total_amount += price * quantity;
-            total_quantity += quantity;
+        for (price, quantity) in self.asks.iter().take(depth as usize) {
+            total_amount += *price * *quantity;
+            total_quantity += *quantity;
+        }
 
-            i += 1;
+        if total_quantity.is_zero() {
+            return Decimal::ZERO;
         }
 
         total_amount / total_quantity
     }
 
-    fn get_average_bid_amount_by_depth(&self, depth: u64) -> f64 {
-        let mut total_amount = 0.0;
-        let mut total_quantity = 0.0;
-
-        let mut i = 0;
-
-        for (price, quantity) in self.bids.iter() {
-            if i >= depth {
-                break;
-            }
+    pub fn get_average_bid_amount_by_depth(&self, depth: u64) -> Decimal {
+        let mut total_amount = Decimal::ZERO;
+        let mut total_quantity = Decimal::ZERO;
 
-            total_amount += price * quantity;
-            total_quantity += quantity;
+        for (price, quantity) in self.bids.iter().rev().take(depth as usize) {
+            total_amount += *price * *quantity;
+            total_quantity += *quantity;
+        }
 
-            i += 1;
+        if total_quantity.is_zero() {
+            return Decimal::ZERO;
         }
 
         total_amount / total_quantity
     }
 
-    fn estimate_fill_price_asks(&self, quantity: f64) -> f64 {
-        let mut filled_quantity = 0.0;
-        let mut estimated_price = 0.0;
+    fn estimate_fill_price_asks(&self, quantity: Decimal) -> Decimal {
+        let mut filled_quantity = Decimal::ZERO;
+        let mut estimated_price = Decimal::ZERO;
 
         for (level_price, level_quantity) in self.asks.iter() {
             let quantity_to_fill = quantity - filled_quantity;
 
             if quantity_to_fill > *level_quantity {
-                filled_quantity += level_quantity;
-                estimated_price += level_price * level_quantity;
+                filled_quantity += *level_quantity;
+                estimated_price += *level_price * *level_quantity;
             } else {
-                filled_quantity += quantity_to_fill;
-                estimated_price += level_price * quantity_to_fill;
+                estimated_price += *level_price * quantity_to_fill;
                 break;
             }
         }
@@ -119,19 +121,18 @@ impl OrderBook {
         estimated_price
     }
 
-    fn estimate_fill_price_bids(&self, quantity: f64) -> f64 {
-        let mut filled_quantity = 0.0;
-        let mut estimated_price = 0.0;
+    fn estimate_fill_price_bids(&self, quantity: Decimal) -> Decimal {
+        let mut filled_quantity = Decimal::ZERO;
+        let mut estimated_price = Decimal::ZERO;
 
-        for (level_price, level_quantity) in self.bids.iter() {
+        for (level_price, level_quantity) in self.bids.iter().rev() {
             let quantity_to_fill = quantity - filled_quantity;
 
             if quantity_to_fill > *level_quantity {
-                filled_quantity += level_quantity;
-                estimated_price += level_price * level_quantity;
+                filled_quantity += *level_quantity;
+                estimated_price += *level_price * *level_quantity;
             } else {
-                filled_quantity += quantity_to_fill;
-                estimated_price += level_price * quantity_to_fill;
+                estimated_price += *level_price * quantity_to_fill;
                 break;
             }
         }
@@ -139,27 +140,41 @@ impl OrderBook {
         estimated_price
     }
 
-    pub fn get_average_bid_amount_by_quantity(&self, quantity_to_fill: f64) -> f64 {
+    pub fn get_average_bid_amount_by_quantity(&self, quantity_to_fill: Decimal) -> Decimal {
         self.estimate_fill_price_bids(quantity_to_fill)
     }
 
-    pub fn get_average_ask_amount_by_quantity(&self, quantity_to_fill: f64) -> f64 {
+    pub fn get_average_ask_amount_by_quantity(&self, quantity_to_fill: Decimal) -> Decimal {
         self.estimate_fill_price_asks(quantity_to_fill)
     }
 
-    pub fn get_spread_by_quantity(&self, quantity_to_fill: f64) -> f64 {
-        self.get_average_ask_amount(quantity_to_fill) - self.get_average_bid_amount(quantity_to_fill)
+    pub fn get_spread_by_quantity(&self, quantity_to_fill: Decimal) -> Decimal {
+        self.get_average_ask_amount_by_quantity(quantity_to_fill) - self.get_average_bid_amount_by_quantity(quantity_to_fill)
+    }
+
+    pub fn best_bid(&self) -> Option<Decimal> {
+        self.bids
+            .keys()
+            .next_back()
+            .copied()
+    }
+
+    pub fn best_ask(&self) -> Option<Decimal> {
+        self.asks
+            .keys()
+            .next()
+            .copied()
     }
 
     pub fn get_depth(&self) -> u64 {
         self.depth
     }
 
-    pub fn bids(&self) -> &BTreeMap<f64, f64> {
+    pub fn bids(&self) -> &BTreeMap<Decimal, Decimal> {
         &self.bids
     }
 
-    pub fn asks(&self) -> &BTreeMap<f64, f64> {
+    pub fn asks(&self) -> &BTreeMap<Decimal, Decimal> {
         &self.asks
     }
 
@@ -167,6 +182,14 @@ impl OrderBook {
         &self.symbol
     }
 
+    pub fn last_update_id(&self) -> u64 {
+        self.last_update_id
+    }
+
+    pub fn set_last_update_id(&mut self, last_update_id: u64) {
+        self.last_update_id = last_update_id;
+    }
+
     pub fn is_empty(&self) -> bool {
         self.bids.is_empty() && self.asks.is_empty()
     }
@@ -0,0 +1,310 @@
+use crate::decimal::Decimal;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AccountMode {
+    Spot,
+    Futures,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MarginMode {
+    /// Each position only risks the margin assigned to it.
+    Isolated,
+
+    /// Positions share the free balance of the funds asset as margin.
+    Cross,
+}
+
+/// Leveraged position on a perpetual futures contract, settled in the funds
+/// asset. The quantity is positive for longs and negative for shorts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Position {
+    assets_symbol: String,
+    funds_symbol: String,
+
+    quantity: Decimal,
+    entry_price: Decimal,
+
+    leverage: u32,
+    margin_mode: MarginMode,
+
+    // initial margin of the open quantity
+    margin: Decimal,
+
+    // funding paid (positive) or received (negative) since the position
+    // was opened
+    funding: Decimal,
+
+    realized_profit_loss: Decimal,
+}
+
+impl Position {
+    pub fn new(
+        assets_symbol: String,
+        funds_symbol: String,
+        leverage: u32,
+        margin_mode: MarginMode,
+    ) -> Position {
+        Position {
+            assets_symbol,
+            funds_symbol,
+
+            quantity: Decimal::ZERO,
+            entry_price: Decimal::ZERO,
+
+            leverage: leverage.max(1),
+            margin_mode,
+
+            margin: Decimal::ZERO,
+
+            funding: Decimal::ZERO,
+
+            realized_profit_loss: Decimal::ZERO,
+        }
+    }
+
+    pub fn assets_symbol(&self) -> &str {
+        &self.assets_symbol
+    }
+
+    pub fn funds_symbol(&self) -> &str {
+        &self.funds_symbol
+    }
+
+    pub fn symbol(&self) -> String {
+        format!("{}{}", self.assets_symbol, self.funds_symbol)
+    }
+
+    pub fn quantity(&self) -> Decimal {
+        self.quantity
+    }
+
+    pub fn entry_price(&self) -> Decimal {
+        self.entry_price
+    }
+
+    pub fn leverage(&self) -> u32 {
+        self.leverage
+    }
+
+    pub fn margin_mode(&self) -> MarginMode {
+        self.margin_mode
+    }
+
+    pub fn margin(&self) -> Decimal {
+        self.margin
+    }
+
+    pub fn funding(&self) -> Decimal {
+        self.funding
+    }
+
+    pub fn realized_profit_loss(&self) -> Decimal {
+        self.realized_profit_loss
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.quantity.is_zero()
+    }
+
+    pub fn is_long(&self) -> bool {
+        self.quantity.is_positive()
+    }
+
+    pub fn is_short(&self) -> bool {
+        self.quantity.is_negative()
+    }
+
+    pub fn notional(&self, price: Decimal) -> Decimal {
+        self.quantity.abs() * price
+    }
+
+    pub fn unrealized_profit_loss(&self, mark_price: Decimal) -> Decimal {
+        self.quantity * (mark_price - self.entry_price)
+    }
+
+    /// Margin needed to keep the position open, `maintenance_margin_rate`
+    /// being a percentage of the notional value.
+    pub fn maintenance_margin(&self, mark_price: Decimal, maintenance_margin_rate: Decimal) -> Decimal {
+        self.notional(mark_price) * maintenance_margin_rate / Decimal::from(100)
+    }
+
+    /// Margin of an isolated position once its unrealized profit or loss is
+    /// taken into account.
+    pub fn margin_balance(&self, mark_price: Decimal) -> Decimal {
+        self.margin + self.unrealized_profit_loss(mark_price)
+    }
+
+    /// Mark price at which an isolated position no longer covers its
+    /// maintenance margin.
+    pub fn liquidation_price(&self, maintenance_margin_rate: Decimal) -> Option<Decimal> {
+        if !self.is_open() {
+            return None;
+        }
+
+        let rate = maintenance_margin_rate / Decimal::from(100);
+        let quantity = self.quantity.abs();
+
+        // margin + q * (p - entry) = q * p * rate for longs, and
+        // margin + q * (entry - p) = q * p * rate for shorts
+        let price = if self.is_long() {
+            (self.entry_price * quantity - self.margin) / (quantity * (Decimal::ONE - rate))
+        } else {
+            (self.entry_price * quantity + self.margin) / (quantity * (Decimal::ONE + rate))
+        };
+
+        Some(price.max(Decimal::ZERO))
+    }
+
+    pub fn set_leverage(&mut self, leverage: u32) {
+        self.leverage = leverage.max(1);
+    }
+
+    pub fn set_margin_mode(&mut self, margin_mode: MarginMode) {
+        self.margin_mode = margin_mode;
+    }
+
+    pub fn add_margin(&mut self, amount: Decimal) {
+        self.margin += amount;
+    }
+
+    pub fn pay_funding(&mut self, amount: Decimal) {
+        self.funding += amount;
+
+        if self.margin_mode == MarginMode::Isolated {
+            self.margin -= amount;
+        }
+    }
+
+    /// Adds a fill to the position, `quantity` being positive for buys and
+    /// negative for sells. Returns the profit or loss realized by the part
+    /// of the fill that reduced the position, and the margin that part
+    /// released.
+    ///
+    /// A fill larger than the position closes it and opens one on the other
+    /// side with the remainder; its margin must be added separately.
+    pub fn fill(&mut self, quantity: Decimal, price: Decimal) -> (Decimal, Decimal) {
+        let same_side = self.quantity.is_zero()
+            || self.quantity.is_positive() == quantity.is_positive();
+
+        if same_side {
+            let total = self.quantity.abs() + quantity.abs();
+
+            self.entry_price = (self.entry_price * self.quantity.abs() + price * quantity.abs()) / total;
+            self.quantity += quantity;
+
+            return (Decimal::ZERO, Decimal::ZERO);
+        }
+
+        let closed = quantity.abs().min(self.quantity.abs());
+
+        let direction = if self.is_long() { Decimal::ONE } else { -Decimal::ONE };
+
+        let profit_loss = closed * (price - self.entry_price) * direction;
+        let released = self.margin * closed / self.quantity.abs();
+
+        self.realized_profit_loss += profit_loss;
+        self.margin -= released;
+
+        self.quantity += quantity;
+
+        if self.quantity.is_zero() {
+            self.entry_price = Decimal::ZERO;
+            self.margin = Decimal::ZERO;
+        } else if self.quantity.is_positive() == quantity.is_positive() {
+            // flipped to the other side
+            self.entry_price = price;
+            self.margin = Decimal::ZERO;
+        }
+
+        (profit_loss, released)
+    }
+}
+
+/// Funding exchanged between longs and shorts of a perpetual contract. A
+/// positive amount was paid by the position, a negative one received.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FundingPayment {
+    symbol: String,
+    time: u64,
+
+    rate: Decimal,
+    mark_price: Decimal,
+
+    amount: Decimal,
+}
+
+impl FundingPayment {
+    pub fn new(
+        symbol: String,
+        time: u64,
+        rate: Decimal,
+        mark_price: Decimal,
+        amount: Decimal,
+    ) -> FundingPayment {
+        FundingPayment {
+            symbol,
+            time,
+
+            rate,
+            mark_price,
+
+            amount,
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    pub fn rate(&self) -> Decimal {
+        self.rate
+    }
+
+    pub fn mark_price(&self) -> Decimal {
+        self.mark_price
+    }
+
+    pub fn amount(&self) -> Decimal {
+        self.amount
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn reduces_and_flips() {
+        let mut position = Position::new("BTC".to_string(), "USDT".to_string(), 10, MarginMode::Isolated);
+
+        position.fill(d("2"), d("100"));
+        position.add_margin(d("20"));
+
+        assert_eq!(position.fill(d("-1"), d("110")), (d("10"), d("10")));
+        assert_eq!(position.fill(d("-3"), d("90")), (d("-10"), d("10")));
+
+        assert!(position.is_short());
+        assert_eq!(position.quantity(), d("-2"));
+        assert_eq!(position.entry_price(), d("90"));
+        assert_eq!(position.realized_profit_loss(), Decimal::ZERO);
+    }
+
+    #[test]
+    fn isolated_liquidation_price() {
+        let mut position = Position::new("BTC".to_string(), "USDT".to_string(), 10, MarginMode::Isolated);
+
+        position.fill(d("1"), d("100"));
+        position.add_margin(d("10"));
+
+        assert_eq!(position.liquidation_price(Decimal::ZERO), Some(d("90")));
+    }
+}
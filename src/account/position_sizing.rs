@@ -0,0 +1,308 @@
+use crate::candles::candle::Candle;
+use crate::decimal::{Decimal, DEFAULT_SCALE};
+use crate::indicators::average_true_range::AverageTrueRange;
+use crate::strategies::indicator::Indicator;
+
+/// What a sizing policy gets to decide the quantity of a buy. Amounts are in
+/// the funds asset of the symbol.
+#[derive(Clone, Debug, Default)]
+pub struct SizingContext {
+    pub equity: Decimal,
+    pub price: Decimal,
+
+    // average true range of the symbol, once enough candles were seen
+    pub atr: Option<f64>,
+
+    // statistics of the finalized trades, profits and losses in percent
+    pub trade_count: usize,
+    pub win_rate: f64,
+    pub average_win: f64,
+    pub average_loss: f64,
+}
+
+pub trait SizingPolicy {
+    /// Quantity of the asset to buy. Zero means the policy won't buy.
+    fn quantity(&self, context: &SizingContext) -> Decimal;
+}
+
+fn quantity_for_amount(amount: Decimal, price: Decimal) -> Decimal {
+    if !price.is_positive() || !amount.is_positive() {
+        return Decimal::ZERO;
+    }
+
+    amount / price
+}
+
+// nothing for a percentage that isn't a number
+fn percentage_of(amount: Decimal, percentage: f64) -> Decimal {
+    match Decimal::from_f64(percentage, DEFAULT_SCALE) {
+        Some(percentage) => amount * percentage / Decimal::from(100),
+        None => Decimal::ZERO,
+    }
+}
+
+/// Buys the same amount of funds every time.
+pub struct FixedNotional {
+    notional: Decimal,
+}
+
+impl FixedNotional {
+    pub fn new(notional: Decimal) -> FixedNotional {
+        FixedNotional { notional }
+    }
+
+    pub fn notional(&self) -> Decimal {
+        self.notional
+    }
+}
+
+impl SizingPolicy for FixedNotional {
+    fn quantity(&self, context: &SizingContext) -> Decimal {
+        quantity_for_amount(self.notional, context.price)
+    }
+}
+
+/// Buys a percentage of the current equity.
+pub struct PercentageOfEquity {
+    percentage: f64,
+}
+
+impl PercentageOfEquity {
+    pub fn new(percentage: f64) -> PercentageOfEquity {
+        PercentageOfEquity { percentage }
+    }
+
+    pub fn percentage(&self) -> f64 {
+        self.percentage
+    }
+}
+
+impl SizingPolicy for PercentageOfEquity {
+    fn quantity(&self, context: &SizingContext) -> Decimal {
+        quantity_for_amount(percentage_of(context.equity, self.percentage), context.price)
+    }
+}
+
+/// Sizes positions so that a move of `atr_multiple` average true ranges
+/// costs `risk_percentage` of the equity. Doesn't buy until the ATR is
+/// known.
+pub struct VolatilityTarget {
+    risk_percentage: f64,
+    atr_multiple: f64,
+}
+
+impl VolatilityTarget {
+    pub fn new(risk_percentage: f64, atr_multiple: f64) -> VolatilityTarget {
+        VolatilityTarget {
+            risk_percentage,
+            atr_multiple,
+        }
+    }
+
+    pub fn risk_percentage(&self) -> f64 {
+        self.risk_percentage
+    }
+
+    pub fn atr_multiple(&self) -> f64 {
+        self.atr_multiple
+    }
+}
+
+impl SizingPolicy for VolatilityTarget {
+    fn quantity(&self, context: &SizingContext) -> Decimal {
+        let risk_per_unit = match context.atr {
+            Some(atr) if atr > 0.0 => atr * self.atr_multiple,
+            _ => return Decimal::ZERO,
+        };
+
+        let risk = percentage_of(context.equity, self.risk_percentage);
+
+        match Decimal::from_f64(risk_per_unit, DEFAULT_SCALE) {
+            Some(risk_per_unit) => quantity_for_amount(risk, risk_per_unit),
+            None => Decimal::ZERO,
+        }
+    }
+}
+
+/// Buys the Kelly fraction of the equity, estimated from the finalized
+/// trades and scaled by `fraction` (e.g. 0.5 for half Kelly). Until
+/// `min_trades` trades are finalized, `fallback_percentage` of the equity
+/// is bought instead.
+pub struct KellyFraction {
+    fraction: f64,
+    min_trades: usize,
+    fallback_percentage: f64,
+}
+
+impl KellyFraction {
+    pub fn new(fraction: f64, min_trades: usize, fallback_percentage: f64) -> KellyFraction {
+        KellyFraction {
+            fraction,
+            min_trades,
+            fallback_percentage,
+        }
+    }
+
+    pub fn fraction(&self) -> f64 {
+        self.fraction
+    }
+
+    pub fn min_trades(&self) -> usize {
+        self.min_trades
+    }
+
+    pub fn fallback_percentage(&self) -> f64 {
+        self.fallback_percentage
+    }
+
+    /// Percentage of the equity to bet: W - (1 - W) / R, R being the ratio
+    /// of the average win to the average loss.
+    pub fn kelly_percentage(&self, context: &SizingContext) -> f64 {
+        if context.trade_count < self.min_trades {
+            return self.fallback_percentage;
+        }
+
+        if context.average_loss == 0.0 {
+            return if context.win_rate > 0.0 { 100.0 * self.fraction } else { 0.0 };
+        }
+
+        let win_loss_ratio = context.average_win / context.average_loss.abs();
+
+        if win_loss_ratio == 0.0 {
+            return 0.0;
+        }
+
+        let kelly = context.win_rate - (1.0 - context.win_rate) / win_loss_ratio;
+
+        (kelly * self.fraction * 100.0).clamp(0.0, 100.0)
+    }
+}
+
+impl SizingPolicy for KellyFraction {
+    fn quantity(&self, context: &SizingContext) -> Decimal {
+        quantity_for_amount(
+            percentage_of(context.equity, self.kelly_percentage(context)),
+            context.price,
+        )
+    }
+}
+
+/// Sizes buys with a policy and keeps them within the exposure limits, in
+/// percent of the equity. Limits set to zero are not enforced.
+pub struct PositionSizer {
+    policy: Box<dyn SizingPolicy>,
+
+    max_asset_exposure: f64,
+    max_strategy_exposure: f64,
+
+    atr: AverageTrueRange,
+}
+
+impl PositionSizer {
+    pub fn new(
+        policy: impl SizingPolicy + 'static,
+        max_asset_exposure: f64,
+        max_strategy_exposure: f64,
+        atr_length: usize,
+    ) -> PositionSizer {
+        PositionSizer {
+            policy: Box::new(policy),
+
+            max_asset_exposure,
+            max_strategy_exposure,
+
+            atr: AverageTrueRange::new(atr_length, 0),
+        }
+    }
+
+    pub fn max_asset_exposure(&self) -> f64 {
+        self.max_asset_exposure
+    }
+
+    pub fn max_strategy_exposure(&self) -> f64 {
+        self.max_strategy_exposure
+    }
+
+    pub fn atr(&self) -> Option<f64> {
+        if self.atr.is_ready() {
+            Some(self.atr.get_value())
+        } else {
+            None
+        }
+    }
+
+    pub fn accept_candle(&self, candle: &Candle) {
+        self.atr.accumulate(candle);
+    }
+
+    /// Quantity of a buy given what is already held of the asset and what
+    /// the strategy already has in open trades, both valued in funds.
+    pub fn size(
+        &self,
+        context: &SizingContext,
+        asset_exposure: Decimal,
+        strategy_exposure: Decimal,
+    ) -> Decimal {
+        let mut quantity = self.policy.quantity(context).max(Decimal::ZERO);
+
+        let limits = [
+            (self.max_asset_exposure, asset_exposure),
+            (self.max_strategy_exposure, strategy_exposure),
+        ];
+
+        for (max_exposure, exposure) in limits.iter() {
+            if *max_exposure <= 0.0 {
+                continue;
+            }
+
+            let room = percentage_of(context.equity, *max_exposure) - *exposure;
+
+            quantity = quantity.min(quantity_for_amount(room, context.price));
+        }
+
+        quantity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn caps_buys_at_the_exposure_limits() {
+        let sizer = PositionSizer::new(PercentageOfEquity::new(50.0), 30.0, 0.0, 14);
+
+        let context = SizingContext {
+            equity: d("1000"),
+            price: d("10"),
+            ..SizingContext::default()
+        };
+
+        assert_eq!(sizer.size(&context, Decimal::ZERO, Decimal::ZERO), d("30"));
+        assert_eq!(sizer.size(&context, d("250"), Decimal::ZERO), d("5"));
+        assert_eq!(sizer.size(&context, d("400"), Decimal::ZERO), Decimal::ZERO);
+    }
+
+    #[test]
+    fn kelly_percentage() {
+        let kelly = KellyFraction::new(0.5, 10, 1.0);
+
+        let mut context = SizingContext {
+            trade_count: 20,
+            win_rate: 0.6,
+            average_win: 2.0,
+            average_loss: -1.0,
+            ..SizingContext::default()
+        };
+
+        assert!((kelly.kelly_percentage(&context) - 20.0).abs() < 1e-9);
+
+        context.trade_count = 5;
+
+        assert_eq!(kelly.kelly_percentage(&context), 1.0);
+    }
+}
@@ -0,0 +1,348 @@
+use std::collections::{HashMap, VecDeque};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Calls to an account that reach the exchange.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum RequestType {
+    Order,
+    Cancel,
+    OrderStatus,
+    Balances,
+}
+
+/// At most `max_weight` of request weight sent within any `interval`
+/// milliseconds.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RateLimit {
+    pub max_weight: u64,
+    pub interval: u64,
+}
+
+impl RateLimit {
+    pub fn new(max_weight: u64, interval: u64) -> RateLimit {
+        RateLimit {
+            max_weight,
+            interval,
+        }
+    }
+}
+
+fn wall_clock() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Budgets the requests an `AccountManager` sends to its account.
+///
+/// Each request type has a weight, counted against a limit on the total
+/// weight sent, while orders also count against a limit on the number of
+/// orders and balance updates against one on the number of updates. Limits
+/// are sliding windows and none are set by default.
+///
+/// Requests over budget are delayed up to `max_delay` milliseconds, or
+/// skipped beyond that unless they can't be, e.g. cancellations. A rate
+/// limit error of the exchange stops every request until its retry-after
+/// time has passed.
+pub struct RateLimiter {
+    weights: HashMap<RequestType, u64>,
+
+    weight_limit: Option<RateLimit>,
+    order_limit: Option<RateLimit>,
+    balance_limit: Option<RateLimit>,
+
+    // time and type of the requests sent within the longest limit interval
+    sent: VecDeque<(u64, RequestType)>,
+
+    // no requests before this time, after a rate limit error
+    blocked_until: u64,
+
+    max_delay: u64,
+
+    clock: Box<dyn Fn() -> u64>,
+    sleep: Box<dyn Fn(u64)>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> RateLimiter {
+        RateLimiter::new()
+    }
+}
+
+impl RateLimiter {
+    pub fn new() -> RateLimiter {
+        let mut weights = HashMap::new();
+
+        weights.insert(RequestType::Order, 1);
+        weights.insert(RequestType::Cancel, 1);
+        weights.insert(RequestType::OrderStatus, 4);
+        weights.insert(RequestType::Balances, 20);
+
+        RateLimiter {
+            weights,
+
+            weight_limit: None,
+            order_limit: None,
+            balance_limit: None,
+
+            sent: VecDeque::new(),
+
+            blocked_until: 0,
+
+            max_delay: 1000,
+
+            clock: Box::new(wall_clock),
+            sleep: Box::new(|millis| thread::sleep(Duration::from_millis(millis))),
+        }
+    }
+
+    pub fn weight(&self, request: RequestType) -> u64 {
+        self.weights.get(&request).copied().unwrap_or(1)
+    }
+
+    pub fn set_weight(&mut self, request: RequestType, weight: u64) {
+        self.weights.insert(request, weight);
+    }
+
+    pub fn weight_limit(&self) -> Option<RateLimit> {
+        self.weight_limit
+    }
+
+    pub fn set_weight_limit(&mut self, weight_limit: Option<RateLimit>) {
+        self.weight_limit = weight_limit;
+    }
+
+    pub fn order_limit(&self) -> Option<RateLimit> {
+        self.order_limit
+    }
+
+    pub fn set_order_limit(&mut self, order_limit: Option<RateLimit>) {
+        self.order_limit = order_limit;
+    }
+
+    pub fn balance_limit(&self) -> Option<RateLimit> {
+        self.balance_limit
+    }
+
+    pub fn set_balance_limit(&mut self, balance_limit: Option<RateLimit>) {
+        self.balance_limit = balance_limit;
+    }
+
+    pub fn max_delay(&self) -> u64 {
+        self.max_delay
+    }
+
+    pub fn set_max_delay(&mut self, max_delay: u64) {
+        self.max_delay = max_delay;
+    }
+
+    /// Replaces the wall clock and `thread::sleep`, both in milliseconds.
+    pub fn set_clock(&mut self, clock: impl Fn() -> u64 + 'static, sleep: impl Fn(u64) + 'static) {
+        self.clock = Box::new(clock);
+        self.sleep = Box::new(sleep);
+    }
+
+    pub fn blocked_until(&self) -> u64 {
+        self.blocked_until
+    }
+
+    /// Stops every request for `retry_after` milliseconds.
+    pub fn back_off(&mut self, retry_after: u64) {
+        let until = (self.clock)() + retry_after;
+
+        self.blocked_until = self.blocked_until.max(until);
+    }
+
+    fn limits(&self, request: RequestType) -> Vec<(RateLimit, Option<RequestType>)> {
+        let mut limits = vec![];
+
+        if let Some(limit) = self.weight_limit {
+            limits.push((limit, None));
+        }
+
+        match (request, self.order_limit, self.balance_limit) {
+            (RequestType::Order, Some(limit), _) => limits.push((limit, Some(RequestType::Order))),
+            (RequestType::Balances, _, Some(limit)) => limits.push((limit, Some(RequestType::Balances))),
+            _ => {}
+        }
+
+        limits
+    }
+
+    // weight each request counts for against a limit on `counted` requests,
+    // or against the weight limit
+    fn cost(&self, request: RequestType, counted: Option<RequestType>) -> u64 {
+        match counted {
+            Some(_) => 1,
+            None => self.weight(request),
+        }
+    }
+
+    /// Milliseconds to wait at `now` before the request fits every limit.
+    pub fn delay(&self, request: RequestType, now: u64) -> u64 {
+        let mut delay = self.blocked_until.saturating_sub(now);
+
+        for (limit, counted) in self.limits(request) {
+            let cost = self.cost(request, counted);
+
+            // the oldest requests of the window leave it first, wait until
+            // enough of them did
+            let mut used: u64 = self.sent
+                .iter()
+                .filter(|(time, sent)| now < time + limit.interval && counted.map(|counted| counted == *sent).unwrap_or(true))
+                .map(|(_, sent)| self.cost(*sent, counted))
+                .sum();
+
+            if used + cost <= limit.max_weight {
+                continue;
+            }
+
+            for (time, sent) in self.sent.iter() {
+                if now >= time + limit.interval || counted.map(|counted| counted != *sent).unwrap_or(false) {
+                    continue;
+                }
+
+                used = used.saturating_sub(self.cost(*sent, counted));
+
+                if used + cost <= limit.max_weight {
+                    delay = delay.max(time + limit.interval - now);
+
+                    break;
+                }
+            }
+        }
+
+        delay
+    }
+
+    pub fn record(&mut self, request: RequestType, now: u64) {
+        let window = [self.weight_limit, self.order_limit, self.balance_limit]
+            .iter()
+            .flatten()
+            .map(|limit| limit.interval)
+            .max();
+
+        let window = match window {
+            Some(window) => window,
+            None => return,
+        };
+
+        while self.sent.front().map(|(time, _)| now >= time + window).unwrap_or(false) {
+            self.sent.pop_front();
+        }
+
+        self.sent.push_back((now, request));
+    }
+
+    /// Waits until the request can be sent and counts it. Gives up without
+    /// counting it if that takes longer than `max_delay`, unless `always`,
+    /// and returns the delay.
+    pub fn acquire(&mut self, request: RequestType, always: bool) -> Result<(), u64> {
+        let delay = self.delay(request, (self.clock)());
+
+        if delay > 0 {
+            if !always && delay > self.max_delay {
+                return Err(delay);
+            }
+
+            (self.sleep)(delay);
+        }
+
+        let now = (self.clock)();
+
+        self.record(request, now);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use crate::account::account_manager::AccountManager;
+    use crate::account::order::OrderSide;
+    use crate::account::order_request::OrderRequest;
+    use crate::account::symbol_info::OrderRejection;
+    use crate::account::trade::TradeSide;
+    use crate::decimal::Decimal;
+    use crate::simulation::simulated_account::SimulatedAccount;
+    use crate::trading_fees::PercentageFee;
+
+    #[test]
+    fn delays_requests_over_budget() {
+        let time = Rc::new(Cell::new(10_000));
+
+        let mut rate_limiter = RateLimiter::new();
+
+        let (clock, sleep) = (time.clone(), time.clone());
+        rate_limiter.set_clock(move || clock.get(), move |millis| sleep.set(sleep.get() + millis));
+
+        rate_limiter.set_weight_limit(Some(RateLimit::new(10, 1000)));
+        rate_limiter.set_order_limit(Some(RateLimit::new(2, 500)));
+
+        assert!(rate_limiter.acquire(RequestType::Order, false).is_ok());
+
+        time.set(10_100);
+        assert!(rate_limiter.acquire(RequestType::Order, false).is_ok());
+
+        // a third order waits for the first to leave the window
+        time.set(10_200);
+        assert_eq!(rate_limiter.delay(RequestType::Order, 10_200), 300);
+        assert!(rate_limiter.acquire(RequestType::Order, false).is_ok());
+        assert_eq!(time.get(), 10_500);
+
+        // 3 of weight used, a status query weighs 4
+        assert!(rate_limiter.acquire(RequestType::OrderStatus, false).is_ok());
+        assert_eq!(rate_limiter.delay(RequestType::OrderStatus, 10_500), 500);
+
+        rate_limiter.set_max_delay(100);
+        assert_eq!(rate_limiter.acquire(RequestType::OrderStatus, false), Err(500));
+
+        rate_limiter.back_off(60_000);
+        assert_eq!(rate_limiter.delay(RequestType::Cancel, 10_500), 60_000);
+        assert!(rate_limiter.acquire(RequestType::Cancel, true).is_ok());
+        assert_eq!(time.get(), 70_500);
+    }
+
+    #[test]
+    fn rejects_orders_over_budget() {
+        let mut account = SimulatedAccount::new(PercentageFee::new(Decimal::ZERO));
+
+        account.set_amount("USDT", Decimal::from(1000));
+
+        let mut account_manager = AccountManager::new(account, 0, 60_000, 1.5);
+
+        account_manager.rate_limiter_mut().set_clock(|| 5000, |_| {});
+        account_manager.rate_limiter_mut().set_order_limit(Some(RateLimit::new(1, 1000)));
+        account_manager.rate_limiter_mut().set_max_delay(0);
+        account_manager.set_max_balance_updates(2);
+
+        assert_eq!(account_manager.rate_limiter().balance_limit(), Some(RateLimit::new(2, 60_000)));
+
+        let order_request = || {
+            let mut order_request = OrderRequest::new(
+                "BTC".to_string(),
+                "USDT".to_string(),
+                OrderSide::Buy,
+                TradeSide::LONG,
+                0,
+                None,
+            );
+
+            order_request.set_price(Decimal::from(100));
+            order_request.set_quantity(Decimal::ONE);
+
+            order_request
+        };
+
+        assert!(account_manager.submit_order(order_request()).is_ok());
+        assert_eq!(
+            account_manager.submit_order(order_request()).unwrap_err(),
+            OrderRejection::RateLimited { retry_after: 1000 },
+        );
+    }
+}
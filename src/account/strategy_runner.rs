@@ -0,0 +1,714 @@
+use std::collections::BTreeMap;
+use crate::account::account_manager::AccountManager;
+use crate::account::context::Context;
+use crate::account::order::{Order, OrderSide, OrderType};
+use crate::account::order_request::OrderRequest;
+use crate::account::orderbook::OrderBook;
+use crate::account::position::AccountMode;
+use crate::account::symbol_info::OrderRejection;
+use crate::account::trade::{Trade, TradeSide};
+use crate::account::trade_switch::TradeSwitcher;
+use crate::account::trader::Trader;
+use crate::account::trading_manager::TradingManager;
+use crate::candles::candle::Candle;
+use crate::candles::candle_feed::CandleSnapshot;
+use crate::candles::candle_history::CandleHistory;
+use crate::client_account::ClientAccount;
+use crate::decimal::{Decimal, DEFAULT_SCALE};
+use crate::indicators::signal::Signal;
+use crate::strategies::strategy::Strategy;
+
+/// Acts on the signals of a strategy for one symbol, the same way whether
+/// the account is simulated or not.
+///
+/// Each closed candle finalizes the orders the account reports as done,
+/// adds the funding paid since the previous one to the open trades, syncs
+/// the balances (right away after a fill, otherwise once expired), checks
+/// margin levels, asks the strategy for a signal and then submits
+/// market orders: buy signals open long trades (or cover shorts for short
+/// strategies) and sell signals close them (or open shorts). Entries go
+/// through `Trader::submit_buy`, sized by the trader's position sizer if it
+/// has one, otherwise with `order_percentage` of the free funds. The last
+/// entry or exit the account manager rejected is kept until taken with
+/// `take_rejection`.
+///
+/// The monitors of the strategy can discard its entries, and those of the
+/// open trades can hold back their exits or stop them whatever the signal.
+///
+/// With a trade switcher, the open trades can be moved to a symbol with a
+/// stronger signal through `check_trade_switch`. The runner doesn't trade
+/// on its own while a switch is in progress, and follows the funds to the
+/// new symbol once it completes.
+pub struct StrategyRunner {
+    trader: Trader,
+
+    candle_history: CandleHistory,
+
+    order_percentage: f64,
+
+    // percentage taken by each fill, left aside when sizing entries
+    fee_percentage: Decimal,
+
+    // an order filled since the balances were last refreshed
+    filled: bool,
+
+    last_rejection: Option<OrderRejection>,
+
+    trade_switcher: Option<TradeSwitcher>,
+}
+
+impl StrategyRunner {
+    pub fn new(
+        trading_manager: TradingManager,
+        order_percentage: f64,
+        fee_percentage: Decimal,
+        history_capacity: usize,
+    ) -> StrategyRunner {
+        StrategyRunner {
+            trader: Trader::new(trading_manager),
+
+            candle_history: CandleHistory::new(history_capacity),
+
+            order_percentage,
+            fee_percentage,
+
+            filled: false,
+
+            last_rejection: None,
+
+            trade_switcher: None,
+        }
+    }
+
+    pub fn last_rejection(&self) -> Option<&OrderRejection> {
+        self.last_rejection.as_ref()
+    }
+
+    pub fn take_rejection(&mut self) -> Option<OrderRejection> {
+        self.last_rejection.take()
+    }
+
+    pub fn trader(&self) -> &Trader {
+        &self.trader
+    }
+
+    pub fn trader_mut(&mut self) -> &mut Trader {
+        &mut self.trader
+    }
+
+    pub fn candle_history(&self) -> &CandleHistory {
+        &self.candle_history
+    }
+
+    pub fn candle_history_mut(&mut self) -> &mut CandleHistory {
+        &mut self.candle_history
+    }
+
+    pub fn trade_switcher(&self) -> Option<&TradeSwitcher> {
+        self.trade_switcher.as_ref()
+    }
+
+    pub fn set_trade_switcher(&mut self, trade_switcher: TradeSwitcher) {
+        self.trade_switcher = Some(trade_switcher);
+    }
+
+    fn is_switching(&self) -> bool {
+        self.trade_switcher
+            .as_ref()
+            .map(|trade_switcher| trade_switcher.is_switching(&self.trader.symbol()))
+            .unwrap_or(false)
+    }
+
+    /// Finalized and open trades, sorted by id.
+    pub fn into_trades(self) -> Vec<Trade> {
+        let mut trades = self.trader.into_trades();
+
+        trades.sort_by_key(|trade| trade.id());
+
+        trades
+    }
+
+    /// Polls the open orders and adds the finalized ones to the trades.
+    pub fn process_orders<A: ClientAccount>(&mut self, account_manager: &mut AccountManager<A>) {
+        for order in account_manager.update_open_orders() {
+            self.filled |= !order.executed_quantity().is_zero();
+
+            self.trader.process_finalized_order(account_manager, &order);
+
+            self.complete_switch(account_manager, &order);
+        }
+    }
+
+    /// Asks the trade switcher, if any, whether the open trades should move
+    /// to a symbol of the snapshot with a stronger signal, and submits the
+    /// exit if so. `open_symbols` are the symbols other runners hold trades
+    /// of.
+    pub fn check_trade_switch<A: ClientAccount>(
+        &mut self,
+        account_manager: &mut AccountManager<A>,
+        snapshot: &CandleSnapshot,
+        signals: &BTreeMap<String, Signal>,
+        open_symbols: &[String],
+    ) -> Result<Option<Order>, OrderRejection> {
+        let trade_switcher = match self.trade_switcher.as_mut() {
+            Some(trade_switcher) => trade_switcher,
+            None => return Ok(None),
+        };
+
+        if !account_manager.open_orders().is_empty() {
+            return Ok(None);
+        }
+
+        trade_switcher.check(account_manager, &self.trader, &self.candle_history, snapshot, signals, open_symbols)
+    }
+
+    // submits the entry of a switch once its exit is finalized. The trader
+    // moves to the new symbol, and its trade gets the strategy and monitors
+    // of the one it replaces
+    fn complete_switch<A: ClientAccount>(&mut self, account_manager: &mut AccountManager<A>, order: &Order) {
+        let entry = match self.trade_switcher.as_mut() {
+            Some(trade_switcher) => trade_switcher.order_finalized(account_manager, order),
+            None => return,
+        };
+
+        let entry = match entry {
+            Some(Ok(entry)) => entry,
+            _ => return,
+        };
+
+        if self.trader.trades().is_empty() {
+            self.trader.set_trading_manager(TradingManager::new(
+                entry.order_request.assets_symbol().to_string(),
+                entry.order_request.funds_symbol().to_string(),
+            ));
+
+            let mut candle_history = CandleHistory::new(self.candle_history.capacity());
+
+            for interval in self.candle_history.intervals() {
+                candle_history.add_interval(interval);
+            }
+
+            self.candle_history = candle_history;
+        }
+
+        self.trader.inherit_strategy(&order.order_id(), &entry.order_id());
+    }
+
+    pub fn on_candle<A: ClientAccount>(
+        &mut self,
+        account_manager: &mut AccountManager<A>,
+        strategy: &dyn Strategy,
+        candle: &Candle,
+        order_book: Option<&OrderBook>,
+    ) {
+        let trading_manager = self.trader.trading_manager().clone();
+
+        if let Some(close) = Decimal::from_f64(candle.close, DEFAULT_SCALE) {
+            account_manager.update_market_price(
+                trading_manager.assets_symbol(),
+                trading_manager.funds_symbol(),
+                close,
+            );
+        }
+
+        self.process_orders(account_manager);
+
+        for funding_payment in account_manager.funding_payments() {
+            self.trader.process_funding_payment(&funding_payment);
+        }
+
+        if self.filled {
+            self.filled = !account_manager.refresh_balances(candle.close_time);
+        } else {
+            account_manager.sync_balances(candle.close_time);
+        }
+
+        for event in account_manager.check_margin_levels(candle.close_time) {
+            self.trader.handle_margin_event(&event);
+        }
+
+        self.trader.tick(candle);
+
+        self.candle_history.accept(candle);
+
+        let short = strategy.trade_side() == Some(TradeSide::SHORT);
+
+        let (signal, stop_reason, allows_exit, discards_entry) = {
+            let context = account_manager.context(&self.trader, order_book, &self.candle_history);
+
+            let trades = self.trader.trades();
+
+            (
+                strategy.get_signal_with_context(*candle, &context),
+                StrategyRunner::stop_reason(trades, &context),
+                StrategyRunner::allows_exit(trades, &context),
+                StrategyRunner::discards_entry(strategy, short, &context),
+            )
+        };
+
+        if account_manager.open_orders().is_empty() && !self.is_switching() {
+            let in_trade = !self.trader.trades().is_empty();
+
+            let (entry, exit) = if short {
+                (Signal::SELL, Signal::BUY)
+            } else {
+                (Signal::BUY, Signal::SELL)
+            };
+
+            let submitted = if !in_trade && signal == entry && !discards_entry {
+                self.enter(account_manager, strategy, short, candle)
+            } else if let (true, Some(stop_reason)) = (in_trade, stop_reason) {
+                for trade in self.trader.trades_mut() {
+                    trade.stop(&stop_reason);
+                }
+
+                self.exit(account_manager, short, candle)
+            } else if in_trade && signal == exit && strategy.exit_on_opposite_signal() && allows_exit {
+                self.exit(account_manager, short, candle)
+            } else {
+                Ok(())
+            };
+
+            if let Err(rejection) = submitted {
+                self.last_rejection = Some(rejection);
+            }
+        }
+
+        account_manager.record_equity(candle);
+    }
+
+    // reason the first monitor of the open trades that wants them stopped
+    // gives
+    fn stop_reason(trades: &[Trade], context: &Context) -> Option<String> {
+        trades.iter().find_map(|trade| {
+            trade.monitors.iter().find_map(|monitor| monitor.handle_stop(trade, context))
+        })
+    }
+
+    fn allows_exit(trades: &[Trade], context: &Context) -> bool {
+        trades.iter().all(|trade| {
+            trade.monitors.iter().all(|monitor| monitor.allow_exit(trade, context))
+        })
+    }
+
+    fn discards_entry(strategy: &dyn Strategy, short: bool, context: &Context) -> bool {
+        strategy.monitors().iter().any(|monitor| {
+            if short {
+                monitor.discard_short_sell(strategy, context)
+            } else {
+                monitor.discard_buy(strategy, context)
+            }
+        })
+    }
+
+    fn market_order(&self, side: OrderSide, trade_side: TradeSide, quantity: Decimal, time: u64) -> OrderRequest {
+        let trading_manager = self.trader.trading_manager();
+
+        let mut order_request = OrderRequest::new(
+            trading_manager.assets_symbol().to_string(),
+            trading_manager.funds_symbol().to_string(),
+            side,
+            trade_side,
+            time,
+            None,
+        );
+
+        order_request.set_order_type(OrderType::Market);
+        order_request.set_quantity(quantity);
+
+        order_request
+    }
+
+    fn enter<A: ClientAccount>(
+        &mut self,
+        account_manager: &mut AccountManager<A>,
+        strategy: &dyn Strategy,
+        short: bool,
+        candle: &Candle,
+    ) -> Result<(), OrderRejection> {
+        let free = account_manager.balance(self.trader.trading_manager().funds_symbol())
+            .map(|balance| balance.free)
+            .unwrap_or(Decimal::ZERO);
+
+        let price = Decimal::from_f64(candle.close, DEFAULT_SCALE).unwrap_or(Decimal::ZERO);
+
+        if !price.is_positive() {
+            return Ok(());
+        }
+
+        let order_percentage = Decimal::from_f64(self.order_percentage, DEFAULT_SCALE).unwrap_or(Decimal::ZERO);
+
+        // leave room for the fee, and for the price moving until the order
+        // fills
+        let amount = free * order_percentage / Decimal::from(100)
+            * (Decimal::ONE - self.fee_percentage * Decimal::from(2) / Decimal::from(100));
+
+        let quantity = (amount / price).truncate(DEFAULT_SCALE);
+
+        let mut order_request = if short {
+            self.market_order(OrderSide::Sell, TradeSide::SHORT, quantity, candle.close_time)
+        } else {
+            self.market_order(OrderSide::Buy, TradeSide::LONG, quantity, candle.close_time)
+        };
+
+        // exits are placed relative to the close, the entry itself stays a
+        // market order
+        order_request.set_price(price);
+        strategy.prepare_entry(&mut order_request);
+        order_request.set_price(Decimal::ZERO);
+
+        self.trader.submit_buy(account_manager, order_request, Some(strategy))?;
+
+        Ok(())
+    }
+
+    fn exit<A: ClientAccount>(
+        &self,
+        account_manager: &mut AccountManager<A>,
+        short: bool,
+        candle: &Candle,
+    ) -> Result<(), OrderRejection> {
+        let quantity: Decimal = self.trader.trades()
+            .iter()
+            .map(|trade| trade.total_units())
+            .sum();
+
+        if !quantity.is_positive() {
+            return Ok(());
+        }
+
+        let order_request = if short {
+            self.market_order(OrderSide::Buy, TradeSide::SHORT, quantity, candle.close_time)
+        } else {
+            let trading_manager = self.trader.trading_manager();
+
+            // never sell more than what is held, futures positions aren't
+            // held as a balance of the asset
+            let held = match account_manager.account_mode() {
+                AccountMode::Futures => account_manager.position(&trading_manager.symbol())
+                    .map(|position| position.quantity().max(Decimal::ZERO))
+                    .unwrap_or(Decimal::ZERO),
+                AccountMode::Spot => account_manager.balance(trading_manager.assets_symbol())
+                    .map(|balance| balance.free)
+                    .unwrap_or(Decimal::ZERO),
+            };
+
+            self.market_order(OrderSide::Sell, TradeSide::LONG, quantity.min(held), candle.close_time)
+        };
+
+        account_manager.submit_order(order_request)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::position::MarginMode;
+    use crate::account::position_sizing::{PercentageOfEquity, PositionSizer};
+    use crate::simulation::funding_history::FundingHistory;
+    use crate::simulation::futures_account::SimulatedFuturesAccount;
+    use crate::simulation::simulated_account::SimulatedAccount;
+    use crate::strategies::declarative::StrategyDefinition;
+    use crate::strategies::indicator::Indicator;
+    use crate::strategies::indicator_group::IndicatorGroup;
+    use crate::strategies::strategy_monitor::StrategyMonitor;
+    use crate::trading_fees::PercentageFee;
+
+    struct Buy;
+
+    impl Strategy for Buy {
+        fn get_signal(&self, _candle: Candle) -> Signal {
+            Signal::BUY
+        }
+
+        fn name(&self) -> Option<&str> {
+            Some("buy")
+        }
+    }
+
+    fn candle(i: u64, close: f64) -> Candle {
+        Candle::new(i * 60_000, i * 60_000 + 59_999, close, close, close, close, 1.0)
+    }
+
+    #[test]
+    fn adds_funding_to_open_trades() {
+        let mut account = SimulatedFuturesAccount::new(PercentageFee::new(Decimal::ZERO), 10, MarginMode::Cross);
+
+        account.set_amount("USDT", Decimal::from(1000));
+        account.set_funding_history(
+            "BTCUSDT",
+            FundingHistory::parse("FUNDING_TIME,FUNDING_RATE\n150000,0.001\n").unwrap(),
+        );
+
+        let mut account_manager = AccountManager::new(account, 0, 0, 1.5);
+
+        let mut runner = StrategyRunner::new(
+            TradingManager::new("BTC".to_string(), "USDT".to_string()),
+            50.0,
+            Decimal::ZERO,
+            10,
+        );
+
+        for i in 0..3 {
+            account_manager.account_mut().accept_candle("BTCUSDT", &candle(i, 100.0));
+
+            runner.on_candle(&mut account_manager, &Buy, &candle(i, 100.0), None);
+        }
+
+        // longs of 5 BTC pay 0.1% of 500
+        let trade = &runner.trader().trades()[0];
+
+        assert_eq!(trade.total_units(), Decimal::from(5));
+        assert_eq!(trade.funding(), "0.5".parse().unwrap());
+        assert!(account_manager.funding_payments().is_empty());
+    }
+
+    struct Threshold;
+
+    impl Strategy for Threshold {
+        fn get_signal(&self, candle: Candle) -> Signal {
+            if candle.close < 105.0 {
+                Signal::BUY
+            } else {
+                Signal::SELL
+            }
+        }
+    }
+
+    #[test]
+    fn closes_futures_longs_and_keeps_rejections() {
+        let mut account = SimulatedFuturesAccount::new(PercentageFee::new(Decimal::ZERO), 10, MarginMode::Cross);
+
+        account.set_amount("USDT", Decimal::from(1000));
+
+        let mut account_manager = AccountManager::new(account, 0, 0, 1.5);
+
+        let mut runner = StrategyRunner::new(
+            TradingManager::new("BTC".to_string(), "USDT".to_string()),
+            50.0,
+            Decimal::ZERO,
+            10,
+        );
+
+        for (i, close) in [100.0, 100.0, 110.0, 110.0].iter().enumerate() {
+            account_manager.account_mut().accept_candle("BTCUSDT", &candle(i as u64, *close));
+
+            runner.on_candle(&mut account_manager, &Threshold, &candle(i as u64, *close), None);
+        }
+
+        // the position isn't a balance of BTC, the sell is sized from it
+        assert!(runner.trader().trades().is_empty());
+        assert_eq!(runner.trader().finalized_trades().len(), 1);
+        assert!(runner.take_rejection().is_none());
+
+        let mut account_manager = AccountManager::new(SimulatedAccount::new(PercentageFee::new(Decimal::ZERO)), 0, 0, 1.5);
+
+        runner.on_candle(&mut account_manager, &Threshold, &candle(4, 100.0), None);
+
+        assert!(runner.take_rejection().is_some());
+        assert!(runner.take_rejection().is_none());
+    }
+
+    struct Cautious;
+
+    impl IndicatorGroup for Cautious {
+        fn is_initialized(&self) -> bool {
+            true
+        }
+
+        fn get_indicators(&self) -> Vec<Box<dyn Indicator>> {
+            vec![]
+        }
+
+        fn candle_accumulated(&self, _candle: &Candle) {}
+    }
+
+    fn latest_close(context: &Context) -> f64 {
+        context.latest_candle().map(|candle| candle.close).unwrap_or(0.0)
+    }
+
+    // buys above 95, sells from 120 and stops at 130
+    impl StrategyMonitor for Cautious {
+        fn handle_stop(&self, _trade: &Trade, context: &Context) -> Option<String> {
+            if latest_close(context) >= 130.0 {
+                Some("target".to_string())
+            } else {
+                None
+            }
+        }
+
+        fn discard_buy(&self, _strategy: &dyn Strategy, context: &Context) -> bool {
+            latest_close(context) < 95.0
+        }
+
+        fn allow_exit(&self, _trade: &Trade, context: &Context) -> bool {
+            latest_close(context) >= 120.0
+        }
+    }
+
+    struct MonitoredThreshold;
+
+    impl Strategy for MonitoredThreshold {
+        fn get_signal(&self, candle: Candle) -> Signal {
+            Threshold.get_signal(candle)
+        }
+
+        fn monitors(&self) -> Vec<Box<dyn StrategyMonitor>> {
+            vec![Box::new(Cautious)]
+        }
+    }
+
+    #[test]
+    fn lets_monitors_discard_entries_and_hold_or_stop_exits() {
+        let mut account = SimulatedAccount::new(PercentageFee::new(Decimal::ZERO));
+
+        account.set_amount("USDT", Decimal::from(1000));
+
+        let mut account_manager = AccountManager::new(account, 0, 0, 1.5);
+
+        let mut runner = StrategyRunner::new(
+            TradingManager::new("BTC".to_string(), "USDT".to_string()),
+            50.0,
+            Decimal::ZERO,
+            10,
+        );
+
+        let mut step = |i: u64, close: f64, runner: &mut StrategyRunner| {
+            account_manager.account_mut().accept_candle("BTCUSDT", &candle(i, close));
+
+            runner.on_candle(&mut account_manager, &MonitoredThreshold, &candle(i, close), None);
+        };
+
+        // the buy signal at 90 is discarded
+        step(0, 90.0, &mut runner);
+        step(1, 100.0, &mut runner);
+
+        assert!(runner.trader().trades().is_empty());
+
+        step(2, 100.0, &mut runner);
+
+        assert_eq!(runner.trader().trades().len(), 1);
+
+        // the sell signals below 120 are held back
+        step(3, 110.0, &mut runner);
+        step(4, 110.0, &mut runner);
+
+        assert_eq!(runner.trader().trades().len(), 1);
+
+        step(5, 130.0, &mut runner);
+        step(6, 130.0, &mut runner);
+
+        assert!(runner.trader().trades().is_empty());
+
+        let trade = &runner.trader().finalized_trades()[0];
+
+        assert!(trade.is_stopped());
+        assert_eq!(trade.exit_reason.as_deref(), Some("target"));
+    }
+
+    #[test]
+    fn syncs_expired_balances_unless_an_order_filled() {
+        let mut account = SimulatedAccount::new(PercentageFee::new(Decimal::ZERO));
+
+        account.set_amount("USDT", Decimal::from(1000));
+
+        let mut account_manager = AccountManager::new(account, 300_000, 0, 1.5);
+
+        let mut runner = StrategyRunner::new(
+            TradingManager::new("BTC".to_string(), "USDT".to_string()),
+            50.0,
+            Decimal::ZERO,
+            10,
+        );
+
+        let mut syncs = vec![];
+
+        for i in 0..8 {
+            account_manager.account_mut().accept_candle("BTCUSDT", &candle(i, 100.0));
+
+            runner.on_candle(&mut account_manager, &Buy, &candle(i, 100.0), None);
+
+            syncs.push(account_manager.last_balance_sync());
+        }
+
+        // the first sync, the buy filling on the next candle, then expiry
+        assert_eq!(
+            syncs,
+            vec![59_999, 119_999, 119_999, 119_999, 119_999, 119_999, 419_999, 419_999],
+        );
+        assert_eq!(account_manager.balance("USDT").unwrap().free, Decimal::from(500));
+    }
+
+    #[test]
+    fn sizes_entries_with_the_position_sizer() {
+        let mut account = SimulatedAccount::new(PercentageFee::new(Decimal::ZERO));
+
+        account.set_amount("USDT", Decimal::from(1000));
+
+        let mut account_manager = AccountManager::new(account, 0, 0, 1.5);
+
+        account_manager.add_market("BTC", "USDT");
+
+        let mut runner = StrategyRunner::new(
+            TradingManager::new("BTC".to_string(), "USDT".to_string()),
+            100.0,
+            Decimal::ZERO,
+            10,
+        );
+
+        // half of the equity, capped at 20% of it
+        runner.trader_mut().set_position_sizer(PositionSizer::new(PercentageOfEquity::new(50.0), 20.0, 0.0, 14));
+
+        for i in 0..2 {
+            account_manager.account_mut().accept_candle("BTCUSDT", &candle(i, 100.0));
+
+            runner.on_candle(&mut account_manager, &Buy, &candle(i, 100.0), None);
+        }
+
+        let trade = &runner.trader().trades()[0];
+
+        assert_eq!(trade.total_units(), Decimal::from(2));
+        assert_eq!(trade.strategy_name(), Some("buy"));
+    }
+
+    #[test]
+    fn attaches_the_strategy_exits_to_entries() {
+        let mut account = SimulatedAccount::new(PercentageFee::new(Decimal::ZERO));
+
+        account.set_amount("USDT", Decimal::from(1000));
+
+        let mut account_manager = AccountManager::new(account, 0, 0, 1.5);
+
+        let mut runner = StrategyRunner::new(
+            TradingManager::new("BTC".to_string(), "USDT".to_string()),
+            50.0,
+            Decimal::ZERO,
+            10,
+        );
+
+        let strategy = StrategyDefinition::parse(r#"{
+            "name": "dip",
+            "entry": { "lt": ["close", 95] },
+            "stop_loss": 10,
+            "take_profit": 20
+        }"#).unwrap().build();
+
+        account_manager.account_mut().accept_candle("BTCUSDT", &candle(0, 90.0));
+
+        runner.on_candle(&mut account_manager, &strategy, &candle(0, 90.0), None);
+
+        let entry = account_manager.open_orders().values().next().unwrap();
+
+        assert_eq!(*entry.order_request.order_type(), OrderType::Market);
+        assert!(entry.order_request.price().is_zero());
+
+        let prices: Vec<Decimal> = entry.attachments()
+            .iter()
+            .map(|attachment| attachment.order_request.price())
+            .collect();
+
+        assert_eq!(prices, vec![Decimal::from(81), Decimal::from(108)]);
+    }
+}
@@ -0,0 +1,289 @@
+use std::fmt;
+use crate::account::order::OrderType;
+use crate::account::order_request::OrderRequest;
+use crate::decimal::Decimal;
+
+/// Reasons for an exchange to refuse an order, detected before the order is
+/// sent.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OrderRejection {
+    InvalidPrice {
+        price: Decimal,
+    },
+    InvalidQuantity {
+        quantity: Decimal,
+    },
+    QuantityBelowMinimum {
+        quantity: Decimal,
+        min_quantity: Decimal,
+    },
+    QuantityAboveMaximum {
+        quantity: Decimal,
+        max_quantity: Decimal,
+    },
+    NotionalBelowMinimum {
+        notional: Decimal,
+        min_notional: Decimal,
+    },
+    InsufficientMargin {
+        required: Decimal,
+        available: Decimal,
+    },
+    InsufficientFunds {
+        required: Decimal,
+        available: Decimal,
+    },
+    ExposureLimitReached {
+        symbol: String,
+    },
+    RateLimited {
+        retry_after: u64,
+    },
+}
+
+impl fmt::Display for OrderRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderRejection::InvalidPrice { price } =>
+                write!(f, "invalid price {}", price),
+            OrderRejection::InvalidQuantity { quantity } =>
+                write!(f, "invalid quantity {}", quantity),
+            OrderRejection::QuantityBelowMinimum { quantity, min_quantity } =>
+                write!(f, "quantity {} is below the minimum of {}", quantity, min_quantity),
+            OrderRejection::QuantityAboveMaximum { quantity, max_quantity } =>
+                write!(f, "quantity {} is above the maximum of {}", quantity, max_quantity),
+            OrderRejection::NotionalBelowMinimum { notional, min_notional } =>
+                write!(f, "order amount {} is below the minimum notional of {}", notional, min_notional),
+            OrderRejection::InsufficientMargin { required, available } =>
+                write!(f, "short sell requires {} of margin but only {} is available", required, available),
+            OrderRejection::InsufficientFunds { required, available } =>
+                write!(f, "order requires {} of funds but only {} is available", required, available),
+            OrderRejection::ExposureLimitReached { symbol } =>
+                write!(f, "position sizing allows no more exposure to {}", symbol),
+            OrderRejection::RateLimited { retry_after } =>
+                write!(f, "request rate limit reached, retry in {} ms", retry_after),
+        }
+    }
+}
+
+impl std::error::Error for OrderRejection {}
+
+/// Trading rules of a symbol. Filters set to zero are not enforced.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SymbolInfo {
+    symbol: String,
+
+    tick_size: Decimal,
+    step_size: Decimal,
+
+    min_quantity: Decimal,
+    max_quantity: Decimal,
+
+    min_notional: Decimal,
+
+    price_precision: u32,
+}
+
+impl SymbolInfo {
+    pub fn new(
+        symbol: impl Into<String>,
+        tick_size: Decimal,
+        step_size: Decimal,
+        min_quantity: Decimal,
+        max_quantity: Decimal,
+        min_notional: Decimal,
+        price_precision: u32,
+    ) -> SymbolInfo {
+        SymbolInfo {
+            symbol: symbol.into(),
+
+            tick_size,
+            step_size,
+
+            min_quantity,
+            max_quantity,
+
+            min_notional,
+
+            price_precision,
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn tick_size(&self) -> Decimal {
+        self.tick_size
+    }
+
+    pub fn step_size(&self) -> Decimal {
+        self.step_size
+    }
+
+    pub fn min_quantity(&self) -> Decimal {
+        self.min_quantity
+    }
+
+    pub fn max_quantity(&self) -> Decimal {
+        self.max_quantity
+    }
+
+    pub fn min_notional(&self) -> Decimal {
+        self.min_notional
+    }
+
+    pub fn price_precision(&self) -> u32 {
+        self.price_precision
+    }
+
+    /// Rounds a price to the tick size, down for buys and up for sells so
+    /// the rounding never works against the order.
+    pub fn round_price(&self, price: Decimal, is_buy: bool) -> Decimal {
+        let mut price = price;
+
+        if self.tick_size.is_positive() {
+            price = if is_buy {
+                price.floor_to(self.tick_size)
+            } else {
+                price.ceil_to(self.tick_size)
+            };
+        }
+
+        if self.price_precision > 0 && price.scale() > self.price_precision {
+            price = if is_buy {
+                price.truncate(self.price_precision)
+            } else {
+                price.ceil_to(Decimal::new(1, self.price_precision))
+            };
+        }
+
+        price
+    }
+
+    /// Rounds a quantity down to the step size.
+    pub fn round_quantity(&self, quantity: Decimal) -> Decimal {
+        if !self.step_size.is_positive() {
+            return quantity;
+        }
+
+        quantity.floor_to(self.step_size)
+    }
+
+    /// Checks an order request against the filters without changing it.
+    pub fn validate(&self, order_request: &OrderRequest) -> Result<(), OrderRejection> {
+        let price = order_request.price();
+        let quantity = order_request.quantity();
+
+        let is_market = *order_request.order_type() == OrderType::Market;
+
+        if price.is_negative() || (price.is_zero() && !is_market) {
+            return Err(OrderRejection::InvalidPrice { price });
+        }
+
+        if !quantity.is_positive() {
+            return Err(OrderRejection::InvalidQuantity { quantity });
+        }
+
+        if self.min_quantity.is_positive() && quantity < self.min_quantity {
+            return Err(OrderRejection::QuantityBelowMinimum {
+                quantity,
+                min_quantity: self.min_quantity,
+            });
+        }
+
+        if self.max_quantity.is_positive() && quantity > self.max_quantity {
+            return Err(OrderRejection::QuantityAboveMaximum {
+                quantity,
+                max_quantity: self.max_quantity,
+            });
+        }
+
+        // market orders without a price can't be checked here
+        let notional = price * quantity;
+
+        if self.min_notional.is_positive() && price.is_positive() && notional < self.min_notional {
+            return Err(OrderRejection::NotionalBelowMinimum {
+                notional,
+                min_notional: self.min_notional,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Rounds the price and quantity of an order request to the symbol's
+    /// tick and step sizes, then validates it.
+    pub fn adjust(&self, order_request: &mut OrderRequest) -> Result<(), OrderRejection> {
+        let is_buy = order_request.is_buy();
+
+        order_request.set_price(
+            self.round_price(order_request.price(), is_buy),
+        );
+
+        order_request.set_quantity(
+            self.round_quantity(order_request.quantity()),
+        );
+
+        if !order_request.trigger_price().is_zero() {
+            let trigger_condition = *order_request.trigger_condition();
+            let trigger_price = self.round_price(order_request.trigger_price(), is_buy);
+
+            let active = order_request.is_active();
+
+            order_request.set_trigger_condition(trigger_condition, trigger_price);
+
+            if active {
+                order_request.activate();
+            }
+        }
+
+        self.validate(order_request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::order::OrderSide;
+    use crate::account::trade::TradeSide;
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn rounds_to_tick_and_step_size() {
+        let symbol_info = SymbolInfo::new("BTCUSDT", d("0.05"), d("0.001"), Decimal::ZERO, Decimal::ZERO, Decimal::ZERO, 2);
+
+        assert_eq!(symbol_info.round_price(d("1.2345"), true), d("1.2"));
+        assert_eq!(symbol_info.round_price(d("1.2345"), false), d("1.25"));
+        assert_eq!(symbol_info.round_price(d("1.25"), false), d("1.25"));
+        assert_eq!(symbol_info.round_quantity(d("0.123456")), d("0.123"));
+    }
+
+    #[test]
+    fn rejects_small_orders() {
+        let symbol_info = SymbolInfo::new("BTCUSDT", d("0.01"), d("0.001"), d("0.001"), d("100"), d("10"), 2);
+
+        let mut order_request = OrderRequest::new(
+            "BTC".to_string(),
+            "USDT".to_string(),
+            OrderSide::Buy,
+            TradeSide::LONG,
+            0,
+            None,
+        );
+
+        order_request.set_price(d("100"));
+        order_request.set_quantity(d("0.05"));
+
+        assert_eq!(
+            symbol_info.adjust(&mut order_request),
+            Err(OrderRejection::NotionalBelowMinimum {
+                notional: d("5"),
+                min_notional: d("10"),
+            }),
+        );
+    }
+}
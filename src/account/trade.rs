@@ -1,11 +1,11 @@
-use std::collections::HashSet;
+use std::collections::BTreeSet;
 use crate::account::order::{Order, OrderSide};
-use crate::account::trader::Trader;
 use crate::candles::candle::Candle;
+use crate::decimal::Decimal;
 use crate::strategies::strategy::Strategy;
 use crate::strategies::strategy_monitor::StrategyMonitor;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum TradeSide {
     LONG,
     SHORT,
@@ -25,24 +25,46 @@ pub fn negative_price_change_ratio(
     1.0 - (current_price / spent)
 }
 
-#[derive(Clone, Debug)]
-pub struct Trade<'a> {
+fn total_filled_amount(orders: &BTreeSet<Order>) -> Decimal {
+    orders
+        .iter()
+        .map(|order| order.average_price() * order.executed_quantity())
+        .sum()
+}
+
+fn total_filled_quantity(orders: &BTreeSet<Order>) -> Decimal {
+    orders
+        .iter()
+        .map(|order| order.executed_quantity())
+        .sum()
+}
+
+fn average_fill_price(orders: &BTreeSet<Order>) -> Decimal {
+    let quantity = total_filled_quantity(orders);
+
+    if quantity.is_zero() {
+        return Decimal::ZERO;
+    }
+
+    total_filled_amount(orders) / quantity
+}
+
+pub struct Trade {
     id: u64,
 
     pub exit_reason: Option<String>,
 
-    pub position: HashSet<Order>,
-    pub exit_orders: HashSet<Order>,
+    pub position: BTreeSet<Order>,
+    pub exit_orders: BTreeSet<Order>,
 
     pub is_placeholder: bool,
 
     pub monitors: Vec<Box<dyn StrategyMonitor>>,
-    pub trader: &'a Trader,
 
-    average_price: f64,
+    average_price: Decimal,
 
-    total_unspent: f64,
-    total_units: f64,
+    total_unspent: Decimal,
+    total_units: Decimal,
 
     ticks: u64,
 
@@ -58,48 +80,52 @@ pub struct Trade<'a> {
     stopped: bool,
 
     finalized: bool,
-    finalized_quantity: f64,
+    finalized_quantity: Decimal,
 
-    actual_profit_loss: f64,
+    actual_profit_loss: Decimal,
     actual_profit_loss_pct: f64,
 
+    // name of the strategy the trade was opened for
+    strategy_name: Option<String>,
+
+    // funding paid (positive) or received (negative) by a futures position
+    funding: Decimal,
+
+    // side of the orders that exit the trade
     side: OrderSide,
 
     strategy_monitors: Vec<Box<dyn StrategyMonitor>>,
 }
 
-impl<'a> Trade<'a> {
+impl Trade {
     pub fn new_from_order(
         id: u64,
         opening_order: Order,
-        trader: &'a Trader,
         opening_strategy: Option<Box<dyn Strategy>>,
+        monitors: Vec<Box<dyn StrategyMonitor>>,
     ) -> Trade {
         let side =
-            if opening_order.side == OrderSide::Buy {
+            if opening_order.order_request.is_buy() {
                 OrderSide::Sell
             } else {
                 OrderSide::Buy
             };
 
-        let trade = Trade::new(
+        let mut trade = Trade::new(
             id,
-            trader,
             side,
             opening_strategy,
-            // todo fix
-            Vec::new(),
+            monitors,
             false,
         );
 
-        // trade.increase_position(id, opening_order, trader);
+        trade.increase_position(opening_order);
 
         trade
     }
 
     pub fn new(
         id: u64,
-        trader: &'a Trader,
         side: OrderSide,
         opening_strategy: Option<Box<dyn Strategy>>,
         monitors: Vec<Box<dyn StrategyMonitor>>,
@@ -108,14 +134,13 @@ impl<'a> Trade<'a> {
         let trade = Trade {
             id,
             exit_reason: None,
-            position: HashSet::new(),
-            exit_orders: HashSet::new(),
+            position: BTreeSet::new(),
+            exit_orders: BTreeSet::new(),
             is_placeholder,
             monitors,
-            trader,
-            average_price: 0.0,
-            total_unspent: 0.0,
-            total_units: 0.0,
+            average_price: Decimal::ZERO,
+            total_unspent: Decimal::ZERO,
+            total_units: Decimal::ZERO,
             ticks: 0,
             max: 0.0,
             min: 0.0,
@@ -126,9 +151,11 @@ impl<'a> Trade<'a> {
             opening_strategy,
             stopped: false,
             finalized: false,
-            finalized_quantity: 0.0,
-            actual_profit_loss: 0.0,
+            finalized_quantity: Decimal::ZERO,
+            actual_profit_loss: Decimal::ZERO,
             actual_profit_loss_pct: 0.0,
+            strategy_name: None,
+            funding: Decimal::ZERO,
             side,
             strategy_monitors: Vec::new(),
         };
@@ -140,12 +167,10 @@ impl<'a> Trade<'a> {
 
     pub fn create_placeholder(
         id: u64,
-        trader: &'a Trader,
         side: OrderSide,
     ) -> Trade {
         Trade::new(
             id,
-            trader,
             side,
             None,
             Vec::new(),
@@ -153,6 +178,315 @@ impl<'a> Trade<'a> {
         )
     }
 
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Renumbers the trade and the orders linked to it, e.g. when the
+    /// trades of several backtests are put together.
+    pub fn set_id(&mut self, id: u64) {
+        self.id = id;
+
+        self.update_orders(|order| {
+            if order.trade_id.is_some() {
+                order.trade_id = Some(id);
+            }
+        });
+    }
+
+    /// Scales the size of the trade by `factor`: the quantities and fees of
+    /// its orders, its funding and its profit or loss. Prices and changes
+    /// stay the same.
+    pub fn scale(&mut self, factor: Decimal) {
+        self.update_orders(|order| {
+            order.set_quantity(order.quantity() * factor);
+            order.set_executed_quantity(order.executed_quantity() * factor);
+            order.set_fees_paid(order.fees_paid() * factor);
+
+            if order.has_partial_fill_details() {
+                order.set_partial_fill_details(order.get_partial_fill_price(), order.get_partial_fill_quantity() * factor);
+            }
+        });
+
+        self.total_unspent = self.total_unspent * factor;
+        self.total_units = self.total_units * factor;
+
+        self.finalized_quantity = self.finalized_quantity * factor;
+
+        self.funding = self.funding * factor;
+        self.actual_profit_loss = self.actual_profit_loss * factor;
+    }
+
+    fn update_orders(&mut self, update: impl Fn(&mut Order)) {
+        for orders in [&mut self.position, &mut self.exit_orders] {
+            *orders = std::mem::take(orders)
+                .into_iter()
+                .map(|mut order| {
+                    update(&mut order);
+
+                    order
+                })
+                .collect();
+        }
+    }
+
+    pub fn average_price(&self) -> Decimal {
+        self.average_price
+    }
+
+    pub fn total_unspent(&self) -> Decimal {
+        self.total_unspent
+    }
+
+    pub fn total_units(&self) -> Decimal {
+        self.total_units
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn min_change(&self) -> f64 {
+        self.min_change
+    }
+
+    pub fn max_change(&self) -> f64 {
+        self.max_change
+    }
+
+    pub fn change(&self) -> f64 {
+        self.change
+    }
+
+    pub fn first_candle(&self) -> Option<&Candle> {
+        self.first_candle.as_ref()
+    }
+
+    pub fn opening_strategy(&self) -> Option<&dyn Strategy> {
+        self.opening_strategy.as_deref()
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    pub fn is_finalized(&self) -> bool {
+        self.finalized
+    }
+
+    pub fn finalized_quantity(&self) -> Decimal {
+        self.finalized_quantity
+    }
+
+    pub fn actual_profit_loss(&self) -> Decimal {
+        self.actual_profit_loss
+    }
+
+    pub fn actual_profit_loss_pct(&self) -> f64 {
+        self.actual_profit_loss_pct
+    }
+
+    pub fn side(&self) -> OrderSide {
+        self.side
+    }
+
+    pub fn strategy_monitors(&self) -> &Vec<Box<dyn StrategyMonitor>> {
+        &self.strategy_monitors
+    }
+
+    pub fn trade_side(&self) -> TradeSide {
+        match self.side {
+            OrderSide::Sell => TradeSide::LONG,
+            OrderSide::Buy => TradeSide::SHORT,
+        }
+    }
+
+    pub fn is_long(&self) -> bool {
+        self.trade_side() == TradeSide::LONG
+    }
+
+    pub fn is_short(&self) -> bool {
+        self.trade_side() == TradeSide::SHORT
+    }
+
+    pub fn assets_symbol(&self) -> Option<&str> {
+        self.position
+            .iter()
+            .next()
+            .map(|order| order.order_request.assets_symbol())
+    }
+
+    pub fn funds_symbol(&self) -> Option<&str> {
+        self.position
+            .iter()
+            .next()
+            .map(|order| order.order_request.funds_symbol())
+    }
+
+    /// Time the first order of the position was placed.
+    pub fn entry_time(&self) -> Option<u64> {
+        self.position
+            .iter()
+            .map(|order| order.order_request.time())
+            .min()
+    }
+
+    /// Time the last exit order was placed.
+    pub fn exit_time(&self) -> Option<u64> {
+        self.exit_orders
+            .iter()
+            .map(|order| order.order_request.time())
+            .max()
+    }
+
+    pub fn exit_price(&self) -> Decimal {
+        average_fill_price(&self.exit_orders)
+    }
+
+    pub fn strategy_name(&self) -> Option<&str> {
+        self.strategy_name.as_deref()
+    }
+
+    pub fn set_strategy_name(&mut self, strategy_name: String) {
+        self.strategy_name = Some(strategy_name);
+    }
+
+    pub fn funding(&self) -> Decimal {
+        self.funding
+    }
+
+    pub fn add_funding(&mut self, amount: Decimal) {
+        if !self.finalized {
+            self.funding += amount;
+        }
+    }
+
+    pub fn fees_paid(&self) -> Decimal {
+        self.position
+            .iter()
+            .chain(self.exit_orders.iter())
+            .map(|order| order.fees_paid())
+            .sum()
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.finalized
+    }
+
+    /// Adds a filled (or partially filled) entry order to the position.
+    pub fn increase_position(&mut self, order: Order) {
+        if order.executed_quantity().is_zero() {
+            return;
+        }
+
+        self.position.replace(order);
+
+        self.average_price = average_fill_price(&self.position);
+        self.update_units();
+    }
+
+    /// Adds an exit order. The trade is finalized once the exits cover the
+    /// whole position.
+    pub fn decrease_position(&mut self, order: Order, exit_reason: &str) {
+        if order.executed_quantity().is_zero() {
+            return;
+        }
+
+        self.exit_orders.replace(order);
+
+        // a stop keeps the reason its monitor gave
+        if !self.stopped {
+            self.exit_reason = Some(exit_reason.to_string());
+        }
+
+        self.update_units();
+
+        if !self.total_units.is_positive() {
+            self.finalize();
+        }
+    }
+
+    /// Closes the position with an order forced by the exchange.
+    pub fn liquidate(&mut self, order: Order) {
+        self.decrease_position(order, "liquidated");
+        self.finalize();
+    }
+
+    fn update_units(&mut self) {
+        self.total_units = total_filled_quantity(&self.position)
+            - total_filled_quantity(&self.exit_orders);
+    }
+
+    /// Updates the price extremes and the change of the trade, in percent,
+    /// with a new candle.
+    pub fn tick(&mut self, candle: &Candle) {
+        if self.finalized {
+            return;
+        }
+
+        self.ticks += 1;
+
+        if self.first_candle.is_none() {
+            self.first_candle = Some(*candle);
+            self.max = candle.close;
+            self.min = candle.close;
+        }
+
+        self.max = self.max.max(candle.close);
+        self.min = self.min.min(candle.close);
+
+        if self.average_price.is_zero() {
+            return;
+        }
+
+        self.change = if self.is_long() {
+            positive_price_change_ratio(self.average_price.to_f64(), candle.close)
+        } else {
+            negative_price_change_ratio(self.average_price.to_f64(), candle.close)
+        } * 100.0;
+
+        self.max_change = self.max_change.max(self.change);
+        self.min_change = self.min_change.min(self.change);
+    }
+
+    pub fn stop(&mut self, exit_reason: &str) {
+        self.stopped = true;
+        self.exit_reason = Some(exit_reason.to_string());
+    }
+
+    fn finalize(&mut self) {
+        if self.finalized {
+            return;
+        }
+
+        let entry_amount = total_filled_amount(&self.position);
+        let exit_amount = total_filled_amount(&self.exit_orders);
+
+        self.actual_profit_loss = if self.is_long() {
+            exit_amount - entry_amount
+        } else {
+            entry_amount - exit_amount
+        } - self.fees_paid() - self.funding;
+
+        self.actual_profit_loss_pct = if entry_amount.is_zero() {
+            0.0
+        } else {
+            (self.actual_profit_loss / entry_amount).to_f64() * 100.0
+        };
+
+        self.finalized_quantity = total_filled_quantity(&self.exit_orders);
+
+        self.finalized = true;
+    }
+
     pub fn init_trade(&mut self) {
         //self.first_candle = Some(self.trader.latest_candle());
         //self.max = self.first_candle.unwrap().close;
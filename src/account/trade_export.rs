@@ -0,0 +1,360 @@
+use std::io::{self, Write};
+use crate::account::order::Order;
+use crate::account::trade::{Trade, TradeSide};
+
+static TRADE_HEADERS: [&str; 16] = [
+    "ID",
+    "SYMBOL",
+    "SIDE",
+    "OPENING_STRATEGY",
+    "ENTRY_TIME",
+    "EXIT_TIME",
+    "ENTRY_PRICE",
+    "EXIT_PRICE",
+    "QUANTITY",
+    "FEES",
+    "FUNDING",
+    "MIN_CHANGE",
+    "MAX_CHANGE",
+    "ACTUAL_PROFIT_LOSS",
+    "ACTUAL_PROFIT_LOSS_PCT",
+    "EXIT_REASON",
+];
+
+static ORDER_HEADERS: [&str; 15] = [
+    "TRADE_ID",
+    "ORDER_ID",
+    "PARENT_ORDER_ID",
+    "ROLE",
+    "SYMBOL",
+    "SIDE",
+    "TRADE_SIDE",
+    "ORDER_TYPE",
+    "STATUS",
+    "TIME",
+    "PRICE",
+    "QUANTITY",
+    "EXECUTED_QUANTITY",
+    "AVERAGE_PRICE",
+    "FEES_PAID",
+];
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    JsonLines,
+}
+
+enum Value {
+    Text(String),
+    Number(String),
+    Empty,
+}
+
+impl Value {
+    fn text(value: &str) -> Value {
+        Value::Text(value.to_string())
+    }
+
+    fn number(value: impl ToString) -> Value {
+        Value::Number(value.to_string())
+    }
+
+    fn optional_number(value: Option<impl ToString>) -> Value {
+        value.map(Value::number).unwrap_or(Value::Empty)
+    }
+
+    fn csv(&self) -> String {
+        match self {
+            Value::Text(text) if text.contains([',', '"', '\n', '\r']) =>
+                format!("\"{}\"", text.replace('"', "\"\"")),
+            Value::Text(text) => text.clone(),
+            Value::Number(number) => number.clone(),
+            Value::Empty => String::new(),
+        }
+    }
+
+    fn json(&self) -> String {
+        match self {
+            Value::Text(text) => json_string(text),
+            // NaN and infinities are not valid JSON numbers
+            Value::Number(number) if number.parse::<f64>().map(|n| n.is_finite()).unwrap_or(false) =>
+                number.clone(),
+            Value::Number(_) | Value::Empty => "null".to_string(),
+        }
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+
+    escaped.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped.push('"');
+
+    escaped
+}
+
+fn write_rows<W: Write>(
+    writer: &mut W,
+    format: ExportFormat,
+    headers: &[&str],
+    rows: Vec<Vec<Value>>,
+) -> io::Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            writeln!(writer, "{}", headers.join(","))?;
+
+            for row in rows {
+                let columns: Vec<String> = row.iter().map(|value| value.csv()).collect();
+
+                writeln!(writer, "{}", columns.join(","))?;
+            }
+        }
+        ExportFormat::JsonLines => {
+            for row in rows {
+                let fields: Vec<String> = headers
+                    .iter()
+                    .zip(row.iter())
+                    .map(|(header, value)| format!("{}:{}", json_string(&header.to_lowercase()), value.json()))
+                    .collect();
+
+                writeln!(writer, "{{{}}}", fields.join(","))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn trade_side_name(trade_side: TradeSide) -> &'static str {
+    match trade_side {
+        TradeSide::LONG => "LONG",
+        TradeSide::SHORT => "SHORT",
+    }
+}
+
+fn trade_row(trade: &Trade) -> Vec<Value> {
+    let symbol = match (trade.assets_symbol(), trade.funds_symbol()) {
+        (Some(assets_symbol), Some(funds_symbol)) => format!("{}{}", assets_symbol, funds_symbol),
+        _ => String::new(),
+    };
+
+    let quantity: f64 = trade.position
+        .iter()
+        .map(|order| order.executed_quantity().to_f64())
+        .sum();
+
+    vec![
+        Value::number(trade.id()),
+        Value::Text(symbol),
+        Value::text(trade_side_name(trade.trade_side())),
+        Value::text(trade.strategy_name().unwrap_or("")),
+        Value::optional_number(trade.entry_time()),
+        Value::optional_number(trade.exit_time()),
+        Value::number(trade.average_price()),
+        Value::number(trade.exit_price()),
+        Value::number(quantity),
+        Value::number(trade.fees_paid()),
+        Value::number(trade.funding()),
+        Value::number(trade.min_change()),
+        Value::number(trade.max_change()),
+        Value::number(trade.actual_profit_loss()),
+        Value::number(trade.actual_profit_loss_pct()),
+        Value::text(trade.exit_reason.as_deref().unwrap_or("")),
+    ]
+}
+
+fn order_row(trade: &Trade, order: &Order, role: &str) -> Vec<Value> {
+    let order_request = &order.order_request;
+
+    vec![
+        Value::number(trade.id()),
+        Value::Text(order.order_id()),
+        order.parent_order_id().map(Value::Text).unwrap_or(Value::Empty),
+        Value::text(role),
+        Value::Text(order_request.symbol()),
+        Value::Text(format!("{:?}", order_request.side()).to_uppercase()),
+        Value::text(trade_side_name(*order_request.trade_side())),
+        Value::Text(format!("{:?}", order_request.order_type()).to_uppercase()),
+        Value::Text(format!("{:?}", order.status()).to_uppercase()),
+        Value::number(order_request.time()),
+        Value::number(order_request.price()),
+        Value::number(order_request.quantity()),
+        Value::number(order.executed_quantity()),
+        Value::number(order.average_price()),
+        Value::number(order.fees_paid()),
+    ]
+}
+
+pub fn trade_headers() -> Vec<String> {
+    TRADE_HEADERS.iter().map(|s| s.to_string()).collect()
+}
+
+pub fn order_headers() -> Vec<String> {
+    ORDER_HEADERS.iter().map(|s| s.to_string()).collect()
+}
+
+/// Writes the finalized trades, one per row or line. Open trades are left
+/// out since their exit is not known yet.
+pub fn export_trades<W: Write>(
+    trades: &[Trade],
+    format: ExportFormat,
+    writer: &mut W,
+) -> io::Result<()> {
+    let rows = trades
+        .iter()
+        .filter(|trade| trade.is_finalized())
+        .map(trade_row)
+        .collect();
+
+    write_rows(writer, format, &TRADE_HEADERS, rows)
+}
+
+/// Writes the orders of the finalized trades, entries first, each linked to
+/// its trade by the trade id. Like `export_trades`, open trades are left
+/// out.
+pub fn export_orders<W: Write>(
+    trades: &[Trade],
+    format: ExportFormat,
+    writer: &mut W,
+) -> io::Result<()> {
+    let mut rows = vec![];
+
+    for trade in trades.iter().filter(|trade| trade.is_finalized()) {
+        for order in trade.position.iter() {
+            rows.push(order_row(trade, order, "ENTRY"));
+        }
+
+        for order in trade.exit_orders.iter() {
+            rows.push(order_row(trade, order, "EXIT"));
+        }
+    }
+
+    write_rows(writer, format, &ORDER_HEADERS, rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::account_manager::AccountManager;
+    use crate::account::order::OrderSide;
+    use crate::account::order_request::OrderRequest;
+    use crate::account::trader::Trader;
+    use crate::account::trading_manager::TradingManager;
+    use crate::candles::candle::Candle;
+    use crate::decimal::Decimal;
+    use crate::indicators::signal::Signal;
+    use crate::simulation::simulated_account::SimulatedAccount;
+    use crate::strategies::strategy::Strategy;
+    use crate::trading_fees::PercentageFee;
+
+    struct Dip;
+
+    impl Strategy for Dip {
+        fn get_signal(&self, _candle: Candle) -> Signal {
+            Signal::NEUTRAL
+        }
+
+        fn name(&self) -> Option<&str> {
+            Some("dip")
+        }
+    }
+
+    fn order_request(side: OrderSide, price: i64, time: u64) -> OrderRequest {
+        let mut order_request = OrderRequest::new(
+            "BTC".to_string(),
+            "USDT".to_string(),
+            side,
+            TradeSide::LONG,
+            time,
+            None,
+        );
+
+        order_request.set_price(Decimal::from(price));
+        order_request.set_quantity(Decimal::ONE);
+
+        order_request
+    }
+
+    #[test]
+    fn escapes_values() {
+        assert_eq!(Value::text("a,\"b\"").csv(), "\"a,\"\"b\"\"\"");
+        assert_eq!(Value::text("a\n\"b\"").json(), "\"a\\n\\\"b\\\"\"");
+        assert_eq!(Value::number(f64::NAN).json(), "null");
+    }
+
+    #[test]
+    fn exports_finalized_trades_with_their_orders() {
+        let mut account = SimulatedAccount::new(PercentageFee::new(Decimal::from(1)));
+
+        account.set_amount("USDT", Decimal::from(1000));
+
+        let mut account_manager = AccountManager::new(account, 0, 0, 1.5);
+        let mut trader = Trader::new(TradingManager::new("BTC".to_string(), "USDT".to_string()));
+
+        let fill = |account_manager: &mut AccountManager<SimulatedAccount>, trader: &mut Trader, time: u64, price: f64| {
+            account_manager.account_mut().accept_candle("BTCUSDT", &Candle::new(time, time + 59_999, price, price, price, price, 1.0));
+
+            for order in account_manager.update_open_orders() {
+                trader.process_finalized_order(account_manager, &order);
+            }
+
+            account_manager.refresh_balances(time + 59_999);
+        };
+
+        account_manager.refresh_balances(0);
+
+        trader.submit_buy(&mut account_manager, order_request(OrderSide::Buy, 100, 0), Some(&Dip)).unwrap();
+        fill(&mut account_manager, &mut trader, 60_000, 100.0);
+
+        account_manager.submit_order(order_request(OrderSide::Sell, 110, 120_000)).unwrap();
+        fill(&mut account_manager, &mut trader, 180_000, 110.0);
+
+        // left out until it is finalized
+        trader.submit_buy(&mut account_manager, order_request(OrderSide::Buy, 100, 240_000), Some(&Dip)).unwrap();
+        fill(&mut account_manager, &mut trader, 300_000, 100.0);
+
+        let trades = trader.into_trades();
+
+        assert_eq!(trades.len(), 2);
+
+        let mut output = vec![];
+
+        export_trades(&trades, ExportFormat::Csv, &mut output).unwrap();
+        export_orders(&trades, ExportFormat::JsonLines, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], TRADE_HEADERS.join(","));
+
+        // 10 of profit, less 1% of fees on both orders
+        let columns: Vec<&str> = lines[1].split(',').collect();
+
+        assert_eq!(&columns[..5], &["1", "BTCUSDT", "LONG", "dip", "0"]);
+        assert_eq!(columns[5], "120000");
+        assert_eq!(columns[6].parse::<Decimal>().unwrap(), Decimal::from(100));
+        assert_eq!(columns[7].parse::<Decimal>().unwrap(), Decimal::from(110));
+        assert_eq!(columns[9].parse::<Decimal>().unwrap(), "2.1".parse().unwrap());
+        assert_eq!(columns[13].parse::<Decimal>().unwrap(), "7.9".parse().unwrap());
+        assert_eq!(columns[15], "exit");
+
+        assert!(lines[2].starts_with("{\"trade_id\":1,\"order_id\":\"1\",\"parent_order_id\":null,\"role\":\"ENTRY\""));
+        assert!(lines[3].starts_with("{\"trade_id\":1,\"order_id\":\"2\",\"parent_order_id\":null,\"role\":\"EXIT\""));
+        assert!(lines[3].contains("\"status\":\"FILLED\""));
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::{BTreeMap, HashMap};
+use crate::account::account_manager::AccountManager;
+use crate::account::order::{Order, OrderSide, OrderStatus, OrderType};
+use crate::account::order_request::OrderRequest;
+use crate::account::symbol_info::OrderRejection;
+use crate::account::trade::{Trade, TradeSide};
+use crate::account::trader::Trader;
+use crate::account::trading_manager::TradingManager;
+use crate::account::context::Context;
+use crate::candles::candle::Candle;
+use crate::candles::candle_feed::CandleSnapshot;
+use crate::candles::candle_history::CandleHistory;
+use crate::client_account::ClientAccount;
+use crate::decimal::{Decimal, DEFAULT_SCALE};
+use crate::indicators::signal::Signal;
+
+/// Entry waiting for the exit order of a switch to be finalized.
+#[derive(Clone, Debug)]
+pub struct PendingSwitch {
+    pub exit_order_id: String,
+
+    pub from_symbol: String,
+    pub to: TradingManager,
+
+    // close of the candle the switch was decided on
+    pub price: Decimal,
+}
+
+/// Moves the funds of open long trades to another symbol of the same funds
+/// when that symbol shows a stronger buy signal and the monitors of the
+/// trade allow it through `StrategyMonitor::allow_trade_switch`.
+///
+/// Both legs are validated before anything is submitted, the entry at the
+/// close of the candle the switch is decided on, and nothing is exited
+/// unless the expected proceeds pay for the entry. The exit is sent first
+/// and the entry only once it is finalized, sized with the proceeds of the
+/// exit, so the same funds are never used twice.
+#[derive(Clone, Debug)]
+pub struct TradeSwitcher {
+    targets: Vec<TradingManager>,
+
+    // percentage of the proceeds left aside for the fees of the entry
+    fee_reserve: Decimal,
+
+    pending: HashMap<String, PendingSwitch>,
+}
+
+fn strength(signal: Option<&Signal>) -> f64 {
+    signal
+        .map(|signal| signal.get_value().1 as f64)
+        .unwrap_or(0.0)
+}
+
+impl TradeSwitcher {
+    pub fn new(targets: Vec<TradingManager>) -> TradeSwitcher {
+        TradeSwitcher {
+            targets,
+
+            fee_reserve: Decimal::new(5, 1),
+
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn targets(&self) -> &Vec<TradingManager> {
+        &self.targets
+    }
+
+    pub fn fee_reserve(&self) -> Decimal {
+        self.fee_reserve
+    }
+
+    pub fn set_fee_reserve(&mut self, fee_reserve: Decimal) {
+        self.fee_reserve = fee_reserve;
+    }
+
+    pub fn pending(&self) -> Vec<&PendingSwitch> {
+        self.pending.values().collect()
+    }
+
+    /// Whether a switch out of or into the symbol is in progress. Traders
+    /// should not open or close trades of these symbols on their own.
+    pub fn is_switching(&self, symbol: &str) -> bool {
+        self.pending
+            .values()
+            .any(|pending| pending.from_symbol == symbol || pending.to.symbol() == symbol)
+    }
+
+    // the strongest buy of a symbol without open trades, if stronger than
+    // the signal of the trader's own symbol
+    fn best_target(
+        &self,
+        trader: &Trader,
+        snapshot: &CandleSnapshot,
+        signals: &BTreeMap<String, Signal>,
+        open_symbols: &[String],
+    ) -> Option<(TradingManager, Candle)> {
+        let current = strength(signals.get(&trader.symbol()));
+
+        self.targets
+            .iter()
+            .filter(|target| target.funds_symbol() == trader.trading_manager().funds_symbol())
+            .filter(|target| !open_symbols.contains(&target.symbol()) && !self.is_switching(&target.symbol()))
+            .filter_map(|target| {
+                let candle = snapshot.get(&target.symbol())?;
+
+                let strength = strength(signals.get(&target.symbol()));
+
+                if strength > 0.0 && strength > current {
+                    Some((target, *candle, strength))
+                } else {
+                    None
+                }
+            })
+            .max_by(|(_, _, a), (_, _, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(target, candle, _)| (target.clone(), candle))
+    }
+
+    fn allows_switch(trade: &Trade, exit_symbol: &str, candle: Candle, candle_ticker: &str, context: &Context) -> bool {
+        !trade.monitors.is_empty()
+            && trade.monitors.iter().all(|monitor| {
+                monitor.allow_trade_switch(trade, exit_symbol.to_string(), candle, candle_ticker.to_string(), context)
+            })
+    }
+
+    /// Looks for a better symbol for the open long trades of a trader and,
+    /// if the monitors agree, submits the exit of the switch. `open_symbols`
+    /// are the symbols other traders hold trades of, `candle_history` the
+    /// candles of the trader's symbol the monitors decide with.
+    ///
+    /// Returns the exit order, or the rejection of either leg in which case
+    /// nothing was submitted.
+    pub fn check<A: ClientAccount>(
+        &mut self,
+        account_manager: &mut AccountManager<A>,
+        trader: &Trader,
+        candle_history: &CandleHistory,
+        snapshot: &CandleSnapshot,
+        signals: &BTreeMap<String, Signal>,
+        open_symbols: &[String],
+    ) -> Result<Option<Order>, OrderRejection> {
+        let from_symbol = trader.symbol();
+
+        if self.is_switching(&from_symbol) {
+            return Ok(None);
+        }
+
+        let trades: Vec<&Trade> = trader.trades()
+            .iter()
+            .filter(|trade| trade.is_long() && !trade.is_finalized())
+            .collect();
+
+        if trades.is_empty() {
+            return Ok(None);
+        }
+
+        let (to, candle) = match self.best_target(trader, snapshot, signals, open_symbols) {
+            Some(target) => target,
+            None => return Ok(None),
+        };
+
+        let allowed = {
+            let context = account_manager.context(trader, None, candle_history);
+
+            trades
+                .iter()
+                .all(|trade| TradeSwitcher::allows_switch(trade, &from_symbol, candle, &to.symbol(), &context))
+        };
+
+        if !allowed {
+            return Ok(None);
+        }
+
+        let units: Decimal = trades.iter().map(|trade| trade.total_units()).sum();
+
+        let held = account_manager.balance(trader.trading_manager().assets_symbol())
+            .map(|balance| balance.free)
+            .unwrap_or(Decimal::ZERO);
+
+        let quantity = units.min(held);
+
+        let mut exit = market_order(trader.trading_manager(), OrderSide::Sell, quantity, snapshot.time());
+
+        account_manager.adjust_order_request(&mut exit)?;
+
+        let exit_price = snapshot.get(&from_symbol)
+            .and_then(|candle| Decimal::from_f64(candle.close, DEFAULT_SCALE))
+            .or_else(|| account_manager.latest_price(&from_symbol))
+            .unwrap_or(Decimal::ZERO);
+
+        let price = Decimal::from_f64(candle.close, DEFAULT_SCALE).unwrap_or(Decimal::ZERO);
+
+        // the entry must be valid, and paid for by the expected proceeds,
+        // before the exit goes out
+        let proceeds = exit.quantity() * exit_price;
+
+        let mut entry = market_order(
+            &to,
+            OrderSide::Buy,
+            self.entry_quantity(proceeds, price),
+            snapshot.time(),
+        );
+
+        entry.set_price(price);
+
+        account_manager.adjust_order_request(&mut entry)?;
+
+        let required = entry.total_order_amount() * (Decimal::ONE + self.fee_reserve / Decimal::from(100));
+
+        if required > proceeds {
+            return Err(OrderRejection::InsufficientFunds {
+                required,
+                available: proceeds,
+            });
+        }
+
+        let order = account_manager.submit_order(exit)?;
+
+        self.pending.insert(order.order_id(), PendingSwitch {
+            exit_order_id: order.order_id(),
+
+            from_symbol,
+            to,
+
+            price,
+        });
+
+        Ok(Some(order))
+    }
+
+    /// Completes the switch a finalized order is the exit of, submitting the
+    /// entry with what the exit returned. Switches whose exit did not fill
+    /// are dropped.
+    pub fn order_finalized<A: ClientAccount>(
+        &mut self,
+        account_manager: &mut AccountManager<A>,
+        order: &Order,
+    ) -> Option<Result<Order, OrderRejection>> {
+        let pending = self.pending.remove(&order.order_id())?;
+
+        if order.executed_quantity().is_zero() || order.status() == OrderStatus::New {
+            return None;
+        }
+
+        let proceeds = order.average_price() * order.executed_quantity() - order.fees_paid();
+
+        let price = account_manager.latest_price(&pending.to.symbol())
+            .unwrap_or(pending.price);
+
+        let entry = market_order(
+            &pending.to,
+            OrderSide::Buy,
+            self.entry_quantity(proceeds, price),
+            order.order_request.time(),
+        );
+
+        Some(account_manager.submit_order(entry))
+    }
+
+    fn entry_quantity(&self, proceeds: Decimal, price: Decimal) -> Decimal {
+        if !price.is_positive() {
+            return Decimal::ZERO;
+        }
+
+        let amount = proceeds * (Decimal::ONE - self.fee_reserve / Decimal::from(100));
+
+        (amount / price).truncate(DEFAULT_SCALE)
+    }
+}
+
+fn market_order(trading_manager: &TradingManager, side: OrderSide, quantity: Decimal, time: u64) -> OrderRequest {
+    let mut order_request = OrderRequest::new(
+        trading_manager.assets_symbol().to_string(),
+        trading_manager.funds_symbol().to_string(),
+        side,
+        TradeSide::LONG,
+        time,
+        None,
+    );
+
+    order_request.set_order_type(OrderType::Market);
+    order_request.set_quantity(quantity);
+
+    order_request
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::symbol_info::SymbolInfo;
+    use crate::candles::candle_feed::CandleFeed;
+    use crate::simulation::simulated_account::SimulatedAccount;
+    use crate::strategies::indicator::Indicator;
+    use crate::strategies::indicator_group::IndicatorGroup;
+    use crate::strategies::strategy_monitor::StrategyMonitor;
+    use crate::trading_fees::PercentageFee;
+
+    struct SwitchWhenFunded;
+
+    impl IndicatorGroup for SwitchWhenFunded {
+        fn is_initialized(&self) -> bool {
+            true
+        }
+
+        fn get_indicators(&self) -> Vec<Box<dyn Indicator>> {
+            vec![]
+        }
+
+        fn candle_accumulated(&self, _candle: &Candle) {}
+    }
+
+    // lets trades switch once the funds balance is known
+    impl StrategyMonitor for SwitchWhenFunded {
+        fn allow_trade_switch(&self, _trade: &Trade, _exit_symbol: String, _candle: Candle, _candle_ticker: String, context: &Context) -> bool {
+            context.funds().is_some()
+        }
+    }
+
+    fn candle(close_time: u64, price: f64) -> Candle {
+        Candle::new(close_time - 59_999, close_time, price, price, price, price, 1.0)
+    }
+
+    fn process_orders(
+        account_manager: &mut AccountManager<SimulatedAccount>,
+        traders: &mut [&mut Trader],
+        switcher: &mut TradeSwitcher,
+    ) {
+        for order in account_manager.update_open_orders() {
+            for trader in traders.iter_mut() {
+                trader.process_finalized_order(account_manager, &order);
+            }
+
+            if let Some(entry) = switcher.order_finalized(account_manager, &order) {
+                entry.unwrap();
+            }
+        }
+
+        account_manager.refresh_balances(0);
+    }
+
+    #[test]
+    fn switches_to_stronger_signal() {
+        let btc = TradingManager::new("BTC".to_string(), "USDT".to_string());
+        let eth = TradingManager::new("ETH".to_string(), "USDT".to_string());
+
+        let mut account = SimulatedAccount::new(PercentageFee::new(Decimal::ZERO));
+
+        account.set_amount("USDT", Decimal::from(1000));
+        account.accept_candle("BTCUSDT", &candle(59_999, 100.0));
+
+        let mut account_manager = AccountManager::new(account, 0, 0, 1.5);
+
+        let mut btc_trader = Trader::new(btc.clone());
+        let mut eth_trader = Trader::new(eth.clone());
+        let mut switcher = TradeSwitcher::new(vec![btc.clone(), eth.clone()]);
+
+        account_manager.submit_order(market_order(&btc, OrderSide::Buy, Decimal::from(10), 59_999)).unwrap();
+        account_manager.account_mut().accept_candle("BTCUSDT", &candle(119_999, 100.0));
+
+        process_orders(&mut account_manager, &mut [&mut btc_trader, &mut eth_trader], &mut switcher);
+
+        // without monitors the trade is never switched
+        assert!(btc_trader.trades()[0].monitors.is_empty());
+
+        btc_trader.trades_mut()[0].monitors.push(Box::new(SwitchWhenFunded));
+
+        let mut feed = CandleFeed::new();
+
+        feed.add_candles("BTCUSDT", &[candle(119_999, 100.0)]);
+        feed.add_candles("ETHUSDT", &[candle(119_999, 50.0)]);
+
+        let snapshot = feed.next().unwrap();
+
+        let mut signals = BTreeMap::new();
+
+        signals.insert("BTCUSDT".to_string(), Signal::NEUTRAL);
+        signals.insert("ETHUSDT".to_string(), Signal::BUY);
+
+        // an entry of at least 2000 can't be paid for with about 1000 of
+        // proceeds, nothing is exited
+        account_manager.add_symbol_info(SymbolInfo::new(
+            "ETHUSDT",
+            Decimal::ZERO,
+            Decimal::ZERO,
+            Decimal::ZERO,
+            Decimal::ZERO,
+            Decimal::from(2000),
+            2,
+        ));
+
+        assert!(matches!(
+            switcher.check(&mut account_manager, &btc_trader, &CandleHistory::new(10), &snapshot, &signals, &[]),
+            Err(OrderRejection::NotionalBelowMinimum { .. }),
+        ));
+        assert!(account_manager.open_orders().is_empty());
+
+        account_manager.add_symbol_info(SymbolInfo::new(
+            "ETHUSDT",
+            Decimal::ZERO,
+            Decimal::ZERO,
+            Decimal::ZERO,
+            Decimal::ZERO,
+            Decimal::from(10),
+            2,
+        ));
+
+        let exit = switcher.check(&mut account_manager, &btc_trader, &CandleHistory::new(10), &snapshot, &signals, &[]).unwrap();
+
+        assert!(exit.is_some());
+        assert!(switcher.is_switching("ETHUSDT"));
+
+        account_manager.account_mut().accept_candle("BTCUSDT", &candle(179_999, 110.0));
+        account_manager.account_mut().accept_candle("ETHUSDT", &candle(179_999, 50.0));
+
+        process_orders(&mut account_manager, &mut [&mut btc_trader, &mut eth_trader], &mut switcher);
+
+        assert!(btc_trader.trades().is_empty());
+        assert!(switcher.pending().is_empty());
+
+        account_manager.account_mut().accept_candle("ETHUSDT", &candle(239_999, 50.0));
+
+        process_orders(&mut account_manager, &mut [&mut btc_trader, &mut eth_trader], &mut switcher);
+
+        // 1100 of proceeds, minus the 0.5% fee reserve, at 50
+        assert_eq!(eth_trader.trades()[0].total_units(), Decimal::new(2189, 2));
+        assert!(account_manager.balance("USDT").unwrap().free >= Decimal::ZERO);
+    }
+}
@@ -1,7 +1,355 @@
+use std::collections::HashMap;
+use crate::account::account_manager::AccountManager;
+use crate::account::margin::MarginEvent;
+use crate::account::order::Order;
+use crate::account::order_request::OrderRequest;
+use crate::account::position::FundingPayment;
+use crate::account::position_sizing::{PositionSizer, SizingContext};
+use crate::account::symbol_info::OrderRejection;
+use crate::account::trade::Trade;
 use crate::account::trading_manager::TradingManager;
+use crate::candles::candle::Candle;
+use crate::client_account::ClientAccount;
+use crate::decimal::{Decimal, DEFAULT_SCALE};
+use crate::strategies::strategy::Strategy;
+use crate::strategies::strategy_monitor::StrategyMonitor;
 
+// name of the strategy an entry was made for and the monitors of the trade
+// it opens
+type StrategyOrder = (Option<String>, Vec<Box<dyn StrategyMonitor>>);
+
+/// Keeps track of the trades opened on the symbol of its trading manager.
 pub struct Trader {
     trading_manager: TradingManager,
 
+    trades: Vec<Trade>,
+    finalized_trades: Vec<Trade>,
+
+    latest_candle: Option<Candle>,
+
+    position_sizer: Option<PositionSizer>,
+
+    // strategy of each submitted entry, by order id
+    strategy_orders: HashMap<String, StrategyOrder>,
+}
+
+impl Trader {
+    pub fn new(
+        trading_manager: TradingManager,
+    ) -> Trader {
+        Trader {
+            trading_manager,
+
+            trades: vec![],
+            finalized_trades: vec![],
+
+            latest_candle: None,
+
+            position_sizer: None,
+
+            strategy_orders: HashMap::new(),
+        }
+    }
+
+    pub fn trading_manager(&self) -> &TradingManager {
+        &self.trading_manager
+    }
+
+    /// Moves the trader to another symbol, e.g. once its trades were
+    /// switched to it.
+    pub fn set_trading_manager(&mut self, trading_manager: TradingManager) {
+        self.trading_manager = trading_manager;
+    }
+
+    pub fn trades(&self) -> &Vec<Trade> {
+        &self.trades
+    }
+
+    pub fn trades_mut(&mut self) -> &mut Vec<Trade> {
+        &mut self.trades
+    }
+
+    pub fn finalized_trades(&self) -> &Vec<Trade> {
+        &self.finalized_trades
+    }
+
+    /// Consumes the trader, returning its finalized trades followed by the
+    /// ones still open.
+    pub fn into_trades(self) -> Vec<Trade> {
+        let mut trades = self.finalized_trades;
+
+        trades.extend(self.trades);
+
+        trades
+    }
+
+    pub fn latest_candle(&self) -> Option<&Candle> {
+        self.latest_candle.as_ref()
+    }
+
+    pub fn symbol(&self) -> String {
+        self.trading_manager.symbol()
+    }
+
+    pub fn position_sizer(&self) -> Option<&PositionSizer> {
+        self.position_sizer.as_ref()
+    }
+
+    pub fn set_position_sizer(&mut self, position_sizer: PositionSizer) {
+        self.position_sizer = Some(position_sizer);
+    }
+
+    /// Updates the open trades with a new candle.
+    pub fn tick(&mut self, candle: &Candle) {
+        for trade in self.trades.iter_mut() {
+            trade.tick(candle);
+        }
+
+        if let Some(position_sizer) = self.position_sizer.as_ref() {
+            position_sizer.accept_candle(candle);
+        }
+
+        self.latest_candle = Some(*candle);
+    }
+
+    /// Equity, price and trade statistics the position sizer decides with.
+    pub fn sizing_context<A: ClientAccount>(
+        &self,
+        account_manager: &AccountManager<A>,
+        price: Decimal,
+    ) -> SizingContext {
+        let wins: Vec<f64> = self.finalized_trades
+            .iter()
+            .map(|trade| trade.actual_profit_loss_pct())
+            .filter(|pct| *pct > 0.0)
+            .collect();
+
+        let losses: Vec<f64> = self.finalized_trades
+            .iter()
+            .map(|trade| trade.actual_profit_loss_pct())
+            .filter(|pct| *pct <= 0.0)
+            .collect();
+
+        let average = |values: &Vec<f64>| {
+            if values.is_empty() {
+                0.0
+            } else {
+                values.iter().sum::<f64>() / values.len() as f64
+            }
+        };
+
+        let trade_count = self.finalized_trades.len();
+
+        SizingContext {
+            equity: account_manager.equity(self.trading_manager.funds_symbol()),
+            price,
+
+            atr: self.position_sizer
+                .as_ref()
+                .and_then(|position_sizer| position_sizer.atr()),
+
+            trade_count,
+            win_rate: if trade_count == 0 { 0.0 } else { wins.len() as f64 / trade_count as f64 },
+            average_win: average(&wins),
+            average_loss: average(&losses),
+        }
+    }
+
+    /// Value of the open trades of a strategy at the given price.
+    pub fn strategy_exposure(&self, strategy_name: &str, price: Decimal) -> Decimal {
+        self.trades
+            .iter()
+            .filter(|trade| trade.strategy_name() == Some(strategy_name))
+            .map(|trade| trade.total_units() * price)
+            .sum()
+    }
+
+    /// Submits an entry, a buy or a short sell, sized by the position sizer,
+    /// if one is set, instead of the quantity of the request. Entries the
+    /// sizer leaves no room for are rejected. The trade the entry opens is
+    /// attributed to the strategy and gets its monitors.
+    pub fn submit_buy<A: ClientAccount>(
+        &mut self,
+        account_manager: &mut AccountManager<A>,
+        mut order_request: OrderRequest,
+        strategy: Option<&dyn Strategy>,
+    ) -> Result<Order, OrderRejection> {
+        let strategy_name = strategy.and_then(|strategy| strategy.name());
+
+        if let Some(position_sizer) = self.position_sizer.as_ref() {
+            let latest_close = self.latest_candle
+                .and_then(|candle| Decimal::from_f64(candle.close, DEFAULT_SCALE));
+
+            let price = if order_request.price().is_positive() {
+                order_request.price()
+            } else {
+                account_manager.latest_price(&order_request.symbol())
+                    .or(latest_close)
+                    .unwrap_or(Decimal::ZERO)
+            };
+
+            let context = self.sizing_context(account_manager, price);
+
+            let asset_exposure = account_manager.asset_exposure(
+                order_request.assets_symbol(),
+                order_request.funds_symbol(),
+            );
+
+            let strategy_exposure = strategy_name
+                .map(|strategy_name| self.strategy_exposure(strategy_name, price))
+                .unwrap_or(Decimal::ZERO);
+
+            let quantity = position_sizer.size(&context, asset_exposure, strategy_exposure);
+
+            if !quantity.is_positive() {
+                return Err(OrderRejection::ExposureLimitReached {
+                    symbol: order_request.symbol(),
+                });
+            }
+
+            order_request.set_quantity(quantity);
+        }
+
+        let order = account_manager.submit_order(order_request)?;
+
+        if let Some(strategy) = strategy {
+            self.strategy_orders.insert(
+                order.order_id(),
+                (strategy_name.map(|name| name.to_string()), strategy.monitors()),
+            );
+        }
+
+        Ok(order)
+    }
+
+    /// Attributes an order to the strategy of the finalized trade that
+    /// `exit_order_id` closed, whose monitors move to the trade the order
+    /// opens, e.g. the entry of a switch.
+    pub fn inherit_strategy(&mut self, exit_order_id: &str, order_id: &str) {
+        let trade = self.finalized_trades
+            .iter_mut()
+            .rev()
+            .find(|trade| trade.exit_orders.iter().any(|order| order.order_id() == exit_order_id));
+
+        if let Some(trade) = trade {
+            let strategy_name = trade.strategy_name().map(|name| name.to_string());
+            let monitors = std::mem::take(&mut trade.monitors);
+
+            self.strategy_orders.insert(order_id.to_string(), (strategy_name, monitors));
+        }
+    }
+
+    /// Adds a finalized order, and any filled attachments, to the trades of
+    /// this trader. Buys of long trades and short sells open a trade or add
+    /// to the open one, the other orders reduce it.
+    pub fn process_finalized_order<A: ClientAccount>(
+        &mut self,
+        account_manager: &mut AccountManager<A>,
+        order: &Order,
+    ) {
+        if order.order_request.symbol() != self.symbol() {
+            return;
+        }
+
+        self.process_order(account_manager, order, "exit");
+
+        for attachment in order.attachments() {
+            self.process_order(account_manager, attachment, "exit_attachment");
+        }
+
+        self.remove_finalized_trades();
+    }
+
+    /// Adds funding paid or received to the open trades of the symbol.
+    pub fn process_funding_payment(&mut self, funding_payment: &FundingPayment) {
+        if funding_payment.symbol() != self.symbol() {
+            return;
+        }
+
+        for trade in self.trades.iter_mut() {
+            trade.add_funding(funding_payment.amount());
+        }
+    }
+
+    /// Closes the trades a liquidation forced out of their position.
+    pub fn handle_margin_event(&mut self, event: &MarginEvent) {
+        if let MarginEvent::Liquidation(position, order) = event {
+            if position.symbol() != self.symbol() {
+                return;
+            }
+
+            let is_long = order.order_request.is_long();
+
+            for trade in self.trades.iter_mut().filter(|trade| trade.is_long() == is_long) {
+                let mut order = (**order).clone();
+
+                order.set_trade(trade);
+
+                trade.liquidate(order);
+            }
+
+            self.remove_finalized_trades();
+        }
+    }
+
+    fn process_order<A: ClientAccount>(
+        &mut self,
+        account_manager: &mut AccountManager<A>,
+        order: &Order,
+        exit_reason: &str,
+    ) {
+        if order.executed_quantity().is_zero() {
+            return;
+        }
+
+        let mut order = order.clone();
+
+        let (strategy_name, monitors) = self.strategy_orders
+            .remove(&order.order_id())
+            .unwrap_or((None, vec![]));
+
+        let opens = order.order_request.is_long_buy() || order.order_request.is_short_sell();
+
+        let is_long = order.order_request.is_long();
+
+        let open_trade = self.trades
+            .iter_mut()
+            .find(|trade| trade.is_long() == is_long);
+
+        match open_trade {
+            Some(trade) => {
+                order.set_trade(trade);
+
+                if opens {
+                    trade.increase_position(order);
+                } else {
+                    trade.decrease_position(order, exit_reason);
+                }
+            }
+            None if opens => {
+                let mut trade = Trade::new_from_order(
+                    account_manager.next_trade_id(),
+                    order,
+                    None,
+                    monitors,
+                );
+
+                if let Some(strategy_name) = strategy_name {
+                    trade.set_strategy_name(strategy_name);
+                }
+
+                self.trades.push(trade);
+            }
+            None => (),
+        }
+    }
+
+    fn remove_finalized_trades(&mut self) {
+        let (finalized, open): (Vec<Trade>, Vec<Trade>) = self.trades
+            .drain(..)
+            .partition(|trade| trade.is_finalized());
 
+        self.trades = open;
+        self.finalized_trades.extend(finalized);
+    }
 }
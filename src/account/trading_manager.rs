@@ -1,3 +1,29 @@
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct TradingManager {
+    assets_symbol: String,
+    funds_symbol: String,
+}
+
+impl TradingManager {
+    pub fn new(
+        assets_symbol: String,
+        funds_symbol: String,
+    ) -> TradingManager {
+        TradingManager {
+            assets_symbol,
+            funds_symbol,
+        }
+    }
+
+    pub fn assets_symbol(&self) -> &str {
+        &self.assets_symbol
+    }
+
+    pub fn funds_symbol(&self) -> &str {
+        &self.funds_symbol
+    }
 
+    pub fn symbol(&self) -> String {
+        format!("{}{}", self.assets_symbol, self.funds_symbol)
+    }
 }
@@ -1,4 +1,3 @@
-use std::collections::HashMap;
 use crate::candles::candle::Candle;
 
 static MINUTE_MS: u64 = 60 * 1000;
@@ -23,9 +22,9 @@ impl Aggregator {
         }
     }
 
-    fn aggregate(&mut self, candle: &Candle) {
+    pub fn aggregate(&mut self, candle: &Candle) {
         if self.partial.is_none() {
-            self.partial = Some(candle.clone());
+            self.partial = Some(*candle);
             self.full = None;
 
             return;
@@ -75,20 +74,20 @@ impl Aggregator {
             return;
         }
 
-        self.full = Some(candle.clone());
+        self.full = Some(*candle);
 
         self.partial = None;
     }
 
-    fn set_full(&mut self, full: &Candle) {
-        self.full = Some(full.clone());
+    pub fn set_full(&mut self, full: &Candle) {
+        self.full = Some(*full);
     }
 
-    fn get_full(&self) -> Option<Candle> {
+    pub fn get_full(&self) -> Option<Candle> {
         self.full
     }
 
-    fn get_partial(&self) -> Option<Candle> {
+    pub fn get_partial(&self) -> Option<Candle> {
         self.partial
     }
 }
@@ -1,6 +1,7 @@
 use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Debug)]
 pub struct Candle {
     pub open_time: u64,
     pub close_time: u64,
@@ -11,6 +12,8 @@ pub struct Candle {
     pub close: f64,
 
     pub volume: f64,
+
+    pub merged: bool,
 }
 
 impl Candle {
@@ -32,6 +35,8 @@ impl Candle {
             low,
             close,
             volume,
+
+            merged: false,
         }
     }
 
@@ -97,6 +102,15 @@ impl PartialOrd for Candle {
             return Some(Ordering::Less);
         }
 
-        return Some(Ordering::Greater);
+        Some(Ordering::Greater)
+    }
+}
+
+impl Eq for Candle {}
+
+impl Hash for Candle {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.open_time.hash(state);
+        self.close_time.hash(state);
     }
 }
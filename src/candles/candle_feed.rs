@@ -0,0 +1,177 @@
+use std::collections::{BTreeMap, VecDeque};
+use crate::candles::candle::Candle;
+
+/// Latest candle of every symbol of a feed at a point in time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CandleSnapshot {
+    time: u64,
+
+    latest: BTreeMap<String, Candle>,
+
+    // symbols with a candle closing at `time`
+    updated: Vec<String>,
+}
+
+impl CandleSnapshot {
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&Candle> {
+        self.latest.get(symbol)
+    }
+
+    pub fn latest(&self) -> &BTreeMap<String, Candle> {
+        &self.latest
+    }
+
+    pub fn updated(&self) -> &Vec<String> {
+        &self.updated
+    }
+
+    pub fn is_updated(&self, symbol: &str) -> bool {
+        self.updated.iter().any(|updated| updated == symbol)
+    }
+
+    /// Adds a closed candle of a symbol, e.g. as they come from a live
+    /// stream. Candles closing later than the snapshot move it forward,
+    /// older ones are ignored.
+    pub fn accept(&mut self, symbol: &str, candle: Candle) {
+        if candle.close_time < self.time {
+            return;
+        }
+
+        if candle.close_time > self.time {
+            self.time = candle.close_time;
+            self.updated.clear();
+        }
+
+        self.latest.insert(symbol.to_string(), candle);
+
+        if !self.is_updated(symbol) {
+            self.updated.push(symbol.to_string());
+        }
+    }
+
+    /// True when every symbol has had at least one candle.
+    pub fn has_all<S: AsRef<str>>(&self, symbols: &[S]) -> bool {
+        symbols.iter().all(|symbol| self.latest.contains_key(symbol.as_ref()))
+    }
+}
+
+/// Merges the candle streams of several symbols by close time. Each step
+/// takes every candle closing at the earliest pending close time, so
+/// symbols with the same interval move together, and yields the snapshot
+/// of the latest candles.
+#[derive(Clone, Debug, Default)]
+pub struct CandleFeed {
+    streams: BTreeMap<String, VecDeque<Candle>>,
+
+    snapshot: CandleSnapshot,
+}
+
+impl CandleFeed {
+    pub fn new() -> CandleFeed {
+        CandleFeed::default()
+    }
+
+    /// Adds candles to the stream of a symbol, sorted by close time.
+    /// Candles closing before the current time of the feed are dropped.
+    pub fn add_candles(&mut self, symbol: &str, candles: &[Candle]) {
+        let stream = self.streams.entry(symbol.to_string()).or_default();
+
+        stream.extend(candles.iter().copied());
+
+        stream.make_contiguous().sort_by_key(|candle| candle.close_time);
+
+        let time = self.snapshot.time;
+        let started = !self.snapshot.latest.is_empty();
+
+        stream.retain(|candle| !started || candle.close_time > time);
+    }
+
+    /// Adds one candle, e.g. as they come from a live stream.
+    pub fn push(&mut self, symbol: &str, candle: Candle) {
+        self.add_candles(symbol, &[candle]);
+    }
+
+    pub fn symbols(&self) -> Vec<String> {
+        self.streams.keys().cloned().collect()
+    }
+
+    pub fn snapshot(&self) -> &CandleSnapshot {
+        &self.snapshot
+    }
+
+    pub fn pending(&self) -> usize {
+        self.streams.values().map(|stream| stream.len()).sum()
+    }
+
+    fn next_close_time(&self) -> Option<u64> {
+        self.streams
+            .values()
+            .filter_map(|stream| stream.front())
+            .map(|candle| candle.close_time)
+            .min()
+    }
+}
+
+impl Iterator for CandleFeed {
+    type Item = CandleSnapshot;
+
+    fn next(&mut self) -> Option<CandleSnapshot> {
+        let time = self.next_close_time()?;
+
+        self.snapshot.time = time;
+        self.snapshot.updated.clear();
+
+        for (symbol, stream) in self.streams.iter_mut() {
+            while stream.front().map(|candle| candle.close_time) == Some(time) {
+                let candle = stream.pop_front().unwrap();
+
+                self.snapshot.latest.insert(symbol.clone(), candle);
+
+                if !self.snapshot.updated.contains(symbol) {
+                    self.snapshot.updated.push(symbol.clone());
+                }
+            }
+        }
+
+        Some(self.snapshot.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(close_time: u64, close: f64) -> Candle {
+        Candle::new(close_time - 59_999, close_time, close, close, close, close, 1.0)
+    }
+
+    #[test]
+    fn merges_by_close_time() {
+        let mut feed = CandleFeed::new();
+
+        feed.add_candles("BTCUSDT", &[candle(119_999, 2.0), candle(59_999, 1.0)]);
+        feed.add_candles("ETHUSDT", &[candle(59_999, 10.0), candle(179_999, 30.0)]);
+
+        let first = feed.next().unwrap();
+
+        assert_eq!(first.time(), 59_999);
+        assert!(first.has_all(&["BTCUSDT", "ETHUSDT"]));
+        assert_eq!(first.updated().len(), 2);
+
+        let second = feed.next().unwrap();
+
+        assert_eq!(second.updated(), &vec!["BTCUSDT".to_string()]);
+        assert_eq!(second.get("BTCUSDT").unwrap().close, 2.0);
+        assert_eq!(second.get("ETHUSDT").unwrap().close, 10.0);
+
+        feed.push("BTCUSDT", candle(59_999, 0.5));
+
+        assert_eq!(feed.pending(), 1);
+        assert_eq!(feed.next().unwrap().time(), 179_999);
+        assert!(feed.next().is_none());
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::{BTreeMap, VecDeque};
+use crate::candles::candle::Candle;
+
+// candles of one interval, built by merging the candles accepted
+#[derive(Clone, Debug, Default)]
+struct IntervalWindow {
+    closed: VecDeque<Candle>,
+    partial: Option<Candle>,
+}
+
+/// Rolling windows of the latest candles: the candles as accepted, and the
+/// same candles merged into each added interval (in milliseconds). Every
+/// window keeps at most `capacity` candles.
+#[derive(Clone, Debug)]
+pub struct CandleHistory {
+    capacity: usize,
+
+    candles: VecDeque<Candle>,
+
+    intervals: BTreeMap<u64, IntervalWindow>,
+}
+
+impl CandleHistory {
+    pub fn new(capacity: usize) -> CandleHistory {
+        CandleHistory {
+            capacity: capacity.max(1),
+
+            candles: VecDeque::new(),
+
+            intervals: BTreeMap::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn add_interval(&mut self, interval: u64) {
+        if interval > 0 {
+            self.intervals.entry(interval).or_default();
+        }
+    }
+
+    pub fn intervals(&self) -> Vec<u64> {
+        self.intervals.keys().copied().collect()
+    }
+
+    pub fn accept(&mut self, candle: &Candle) {
+        if let Some(latest) = self.candles.back() {
+            if candle.close_time <= latest.close_time {
+                return;
+            }
+        }
+
+        push(&mut self.candles, *candle, self.capacity);
+
+        for (interval, window) in self.intervals.iter_mut() {
+            let bucket = candle.open_time / interval;
+
+            window.partial = match window.partial {
+                Some(partial) if partial.open_time / interval == bucket => Some(partial.merge(candle)),
+                Some(partial) => {
+                    push(&mut window.closed, partial, self.capacity);
+
+                    Some(*candle)
+                }
+                None => Some(*candle),
+            };
+
+            // closes the interval right away when the candle ends it
+            if let Some(partial) = window.partial {
+                if (candle.close_time + 1) / interval > bucket {
+                    push(&mut window.closed, partial, self.capacity);
+
+                    window.partial = None;
+                }
+            }
+        }
+    }
+
+    /// Candles as accepted, oldest first.
+    pub fn candles(&self) -> &VecDeque<Candle> {
+        &self.candles
+    }
+
+    pub fn latest(&self) -> Option<&Candle> {
+        self.candles.back()
+    }
+
+    /// Closed candles of an interval, oldest first.
+    pub fn window(&self, interval: u64) -> Option<&VecDeque<Candle>> {
+        self.intervals
+            .get(&interval)
+            .map(|window| &window.closed)
+    }
+
+    /// Candle of an interval that is still being built.
+    pub fn partial(&self, interval: u64) -> Option<&Candle> {
+        self.intervals
+            .get(&interval)
+            .and_then(|window| window.partial.as_ref())
+    }
+}
+
+fn push(window: &mut VecDeque<Candle>, candle: Candle, capacity: usize) {
+    window.push_back(candle);
+
+    while window.len() > capacity {
+        window.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_rolling_windows() {
+        let mut history = CandleHistory::new(2);
+
+        history.add_interval(180_000);
+
+        for i in 0..7u64 {
+            let close = i as f64;
+
+            history.accept(&Candle::new(i * 60_000, i * 60_000 + 59_999, close, close, close, close, 1.0));
+        }
+
+        assert_eq!(history.candles().len(), 2);
+        assert_eq!(history.latest().unwrap().close, 6.0);
+
+        let window = history.window(180_000).unwrap();
+
+        assert_eq!(window.len(), 2);
+        assert_eq!((window[0].open, window[0].close, window[0].volume), (0.0, 2.0, 3.0));
+        assert_eq!((window[1].open, window[1].close), (3.0, 5.0));
+        assert_eq!(history.partial(180_000).unwrap().close, 6.0);
+    }
+}
@@ -1,2 +1,4 @@
 pub mod aggregator;
 pub mod candle;
+pub mod candle_feed;
+pub mod candle_history;
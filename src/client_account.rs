@@ -1,24 +1,77 @@
 use crate::account::order_request::OrderRequest;
 use crate::account::order::Order;
 use crate::account::balance::Balance;
+use crate::account::orderbook::OrderBook;
+use crate::account::position::{AccountMode, FundingPayment, Position};
 use std::collections::HashMap;
+use crate::candles::candle::Candle;
+use crate::decimal::Decimal;
 
 pub trait ClientAccount {
-    fn execute_order(order_details: OrderRequest) -> Order;
+    fn execute_order(&mut self, order_details: OrderRequest) -> Order;
 
-    fn update_balances(force: bool) -> HashMap<String, Balance>;
+    /// Submits orders of which only one may fill, e.g. the take profit and
+    /// stop loss of a position: once one of them fills or is cancelled, the
+    /// others are cancelled. Accounts without such order lists submit each
+    /// order on its own, leaving the cancellations to the caller.
+    fn execute_oco(&mut self, order_details: Vec<OrderRequest>) -> Vec<Order> {
+        order_details
+            .into_iter()
+            .map(|order_request| self.execute_order(order_request))
+            .collect()
+    }
+
+    fn update_balances(&mut self, force: bool) -> HashMap<String, Balance>;
 
     //fn get_order_book(symbol: String, depth: u64) -> OrderBook;
 
-    fn update_order_status(order: Order);
+    fn update_order_status(&mut self, order: &mut Order);
 
-    fn cancel(order: Order);
+    fn cancel(&mut self, order: &mut Order);
+
+    /// Closes a position below its maintenance margin. `liquidation_fee` is
+    /// the percentage of the closed amount the exchange charges on top of
+    /// the trading fees.
+    fn liquidate(&mut self, order_details: OrderRequest, _liquidation_fee: Decimal) -> Order {
+        self.execute_order(order_details)
+    }
 
-    fn is_simulated() -> bool {
+    fn is_simulated(&self) -> bool {
         false
     }
 
-    fn margin_reserve_percentage() -> u64 {
+    /// Market data seen by the runtime. Exchanges know the market already,
+    /// simulated accounts fill their orders with it.
+    fn on_candle(&mut self, _symbol: &str, _candle: &Candle) {}
+
+    fn on_order_book(&mut self, _order_book: &OrderBook) {}
+
+    /// Milliseconds the exchange asked to wait before sending more requests,
+    /// if it rejected one for exceeding its rate limits since the last call.
+    fn retry_after(&mut self) -> Option<u64> {
+        None
+    }
+
+    fn margin_reserve_percentage(&self) -> u64 {
         150
     }
+
+    fn account_mode(&self) -> AccountMode {
+        AccountMode::Spot
+    }
+
+    /// Open futures positions by symbol.
+    fn update_positions(&mut self) -> HashMap<String, Position> {
+        HashMap::new()
+    }
+
+    /// Funding paid or received since the last call.
+    fn funding_payments(&mut self) -> Vec<FundingPayment> {
+        vec![]
+    }
+
+    /// Orders the exchange closed positions with since the last call.
+    fn liquidations(&mut self) -> Vec<Order> {
+        vec![]
+    }
 }
@@ -0,0 +1,490 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+/// Largest number of decimals kept by multiplications and divisions.
+pub const MAX_SCALE: u32 = 18;
+
+/// Scale used when converting prices coming from candles and indicators.
+pub const DEFAULT_SCALE: u32 = 8;
+
+/// Fixed-point decimal number: `mantissa * 10^-scale`.
+///
+/// Additions, subtractions and multiplications are exact (multiplications
+/// are rounded only past `MAX_SCALE` decimals), divisions are rounded half
+/// away from zero to `MAX_SCALE` decimals.
+#[derive(Copy, Clone, Default)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseDecimalError {
+    input: String,
+}
+
+impl fmt::Display for ParseDecimalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid decimal '{}'", self.input)
+    }
+}
+
+impl std::error::Error for ParseDecimalError {}
+
+fn pow10(exponent: u32) -> i128 {
+    10i128.pow(exponent)
+}
+
+// divides rounding half away from zero
+fn div_round(numerator: i128, denominator: i128) -> i128 {
+    let quotient = numerator / denominator;
+    let remainder = numerator % denominator;
+
+    if remainder.abs() * 2 >= denominator.abs() {
+        if (numerator < 0) == (denominator < 0) {
+            quotient + 1
+        } else {
+            quotient - 1
+        }
+    } else {
+        quotient
+    }
+}
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal { mantissa: 0, scale: 0 };
+    pub const ONE: Decimal = Decimal { mantissa: 1, scale: 0 };
+
+    pub fn new(
+        mantissa: i128,
+        scale: u32,
+    ) -> Decimal {
+        Decimal {
+            mantissa,
+            scale,
+        }
+    }
+
+    /// Converts a float, rounding it to `scale` decimals. None if it isn't
+    /// finite or doesn't fit at that scale.
+    pub fn from_f64(
+        value: f64,
+        scale: u32,
+    ) -> Option<Decimal> {
+        if !value.is_finite() {
+            return None;
+        }
+
+        format!("{:.*}", scale.min(MAX_SCALE) as usize, value)
+            .parse()
+            .ok()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.mantissa as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa == 0
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.mantissa > 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.mantissa < 0
+    }
+
+    pub fn abs(&self) -> Decimal {
+        Decimal::new(self.mantissa.abs(), self.scale)
+    }
+
+    pub fn min(self, other: Decimal) -> Decimal {
+        if other < self { other } else { self }
+    }
+
+    pub fn max(self, other: Decimal) -> Decimal {
+        if other > self { other } else { self }
+    }
+
+    /// Same value with trailing zeros removed from the decimals.
+    pub fn normalize(&self) -> Decimal {
+        let mut mantissa = self.mantissa;
+        let mut scale = self.scale;
+
+        while scale > 0 && mantissa % 10 == 0 {
+            mantissa /= 10;
+            scale -= 1;
+        }
+
+        Decimal::new(mantissa, scale)
+    }
+
+    /// Rounds (half away from zero) or pads the value to `scale` decimals.
+    pub fn rescale(&self, scale: u32) -> Decimal {
+        if scale >= self.scale {
+            Decimal::new(self.mantissa * pow10(scale - self.scale), scale)
+        } else {
+            Decimal::new(div_round(self.mantissa, pow10(self.scale - scale)), scale)
+        }
+    }
+
+    /// Truncates the value to `scale` decimals.
+    pub fn truncate(&self, scale: u32) -> Decimal {
+        if scale >= self.scale {
+            self.rescale(scale)
+        } else {
+            Decimal::new(self.mantissa / pow10(self.scale - scale), scale)
+        }
+    }
+
+    /// Largest multiple of `step` that is not greater than the value.
+    pub fn floor_to(&self, step: Decimal) -> Decimal {
+        if !step.is_positive() {
+            return *self;
+        }
+
+        let (value, step) = Decimal::align(*self, step);
+
+        Decimal::new(value.mantissa.div_euclid(step.mantissa) * step.mantissa, value.scale)
+    }
+
+    /// Smallest multiple of `step` that is not less than the value.
+    pub fn ceil_to(&self, step: Decimal) -> Decimal {
+        let floor = self.floor_to(step);
+
+        if floor == *self {
+            floor
+        } else {
+            floor + step
+        }
+    }
+
+    fn align(a: Decimal, b: Decimal) -> (Decimal, Decimal) {
+        match a.scale.cmp(&b.scale) {
+            Ordering::Less => (a.rescale(b.scale), b),
+            Ordering::Greater => (a, b.rescale(a.scale)),
+            Ordering::Equal => (a, b),
+        }
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    fn from_str(s: &str) -> Result<Decimal, ParseDecimalError> {
+        let error = || ParseDecimalError { input: s.to_string() };
+
+        let input = s.trim();
+
+        let (negative, digits) = match input.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, input.strip_prefix('+').unwrap_or(input)),
+        };
+
+        let (integer, fraction) = match digits.split_once('.') {
+            Some((integer, fraction)) => (integer, fraction),
+            None => (digits, ""),
+        };
+
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(error());
+        }
+
+        if !integer.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+            return Err(error());
+        }
+
+        // zeros past the largest scale don't change the value
+        let fraction = if fraction.len() > MAX_SCALE as usize {
+            fraction.trim_end_matches('0')
+        } else {
+            fraction
+        };
+
+        if fraction.len() > MAX_SCALE as usize {
+            return Err(error());
+        }
+
+        let mut mantissa: i128 = 0;
+
+        for c in integer.chars().chain(fraction.chars()) {
+            mantissa = mantissa
+                .checked_mul(10)
+                .and_then(|m| m.checked_add(c as i128 - '0' as i128))
+                .ok_or_else(error)?;
+        }
+
+        if negative {
+            mantissa = -mantissa;
+        }
+
+        Ok(Decimal::new(mantissa, fraction.len() as u32))
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+
+        let digits = self.mantissa.unsigned_abs().to_string();
+
+        if self.scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+
+        let scale = self.scale as usize;
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - scale);
+
+        write!(f, "{}{}.{}", sign, integer, fraction)
+    }
+}
+
+impl fmt::Debug for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (a, b) = Decimal::align(self.normalize(), other.normalize());
+
+        a.mantissa.cmp(&b.mantissa)
+    }
+}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let normalized = self.normalize();
+
+        normalized.mantissa.hash(state);
+        normalized.scale.hash(state);
+    }
+}
+
+impl Add for Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: Decimal) -> Decimal {
+        let (a, b) = Decimal::align(self, other);
+
+        Decimal::new(a.mantissa + b.mantissa, a.scale)
+    }
+}
+
+impl Sub for Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: Decimal) -> Decimal {
+        let (a, b) = Decimal::align(self, other);
+
+        Decimal::new(a.mantissa - b.mantissa, a.scale)
+    }
+}
+
+impl Mul for Decimal {
+    type Output = Decimal;
+
+    fn mul(self, other: Decimal) -> Decimal {
+        let mut a = self.normalize();
+        let mut b = other.normalize();
+
+        loop {
+            if let Some(mantissa) = a.mantissa.checked_mul(b.mantissa) {
+                let product = Decimal::new(mantissa, a.scale + b.scale);
+
+                return if product.scale > MAX_SCALE {
+                    product.rescale(MAX_SCALE).normalize()
+                } else {
+                    product
+                };
+            }
+
+            // drop the least significant decimal of the more precise operand
+            if a.scale == 0 && b.scale == 0 {
+                panic!("decimal overflow multiplying {} by {}", self, other);
+            } else if a.scale >= b.scale {
+                a = a.rescale(a.scale - 1);
+            } else {
+                b = b.rescale(b.scale - 1);
+            }
+        }
+    }
+}
+
+impl Div for Decimal {
+    type Output = Decimal;
+
+    fn div(self, other: Decimal) -> Decimal {
+        if other.is_zero() {
+            panic!("decimal division by zero");
+        }
+
+        let a = self.normalize();
+        let b = other.normalize();
+
+        let mut scale = MAX_SCALE;
+
+        loop {
+            let exponent = scale as i64 + b.scale as i64 - a.scale as i64;
+
+            let operands = if exponent >= 0 {
+                a.mantissa
+                    .checked_mul(pow10(exponent as u32))
+                    .map(|numerator| (numerator, b.mantissa))
+            } else {
+                b.mantissa
+                    .checked_mul(pow10((-exponent) as u32))
+                    .map(|denominator| (a.mantissa, denominator))
+            };
+
+            if let Some((numerator, denominator)) = operands {
+                return Decimal::new(div_round(numerator, denominator), scale).normalize();
+            }
+
+            if scale == 0 {
+                panic!("decimal overflow dividing {} by {}", self, other);
+            }
+
+            scale -= 1;
+        }
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal::new(-self.mantissa, self.scale)
+    }
+}
+
+impl AddAssign for Decimal {
+    fn add_assign(&mut self, other: Decimal) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Decimal {
+    fn sub_assign(&mut self, other: Decimal) {
+        *self = *self - other;
+    }
+}
+
+impl Sum for Decimal {
+    fn sum<I: Iterator<Item = Decimal>>(iter: I) -> Decimal {
+        iter.fold(Decimal::ZERO, |total, value| total + value)
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Decimal {
+        Decimal::new(value as i128, 0)
+    }
+}
+
+impl From<u64> for Decimal {
+    fn from(value: u64) -> Decimal {
+        Decimal::new(value as i128, 0)
+    }
+}
+
+impl From<i32> for Decimal {
+    fn from(value: i32) -> Decimal {
+        Decimal::new(value as i128, 0)
+    }
+}
+
+impl From<u32> for Decimal {
+    fn from(value: u32) -> Decimal {
+        Decimal::new(value as i128, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn adds_without_rounding_dust() {
+        let mut total = Decimal::ZERO;
+
+        for _ in 0..10 {
+            total += d("0.1");
+        }
+
+        assert_eq!(total, Decimal::ONE);
+        assert_eq!(d("0.1") + d("0.2"), d("0.3"));
+        assert_eq!((d("1.10") - d("0.1")).to_string(), "1.00");
+    }
+
+    #[test]
+    fn multiplies_and_divides() {
+        assert_eq!(d("1.5") * d("0.002"), d("0.003"));
+        assert_eq!(d("1") / d("3"), d("0.333333333333333333"));
+        assert_eq!(d("-2") / d("3"), d("-0.666666666666666667"));
+        assert_eq!(d("10") / d("4"), d("2.5"));
+    }
+
+    #[test]
+    fn parses_and_formats() {
+        assert_eq!(d("-0.05").to_string(), "-0.05");
+        assert_eq!(d("42").to_string(), "42");
+        assert_eq!(Decimal::from_f64(0.1, 8).unwrap().to_string(), "0.10000000");
+        assert!("1.2.3".parse::<Decimal>().is_err());
+        assert!("abc".parse::<Decimal>().is_err());
+    }
+
+    #[test]
+    fn rejects_what_does_not_fit() {
+        assert_eq!(Decimal::from_f64(f64::NAN, 8), None);
+        assert_eq!(Decimal::from_f64(1e30, 18), None);
+
+        // more decimals than the largest scale, unless they are zeros
+        assert!("0.0000000000000000001".parse::<Decimal>().is_err());
+        assert!(format!("1.{}", "1".repeat(40)).parse::<Decimal>().is_err());
+        assert_eq!(d("1.50000000000000000000"), d("1.5"));
+    }
+
+    #[test]
+    fn rounds_to_steps() {
+        assert_eq!(d("1.2345").floor_to(d("0.05")), d("1.2"));
+        assert_eq!(d("1.2345").ceil_to(d("0.05")), d("1.25"));
+        assert_eq!(d("1.25").ceil_to(d("0.05")), d("1.25"));
+        assert_eq!(d("0.125").rescale(2), d("0.13"));
+        assert_eq!(d("0.129").truncate(2), d("0.12"));
+    }
+}
@@ -0,0 +1,133 @@
+use std::cell::{Cell, RefCell};
+use crate::candles::aggregator::Aggregator;
+use crate::candles::candle::Candle;
+use crate::indicators::signal::Signal;
+use crate::strategies::indicator::Indicator;
+
+/// Average true range over `length` candles, smoothed the way Wilder
+/// described it. Measures volatility only, so its signal is always neutral.
+pub struct AverageTrueRange {
+    length: usize,
+    interval: u64,
+
+    previous_close: Cell<Option<f64>>,
+
+    // true ranges until the first average is available
+    true_ranges: RefCell<Vec<f64>>,
+
+    value: Cell<f64>,
+    accumulation_count: Cell<u64>,
+
+    recalculate_every_tick: Cell<bool>,
+}
+
+impl AverageTrueRange {
+    pub fn new(
+        length: usize,
+        interval: u64,
+    ) -> AverageTrueRange {
+        AverageTrueRange {
+            length: length.max(1),
+            interval,
+
+            previous_close: Cell::new(None),
+
+            true_ranges: RefCell::new(vec![]),
+
+            value: Cell::new(0.0),
+            accumulation_count: Cell::new(0),
+
+            recalculate_every_tick: Cell::new(false),
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// True once `length` candles were accumulated.
+    pub fn is_ready(&self) -> bool {
+        self.accumulation_count.get() >= self.length as u64
+    }
+
+    pub fn is_recalculated_every_tick(&self) -> bool {
+        self.recalculate_every_tick.get()
+    }
+
+    fn true_range(&self, candle: &Candle) -> f64 {
+        let range = candle.high - candle.low;
+
+        match self.previous_close.get() {
+            Some(previous_close) => range
+                .max((candle.high - previous_close).abs())
+                .max((candle.low - previous_close).abs()),
+            None => range,
+        }
+    }
+}
+
+impl Indicator for AverageTrueRange {
+    fn accumulate(&self, candle: &Candle) {
+        let true_range = self.true_range(candle);
+
+        self.previous_close.set(Some(candle.close));
+
+        let count = self.accumulation_count.get() + 1;
+
+        self.accumulation_count.set(count);
+
+        if count <= self.length as u64 {
+            let mut true_ranges = self.true_ranges.borrow_mut();
+
+            true_ranges.push(true_range);
+
+            self.value.set(true_ranges.iter().sum::<f64>() / true_ranges.len() as f64);
+
+            if count == self.length as u64 {
+                true_ranges.clear();
+            }
+
+            return;
+        }
+
+        let length = self.length as f64;
+
+        self.value.set((self.value.get() * (length - 1.0) + true_range) / length);
+    }
+
+    fn get_accumulation_count(&self) -> f64 {
+        self.accumulation_count.get() as f64
+    }
+
+    fn get_value(&self) -> f64 {
+        self.value.get()
+    }
+
+    fn get_interval(&self) -> u64 {
+        self.interval
+    }
+
+    fn get_signal(&self, _candle: &Candle) -> Signal {
+        Signal::NEUTRAL
+    }
+
+    fn signal_description(&self) -> String {
+        format!("ATR({}) = {}", self.length, self.value.get())
+    }
+
+    fn initialize(
+        &self,
+        aggregator: &Aggregator,
+    ) {
+        if let Some(candle) = aggregator.get_full() {
+            self.accumulate(&candle);
+        }
+    }
+
+    fn recalculate_every_tick(
+        &self,
+        recalculate_every_tick: bool,
+    ) {
+        self.recalculate_every_tick.set(recalculate_every_tick);
+    }
+}
@@ -1 +1,4 @@
+pub mod average_true_range;
+pub mod moving_average;
+pub mod relative_strength_index;
 pub mod signal;
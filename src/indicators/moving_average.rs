@@ -0,0 +1,265 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use crate::candles::aggregator::Aggregator;
+use crate::candles::candle::Candle;
+use crate::indicators::signal::Signal;
+use crate::strategies::indicator::Indicator;
+
+// buy when the close is above the average, sell when it is below
+fn trend_signal(candle: &Candle, average: f64, ready: bool) -> Signal {
+    if !ready || candle.close == average {
+        Signal::NEUTRAL
+    } else if candle.close > average {
+        Signal::BUY
+    } else {
+        Signal::SELL
+    }
+}
+
+/// Average of the closes of the last `length` candles.
+pub struct SimpleMovingAverage {
+    length: usize,
+    interval: u64,
+
+    closes: RefCell<VecDeque<f64>>,
+    sum: Cell<f64>,
+
+    accumulation_count: Cell<u64>,
+
+    recalculate_every_tick: Cell<bool>,
+}
+
+impl SimpleMovingAverage {
+    pub fn new(
+        length: usize,
+        interval: u64,
+    ) -> SimpleMovingAverage {
+        SimpleMovingAverage {
+            length: length.max(1),
+            interval,
+
+            closes: RefCell::new(VecDeque::new()),
+            sum: Cell::new(0.0),
+
+            accumulation_count: Cell::new(0),
+
+            recalculate_every_tick: Cell::new(false),
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// True once `length` candles were accumulated.
+    pub fn is_ready(&self) -> bool {
+        self.accumulation_count.get() >= self.length as u64
+    }
+}
+
+impl Indicator for SimpleMovingAverage {
+    fn accumulate(&self, candle: &Candle) {
+        let mut closes = self.closes.borrow_mut();
+
+        closes.push_back(candle.close);
+
+        let mut sum = self.sum.get() + candle.close;
+
+        if closes.len() > self.length {
+            sum -= closes.pop_front().unwrap_or(0.0);
+        }
+
+        self.sum.set(sum);
+        self.accumulation_count.set(self.accumulation_count.get() + 1);
+    }
+
+    fn get_accumulation_count(&self) -> f64 {
+        self.accumulation_count.get() as f64
+    }
+
+    fn get_value(&self) -> f64 {
+        let count = self.closes.borrow().len();
+
+        if count == 0 {
+            0.0
+        } else {
+            self.sum.get() / count as f64
+        }
+    }
+
+    fn get_interval(&self) -> u64 {
+        self.interval
+    }
+
+    fn get_signal(&self, candle: &Candle) -> Signal {
+        trend_signal(candle, self.get_value(), self.is_ready())
+    }
+
+    fn signal_description(&self) -> String {
+        format!("SMA({}) = {}", self.length, self.get_value())
+    }
+
+    fn initialize(
+        &self,
+        aggregator: &Aggregator,
+    ) {
+        if let Some(candle) = aggregator.get_full() {
+            self.accumulate(&candle);
+        }
+    }
+
+    fn recalculate_every_tick(
+        &self,
+        recalculate_every_tick: bool,
+    ) {
+        self.recalculate_every_tick.set(recalculate_every_tick);
+    }
+}
+
+/// Exponential moving average of the closes, seeded with the simple
+/// average of the first `length` candles.
+pub struct ExponentialMovingAverage {
+    length: usize,
+    interval: u64,
+
+    value: Cell<f64>,
+    accumulation_count: Cell<u64>,
+
+    recalculate_every_tick: Cell<bool>,
+}
+
+impl ExponentialMovingAverage {
+    pub fn new(
+        length: usize,
+        interval: u64,
+    ) -> ExponentialMovingAverage {
+        ExponentialMovingAverage {
+            length: length.max(1),
+            interval,
+
+            value: Cell::new(0.0),
+            accumulation_count: Cell::new(0),
+
+            recalculate_every_tick: Cell::new(false),
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// True once `length` candles were accumulated.
+    pub fn is_ready(&self) -> bool {
+        self.accumulation_count.get() >= self.length as u64
+    }
+}
+
+impl Indicator for ExponentialMovingAverage {
+    fn accumulate(&self, candle: &Candle) {
+        let count = self.accumulation_count.get() + 1;
+
+        self.accumulation_count.set(count);
+
+        let value = if count <= self.length as u64 {
+            self.value.get() + (candle.close - self.value.get()) / count as f64
+        } else {
+            let alpha = 2.0 / (self.length as f64 + 1.0);
+
+            self.value.get() + alpha * (candle.close - self.value.get())
+        };
+
+        self.value.set(value);
+    }
+
+    fn get_accumulation_count(&self) -> f64 {
+        self.accumulation_count.get() as f64
+    }
+
+    fn get_value(&self) -> f64 {
+        self.value.get()
+    }
+
+    fn get_interval(&self) -> u64 {
+        self.interval
+    }
+
+    fn get_signal(&self, candle: &Candle) -> Signal {
+        trend_signal(candle, self.get_value(), self.is_ready())
+    }
+
+    fn signal_description(&self) -> String {
+        format!("EMA({}) = {}", self.length, self.value.get())
+    }
+
+    fn initialize(
+        &self,
+        aggregator: &Aggregator,
+    ) {
+        if let Some(candle) = aggregator.get_full() {
+            self.accumulate(&candle);
+        }
+    }
+
+    fn recalculate_every_tick(
+        &self,
+        recalculate_every_tick: bool,
+    ) {
+        self.recalculate_every_tick.set(recalculate_every_tick);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // closes of the StockCharts moving average example
+    const CLOSES: [f64; 30] = [
+        22.27, 22.19, 22.08, 22.17, 22.18, 22.13, 22.23, 22.43, 22.24, 22.29,
+        22.15, 22.39, 22.38, 22.61, 23.36, 24.05, 23.75, 23.83, 23.95, 23.63,
+        23.82, 23.87, 23.65, 23.19, 23.10, 23.33, 22.68, 23.10, 22.40, 22.17,
+    ];
+
+    fn values(indicator: &dyn Indicator) -> Vec<f64> {
+        CLOSES
+            .iter()
+            .enumerate()
+            .map(|(i, close)| {
+                let time = i as u64 * 60_000;
+
+                indicator.accumulate(&Candle::new(time, time + 59_999, *close, *close, *close, *close, 1.0));
+
+                indicator.get_value()
+            })
+            .skip(9)
+            .collect()
+    }
+
+    fn assert_close(actual: Vec<f64>, expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+
+        for (actual, expected) in actual.iter().zip(expected.iter()) {
+            assert!((actual - expected).abs() < 0.006, "{} != {}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn simple_moving_average() {
+        let sma = SimpleMovingAverage::new(10, 0);
+
+        assert_close(values(&sma), &[
+            22.22, 22.21, 22.23, 22.26, 22.30, 22.42, 22.61, 22.77, 22.91, 23.08, 23.21,
+            23.38, 23.52, 23.65, 23.71, 23.68, 23.61, 23.51, 23.43, 23.28, 23.13,
+        ]);
+        assert!(sma.is_ready());
+    }
+
+    #[test]
+    fn exponential_moving_average() {
+        let ema = ExponentialMovingAverage::new(10, 0);
+
+        assert_close(values(&ema), &[
+            22.22, 22.21, 22.24, 22.27, 22.33, 22.52, 22.80, 22.97, 23.13, 23.28, 23.34,
+            23.43, 23.51, 23.53, 23.47, 23.40, 23.39, 23.26, 23.23, 23.08, 22.92,
+        ]);
+    }
+}
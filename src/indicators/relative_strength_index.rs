@@ -0,0 +1,189 @@
+use std::cell::Cell;
+use crate::candles::aggregator::Aggregator;
+use crate::candles::candle::Candle;
+use crate::indicators::signal::Signal;
+use crate::strategies::indicator::Indicator;
+
+/// Relative strength index of the closes over `length` candles, with
+/// Wilder's smoothing. Signals buys below `oversold` and sells above
+/// `overbought`, 30 and 70 by default.
+pub struct RelativeStrengthIndex {
+    length: usize,
+    interval: u64,
+
+    oversold: f64,
+    overbought: f64,
+
+    previous_close: Cell<Option<f64>>,
+
+    average_gain: Cell<f64>,
+    average_loss: Cell<f64>,
+
+    accumulation_count: Cell<u64>,
+
+    recalculate_every_tick: Cell<bool>,
+}
+
+impl RelativeStrengthIndex {
+    pub fn new(
+        length: usize,
+        interval: u64,
+    ) -> RelativeStrengthIndex {
+        RelativeStrengthIndex {
+            length: length.max(1),
+            interval,
+
+            oversold: 30.0,
+            overbought: 70.0,
+
+            previous_close: Cell::new(None),
+
+            average_gain: Cell::new(0.0),
+            average_loss: Cell::new(0.0),
+
+            accumulation_count: Cell::new(0),
+
+            recalculate_every_tick: Cell::new(false),
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    pub fn set_thresholds(&mut self, oversold: f64, overbought: f64) {
+        self.oversold = oversold;
+        self.overbought = overbought;
+    }
+
+    /// True once `length` price changes were accumulated.
+    pub fn is_ready(&self) -> bool {
+        self.accumulation_count.get() > self.length as u64
+    }
+}
+
+impl Indicator for RelativeStrengthIndex {
+    fn accumulate(&self, candle: &Candle) {
+        let count = self.accumulation_count.get() + 1;
+
+        self.accumulation_count.set(count);
+
+        let previous_close = self.previous_close.replace(Some(candle.close));
+
+        let change = match previous_close {
+            Some(previous_close) => candle.close - previous_close,
+            None => return,
+        };
+
+        let gain = change.max(0.0);
+        let loss = (-change).max(0.0);
+
+        // the first changes are averaged, the next ones smoothed
+        let changes = (count - 1) as f64;
+        let length = self.length as f64;
+
+        let weight = if changes <= length { changes } else { length };
+
+        self.average_gain.set(self.average_gain.get() + (gain - self.average_gain.get()) / weight);
+        self.average_loss.set(self.average_loss.get() + (loss - self.average_loss.get()) / weight);
+    }
+
+    fn get_accumulation_count(&self) -> f64 {
+        self.accumulation_count.get() as f64
+    }
+
+    fn get_value(&self) -> f64 {
+        let average_gain = self.average_gain.get();
+        let average_loss = self.average_loss.get();
+
+        if average_loss == 0.0 {
+            return if average_gain == 0.0 { 50.0 } else { 100.0 };
+        }
+
+        100.0 - 100.0 / (1.0 + average_gain / average_loss)
+    }
+
+    fn get_interval(&self) -> u64 {
+        self.interval
+    }
+
+    fn get_signal(&self, _candle: &Candle) -> Signal {
+        if !self.is_ready() {
+            return Signal::NEUTRAL;
+        }
+
+        let value = self.get_value();
+
+        if value < self.oversold {
+            Signal::BUY
+        } else if value > self.overbought {
+            Signal::SELL
+        } else {
+            Signal::NEUTRAL
+        }
+    }
+
+    fn signal_description(&self) -> String {
+        format!("RSI({}) = {}", self.length, self.get_value())
+    }
+
+    fn initialize(
+        &self,
+        aggregator: &Aggregator,
+    ) {
+        if let Some(candle) = aggregator.get_full() {
+            self.accumulate(&candle);
+        }
+    }
+
+    fn recalculate_every_tick(
+        &self,
+        recalculate_every_tick: bool,
+    ) {
+        self.recalculate_every_tick.set(recalculate_every_tick);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wilder_relative_strength_index() {
+        // closes of the StockCharts RSI example, the values are those of
+        // TA-Lib, which doesn't round the averages
+        let closes = [
+            44.34, 44.09, 44.15, 43.61, 44.33, 44.83, 45.10, 45.42, 45.84, 46.08, 45.89,
+            46.03, 45.61, 46.28, 46.28, 46.00, 46.03, 46.41, 46.22, 45.64, 46.21, 46.25,
+            45.71, 46.45, 45.78, 45.35, 44.03, 44.18, 44.22, 44.57, 43.42, 42.66, 43.13,
+        ];
+
+        let expected = [
+            70.46, 66.25, 66.48, 69.35, 66.29, 57.92, 62.88, 63.21, 56.01, 62.34,
+            54.67, 50.39, 40.02, 41.49, 41.90, 45.50, 37.32, 33.09, 37.79,
+        ];
+
+        let rsi = RelativeStrengthIndex::new(14, 0);
+
+        let values: Vec<f64> = closes
+            .iter()
+            .enumerate()
+            .map(|(i, close)| {
+                let time = i as u64 * 60_000;
+
+                rsi.accumulate(&Candle::new(time, time + 59_999, *close, *close, *close, *close, 1.0));
+
+                assert_eq!(rsi.is_ready(), i >= 14);
+
+                rsi.get_value()
+            })
+            .skip(14)
+            .collect();
+
+        assert_eq!(values.len(), expected.len());
+
+        for (value, expected) in values.iter().zip(expected.iter()) {
+            assert!((value - expected).abs() < 0.006, "{} != {}", value, expected);
+        }
+    }
+}
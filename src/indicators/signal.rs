@@ -1,3 +1,4 @@
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Signal {
     UNDERVALUED, // ('u', 1)
     BUY, // ('B', 0.5)
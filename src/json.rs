@@ -0,0 +1,360 @@
+use std::fmt;
+
+/// Parsed JSON document. Object members keep the order of the input.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+
+    // value and the text it was parsed from, which keeps integers too
+    // large for a f64 exact
+    Number(f64, String),
+
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JsonError {
+    pub message: String,
+
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}, column {}", self.message, self.line, self.column)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl JsonValue {
+    pub fn parse(input: &str) -> Result<JsonValue, JsonError> {
+        let mut parser = Parser {
+            input,
+            chars: input.char_indices().collect(),
+            position: 0,
+        };
+
+        let value = parser.value()?;
+
+        parser.skip_whitespace();
+
+        if parser.position < parser.chars.len() {
+            return Err(parser.error("unexpected trailing characters"));
+        }
+
+        Ok(value)
+    }
+
+    /// Member of an object.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(number, _) => Some(*number),
+            _ => None,
+        }
+    }
+
+    /// Value of an integer number, exactly as written.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            JsonValue::Number(_, text) => text.parse::<u64>().ok(),
+            _ => None,
+        }
+    }
+
+    /// Text a number was written as.
+    pub fn number_text(&self) -> Option<&str> {
+        match self {
+            JsonValue::Number(_, text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Vec<(String, JsonValue)>> {
+        match self {
+            JsonValue::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == JsonValue::Null
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+
+    chars: Vec<(usize, char)>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> JsonError {
+        let offset = self.chars
+            .get(self.position)
+            .map(|(offset, _)| *offset)
+            .unwrap_or(self.input.len());
+
+        let before = &self.input[..offset];
+
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+
+        JsonError {
+            message: message.to_string(),
+
+            line,
+            column,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).map(|(_, c)| *c)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+
+        self.position += 1;
+
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        self.skip_whitespace();
+
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("expected '{}'", expected)));
+        }
+
+        self.position += 1;
+
+        Ok(())
+    }
+
+    fn keyword(&mut self, keyword: &str, value: JsonValue) -> Result<JsonValue, JsonError> {
+        for expected in keyword.chars() {
+            if self.peek() != Some(expected) {
+                return Err(self.error("invalid literal"));
+            }
+
+            self.position += 1;
+        }
+
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<JsonValue, JsonError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(JsonValue::String(self.string()?)),
+            Some('t') => self.keyword("true", JsonValue::Bool(true)),
+            Some('f') => self.keyword("false", JsonValue::Bool(false)),
+            Some('n') => self.keyword("null", JsonValue::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, JsonError> {
+        self.expect('{')?;
+
+        let mut members = vec![];
+
+        self.skip_whitespace();
+
+        if self.peek() == Some('}') {
+            self.position += 1;
+
+            return Ok(JsonValue::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a member name"));
+            }
+
+            let name = self.string()?;
+
+            self.expect(':')?;
+
+            members.push((name, self.value()?));
+
+            self.skip_whitespace();
+
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(JsonValue::Object(members)),
+                _ => {
+                    self.position -= 1;
+
+                    return Err(self.error("expected ',' or '}'"));
+                }
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue, JsonError> {
+        self.expect('[')?;
+
+        let mut values = vec![];
+
+        self.skip_whitespace();
+
+        if self.peek() == Some(']') {
+            self.position += 1;
+
+            return Ok(JsonValue::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+
+            self.skip_whitespace();
+
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(JsonValue::Array(values)),
+                _ => {
+                    self.position -= 1;
+
+                    return Err(self.error("expected ',' or ']'"));
+                }
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+
+        let mut s = String::new();
+
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let escaped = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let hex: String = (0..4).filter_map(|_| self.next()).collect();
+
+                            u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("invalid unicode escape"))?
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+
+                    s.push(escaped);
+                }
+                Some(c) => s.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.position;
+
+        while let Some(c) = self.peek() {
+            if !(c.is_ascii_digit() || "+-.eE".contains(c)) {
+                break;
+            }
+
+            self.position += 1;
+        }
+
+        let text: String = self.chars[start..self.position].iter().map(|(_, c)| *c).collect();
+
+        text.parse::<f64>()
+            .map(|number| JsonValue::Number(number, text.clone()))
+            .map_err(|_| {
+                self.position = start;
+
+                self.error("invalid number")
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_documents() {
+        let value = JsonValue::parse(r#"{"name": "ema \"cross\"", "lengths": [9, 21.5, -1e2], "short": false, "stop": null}"#).unwrap();
+
+        assert_eq!(value.get("name").and_then(|name| name.as_str()), Some("ema \"cross\""));
+        assert_eq!(value.get("lengths").and_then(|lengths| lengths.as_array()).unwrap()[2], JsonValue::Number(-100.0, "-1e2".to_string()));
+        assert_eq!(value.get("short").and_then(|short| short.as_bool()), Some(false));
+        assert!(value.get("stop").unwrap().is_null());
+
+        let value = JsonValue::parse(r#"{"orderId": 9007199254740993}"#).unwrap();
+
+        assert_eq!(value.get("orderId").and_then(|order_id| order_id.as_u64()), Some(9_007_199_254_740_993));
+
+        let error = JsonValue::parse("{\n  \"a\": 1,\n  \"b\" 2\n}").unwrap_err();
+
+        assert_eq!((error.line, error.column), (3, 7));
+    }
+}
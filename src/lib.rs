@@ -1,10 +1,13 @@
-mod account;
-mod candles;
-mod indicators;
-mod strategies;
-mod client_account;
-mod trading_fees;
-mod simulation;
+pub mod account;
+pub mod candles;
+pub mod indicators;
+pub mod strategies;
+pub mod client_account;
+pub mod decimal;
+pub mod json;
+pub mod trading_fees;
+pub mod simulation;
+pub mod live;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,795 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use crate::account::balance::Balance;
+use crate::account::order::{Order, OrderSide, OrderStatus, OrderType, TriggerCondition};
+use crate::account::order_request::OrderRequest;
+use crate::account::orderbook::OrderBook;
+use crate::client_account::ClientAccount;
+use crate::decimal::Decimal;
+use crate::json::JsonValue;
+use crate::live::http::{HttpRequest, Method, Transport, query_string};
+use crate::live::runtime::now_ms;
+use crate::live::signing::{hmac_sha256, to_hex};
+
+/// Failed request to the exchange: the transport failed, the exchange
+/// answered with an error, or its answer couldn't be understood.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExchangeError {
+    // HTTP status, 0 if no response was received
+    pub status: u16,
+
+    // error code in the body of the response, if any
+    pub code: Option<i64>,
+
+    pub message: String,
+}
+
+impl ExchangeError {
+    pub fn new(status: u16, code: Option<i64>, message: &str) -> ExchangeError {
+        ExchangeError {
+            status,
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ExchangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "HTTP {} (code {}): {}", self.status, code, self.message),
+            None => write!(f, "HTTP {}: {}", self.status, self.message),
+        }
+    }
+}
+
+impl Error for ExchangeError {}
+
+/// `ClientAccount` of a Binance spot account, through the REST API.
+///
+/// Signed requests carry the API key in the `X-MBX-APIKEY` header and the
+/// HMAC-SHA256 of their query string, keyed by the secret, as `signature`.
+/// Pairs are named by the asset and funds symbols concatenated, unless
+/// mapped otherwise with `set_pair_name`. Orders the exchange rejects are
+/// returned cancelled, with the reason kept as `last_error`. Fees of fills
+/// found by querying or cancelling an order are read from its trades.
+pub struct BinanceAccount<T: Transport> {
+    transport: T,
+
+    api_key: String,
+    secret: String,
+
+    recv_window: u64,
+
+    clock: Box<dyn Fn() -> u64>,
+
+    // exchange name of each pair, by asset and funds symbols
+    pair_names: HashMap<(String, String), String>,
+
+    order_counter: u64,
+
+    // orders whose trades couldn't be queried, so their fees are unknown
+    unknown_fees: HashSet<String>,
+
+    last_error: Option<ExchangeError>,
+
+    // wait asked for by the last rate limit error, in milliseconds
+    retry_after: Option<u64>,
+}
+
+impl<T: Transport> BinanceAccount<T> {
+    pub fn new(
+        transport: T,
+        api_key: &str,
+        secret: &str,
+    ) -> BinanceAccount<T> {
+        BinanceAccount {
+            transport,
+
+            api_key: api_key.to_string(),
+            secret: secret.to_string(),
+
+            recv_window: 5000,
+
+            clock: Box::new(now_ms),
+
+            pair_names: HashMap::new(),
+
+            order_counter: 0,
+
+            unknown_fees: HashSet::new(),
+
+            last_error: None,
+
+            retry_after: None,
+        }
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    pub fn set_recv_window(&mut self, recv_window: u64) {
+        self.recv_window = recv_window;
+    }
+
+    /// Replaces the wall clock, in milliseconds, requests are timestamped
+    /// with.
+    pub fn set_clock(&mut self, clock: impl Fn() -> u64 + 'static) {
+        self.clock = Box::new(clock);
+    }
+
+    pub fn set_pair_name(&mut self, assets_symbol: &str, funds_symbol: &str, pair_name: &str) {
+        self.pair_names.insert(
+            (assets_symbol.to_string(), funds_symbol.to_string()),
+            pair_name.to_string(),
+        );
+    }
+
+    pub fn pair_name(&self, assets_symbol: &str, funds_symbol: &str) -> String {
+        self.pair_names
+            .get(&(assets_symbol.to_string(), funds_symbol.to_string()))
+            .cloned()
+            .unwrap_or_else(|| format!("{}{}", assets_symbol, funds_symbol).to_uppercase())
+    }
+
+    pub fn last_error(&self) -> Option<&ExchangeError> {
+        self.last_error.as_ref()
+    }
+
+    pub fn take_error(&mut self) -> Option<ExchangeError> {
+        self.last_error.take()
+    }
+
+    /// Whether the fees of an order are unknown, its trades having failed
+    /// to be queried. They are queried again with its next status update.
+    pub fn has_unknown_fees(&self, order: &Order) -> bool {
+        self.unknown_fees.contains(&order.order_id())
+    }
+
+    pub fn sign(&self, query: &str) -> String {
+        to_hex(&hmac_sha256(self.secret.as_bytes(), query.as_bytes()))
+    }
+
+    pub fn request(
+        &mut self,
+        method: Method,
+        path: &str,
+        parameters: &[(&str, String)],
+        signed: bool,
+    ) -> Result<JsonValue, ExchangeError> {
+        let mut query = query_string(parameters);
+
+        if signed {
+            let timing = query_string(&[
+                ("recvWindow", self.recv_window.to_string()),
+                ("timestamp", (self.clock)().to_string()),
+            ]);
+
+            if !query.is_empty() {
+                query.push('&');
+            }
+
+            query.push_str(&timing);
+
+            let signature = self.sign(&query);
+
+            query.push_str(&format!("&signature={}", signature));
+        }
+
+        let mut request = HttpRequest::new(method, path, query);
+
+        request.header("X-MBX-APIKEY", &self.api_key);
+
+        let response = self.transport
+            .send(&request)
+            .map_err(|error| ExchangeError::new(0, None, &error.to_string()))?;
+
+        // 429 when over the limits, 418 once banned for ignoring them
+        if response.status == 429 || response.status == 418 {
+            let retry_after = response.header("Retry-After")
+                .and_then(|seconds| seconds.trim().parse::<u64>().ok())
+                .map(|seconds| seconds * 1000)
+                .unwrap_or(60_000);
+
+            self.retry_after = Some(self.retry_after.unwrap_or(0).max(retry_after));
+        }
+
+        let body = JsonValue::parse(&response.body);
+
+        if !response.is_success() {
+            let body = body.ok();
+
+            let code = body.as_ref()
+                .and_then(|body| body.get("code"))
+                .and_then(|code| code.as_f64())
+                .map(|code| code as i64);
+
+            let message = body.as_ref()
+                .and_then(|body| body.get("msg"))
+                .and_then(|message| message.as_str())
+                .unwrap_or(&response.body)
+                .to_string();
+
+            return Err(ExchangeError::new(response.status, code, &message));
+        }
+
+        body.map_err(|error| ExchangeError::new(response.status, None, &error.to_string()))
+    }
+
+    /// Snapshot of the order book of a pair, `depth` levels on each side,
+    /// with the id of the last update it includes.
+    pub fn order_book(
+        &mut self,
+        assets_symbol: &str,
+        funds_symbol: &str,
+        depth: u64,
+    ) -> Result<OrderBook, ExchangeError> {
+        let symbol = self.pair_name(assets_symbol, funds_symbol);
+
+        let body = self.request(
+            Method::Get,
+            "/api/v3/depth",
+            &[("symbol", symbol), ("limit", depth.to_string())],
+            false,
+        )?;
+
+        let mut order_book = OrderBook::new(format!("{}{}", assets_symbol, funds_symbol), depth);
+
+        order_book.set_last_update_id(body.get("lastUpdateId").and_then(|id| id.as_u64()).unwrap_or(0));
+
+        for (side, bids) in [("bids", true), ("asks", false)].iter() {
+            for level in body.get(side).and_then(|levels| levels.as_array()).unwrap_or(&vec![]) {
+                let price = level_value(level, 0)?;
+                let quantity = level_value(level, 1)?;
+
+                if *bids {
+                    order_book.add_bid(price, quantity);
+                } else {
+                    order_book.add_ask(price, quantity);
+                }
+            }
+        }
+
+        Ok(order_book)
+    }
+
+    fn order_parameters(&self, order_request: &OrderRequest) -> Vec<(&'static str, String)> {
+        let side = match order_request.side() {
+            OrderSide::Buy => "BUY",
+            OrderSide::Sell => "SELL",
+        };
+
+        let limit = *order_request.order_type() == OrderType::Limit;
+
+        let order_type = match (order_request.trigger_condition(), limit) {
+            (TriggerCondition::None, true) => "LIMIT",
+            (TriggerCondition::None, false) => "MARKET",
+            (TriggerCondition::StopLoss, true) => "STOP_LOSS_LIMIT",
+            (TriggerCondition::StopLoss, false) => "STOP_LOSS",
+            (TriggerCondition::StopGain, true) => "TAKE_PROFIT_LIMIT",
+            (TriggerCondition::StopGain, false) => "TAKE_PROFIT",
+        };
+
+        let mut parameters = vec![
+            ("symbol", self.pair_name(order_request.assets_symbol(), order_request.funds_symbol())),
+            ("side", side.to_string()),
+            ("type", order_type.to_string()),
+            ("quantity", order_request.quantity().to_string()),
+        ];
+
+        if limit {
+            parameters.push(("timeInForce", "GTC".to_string()));
+            parameters.push(("price", order_request.price().to_string()));
+        }
+
+        if *order_request.trigger_condition() != TriggerCondition::None {
+            parameters.push(("stopPrice", order_request.trigger_price().to_string()));
+        }
+
+        parameters.push(("newOrderRespType", "FULL".to_string()));
+
+        parameters
+    }
+
+    // parameters of an OCO order list made of a take profit and a stop loss
+    // closing the same quantity, None for any other orders
+    fn oco_parameters(&self, order_details: &[OrderRequest]) -> Option<Vec<(&'static str, String)>> {
+        let (take_profit, stop_loss) = match order_details {
+            [first, second] if *first.trigger_condition() == TriggerCondition::StopLoss => (second, first),
+            [first, second] => (first, second),
+            _ => return None,
+        };
+
+        if *take_profit.trigger_condition() != TriggerCondition::StopGain
+            || *stop_loss.trigger_condition() != TriggerCondition::StopLoss
+            || take_profit.side() != stop_loss.side()
+            || take_profit.symbol() != stop_loss.symbol()
+            || take_profit.quantity() != stop_loss.quantity()
+        {
+            return None;
+        }
+
+        let side = match take_profit.side() {
+            OrderSide::Buy => "BUY",
+            OrderSide::Sell => "SELL",
+        };
+
+        let mut parameters = vec![
+            ("symbol", self.pair_name(take_profit.assets_symbol(), take_profit.funds_symbol())),
+            ("side", side.to_string()),
+            ("quantity", take_profit.quantity().to_string()),
+            ("price", take_profit.price().to_string()),
+            ("stopPrice", stop_loss.trigger_price().to_string()),
+        ];
+
+        if *stop_loss.order_type() == OrderType::Limit {
+            parameters.push(("stopLimitPrice", stop_loss.price().to_string()));
+            parameters.push(("stopLimitTimeInForce", "GTC".to_string()));
+        }
+
+        parameters.push(("newOrderRespType", "FULL".to_string()));
+
+        Some(parameters)
+    }
+
+    fn order_query(&self, order: &Order) -> Vec<(&'static str, String)> {
+        vec![
+            ("symbol", self.pair_name(order.order_request.assets_symbol(), order.order_request.funds_symbol())),
+            ("orderId", order.order_id()),
+        ]
+    }
+
+    // applies the state of an order queried or cancelled, which comes
+    // without its fills, so the fees are read from its trades when more of
+    // it was executed
+    fn apply_queried_state(&mut self, order: &mut Order, body: &JsonValue) {
+        let executed_quantity = order.executed_quantity();
+
+        apply_order_state(order, body);
+
+        if order.executed_quantity() == executed_quantity && !self.has_unknown_fees(order) {
+            return;
+        }
+
+        let parameters = self.order_query(order);
+
+        match self.request(Method::Get, "/api/v3/myTrades", &parameters, true) {
+            Ok(trades) => {
+                order.set_fees_paid(fees(order, trades.as_array().unwrap_or(&vec![])));
+
+                self.unknown_fees.remove(&order.order_id());
+            }
+            Err(error) => {
+                self.unknown_fees.insert(order.order_id());
+
+                self.last_error = Some(error);
+            }
+        }
+    }
+}
+
+fn level_value(level: &JsonValue, index: usize) -> Result<Decimal, ExchangeError> {
+    level.as_array()
+        .and_then(|level| level.get(index))
+        .and_then(decimal)
+        .ok_or_else(|| ExchangeError::new(200, None, "invalid order book level"))
+}
+
+// amounts are sent as strings to keep their precision
+fn decimal(value: &JsonValue) -> Option<Decimal> {
+    match value {
+        JsonValue::String(value) => value.parse::<Decimal>().ok(),
+        // exactly as written, unless in exponent notation
+        JsonValue::Number(value, text) => text.parse::<Decimal>()
+            .or_else(|_| format!("{}", value).parse::<Decimal>())
+            .ok(),
+        _ => None,
+    }
+}
+
+fn order_status(status: &str) -> OrderStatus {
+    match status {
+        "NEW" | "PENDING_NEW" => OrderStatus::New,
+        "PARTIALLY_FILLED" => OrderStatus::PartiallyFilled,
+        "FILLED" => OrderStatus::Filled,
+        // canceled, rejected or expired
+        _ => OrderStatus::Cancelled,
+    }
+}
+
+// copies the state reported by the exchange to the order
+fn apply_order_state(order: &mut Order, body: &JsonValue) {
+    if let Some(order_id) = body.get("orderId").and_then(|order_id| order_id.as_u64()) {
+        order.set_order_id(order_id.to_string());
+    }
+
+    if let Some(status) = body.get("status").and_then(|status| status.as_str()) {
+        order.set_status(order_status(status));
+    }
+
+    let executed_quantity = body.get("executedQty").and_then(decimal);
+    let quote_quantity = body.get("cummulativeQuoteQty").and_then(decimal);
+
+    if let Some(executed_quantity) = executed_quantity {
+        order.set_executed_quantity(executed_quantity);
+
+        if let Some(quote_quantity) = quote_quantity {
+            if executed_quantity.is_positive() {
+                order.set_average_price(quote_quantity / executed_quantity);
+            }
+        }
+    }
+
+    // fees are only reported with the fills of a new order
+    if let Some(fills) = body.get("fills").and_then(|fills| fills.as_array()) {
+        order.set_fees_paid(fees(order, fills));
+    }
+}
+
+// fees of the fills or trades of an order, in the funds symbol, when paid
+// in the asset or funds symbol rather than with another asset
+fn fees(order: &Order, fills: &[JsonValue]) -> Decimal {
+    let mut fees = Decimal::ZERO;
+
+    for fill in fills {
+        let commission = fill.get("commission").and_then(decimal).unwrap_or(Decimal::ZERO);
+        let price = fill.get("price").and_then(decimal).unwrap_or(Decimal::ZERO);
+
+        match fill.get("commissionAsset").and_then(|asset| asset.as_str()) {
+            Some(asset) if asset == order.order_request.funds_symbol() => fees += commission,
+            Some(asset) if asset == order.order_request.assets_symbol() => fees += commission * price,
+            _ => {}
+        }
+    }
+
+    fees
+}
+
+impl<T: Transport> ClientAccount for BinanceAccount<T> {
+    fn execute_order(&mut self, order_details: OrderRequest) -> Order {
+        self.order_counter += 1;
+
+        let mut order = Order::new_from_order_request(
+            self.order_counter,
+            order_details,
+        );
+
+        if order.order_request.is_cancelled() {
+            order.set_status(OrderStatus::Cancelled);
+
+            return order;
+        }
+
+        let parameters = self.order_parameters(&order.order_request);
+
+        match self.request(Method::Post, "/api/v3/order", &parameters, true) {
+            Ok(body) => apply_order_state(&mut order, &body),
+            Err(error) => {
+                order.set_status(OrderStatus::Cancelled);
+
+                self.last_error = Some(error);
+            }
+        }
+
+        order
+    }
+
+    /// A take profit and a stop loss are placed as an OCO order list, the
+    /// take profit as a limit maker order. Other orders are placed on their
+    /// own.
+    fn execute_oco(&mut self, order_details: Vec<OrderRequest>) -> Vec<Order> {
+        let parameters = match self.oco_parameters(&order_details) {
+            Some(parameters) => parameters,
+            None => {
+                return order_details
+                    .into_iter()
+                    .map(|order_request| self.execute_order(order_request))
+                    .collect();
+            }
+        };
+
+        let mut orders = vec![];
+
+        for order_request in order_details {
+            self.order_counter += 1;
+
+            orders.push(Order::new_from_order_request(self.order_counter, order_request));
+        }
+
+        match self.request(Method::Post, "/api/v3/order/oco", &parameters, true) {
+            Ok(body) => {
+                for report in body.get("orderReports").and_then(|reports| reports.as_array()).unwrap_or(&vec![]) {
+                    let stop_loss = report.get("type")
+                        .and_then(|order_type| order_type.as_str())
+                        .map(|order_type| order_type.starts_with("STOP_LOSS"))
+                        .unwrap_or(false);
+
+                    let order = orders
+                        .iter_mut()
+                        .find(|order| (*order.order_request.trigger_condition() == TriggerCondition::StopLoss) == stop_loss);
+
+                    if let Some(order) = order {
+                        apply_order_state(order, report);
+                    }
+                }
+            }
+            Err(error) => {
+                for order in orders.iter_mut() {
+                    order.set_status(OrderStatus::Cancelled);
+                }
+
+                self.last_error = Some(error);
+            }
+        }
+
+        orders
+    }
+
+    fn retry_after(&mut self) -> Option<u64> {
+        self.retry_after.take()
+    }
+
+    fn update_balances(&mut self, _force: bool) -> HashMap<String, Balance> {
+        let body = match self.request(Method::Get, "/api/v3/account", &[], true) {
+            Ok(body) => body,
+            Err(error) => {
+                self.last_error = Some(error);
+
+                return HashMap::new();
+            }
+        };
+
+        let mut balances = HashMap::new();
+
+        for entry in body.get("balances").and_then(|balances| balances.as_array()).unwrap_or(&vec![]) {
+            let symbol = match entry.get("asset").and_then(|asset| asset.as_str()) {
+                Some(symbol) => symbol.to_string(),
+                None => continue,
+            };
+
+            let mut balance = Balance::new(symbol.clone());
+
+            balance.free(entry.get("free").and_then(decimal).unwrap_or(Decimal::ZERO));
+            balance.locked(entry.get("locked").and_then(decimal).unwrap_or(Decimal::ZERO));
+
+            balances.insert(symbol, balance);
+        }
+
+        balances
+    }
+
+    fn update_order_status(&mut self, order: &mut Order) {
+        let parameters = self.order_query(order);
+
+        match self.request(Method::Get, "/api/v3/order", &parameters, true) {
+            Ok(body) => self.apply_queried_state(order, &body),
+            Err(error) => self.last_error = Some(error),
+        }
+    }
+
+    fn cancel(&mut self, order: &mut Order) {
+        let parameters = self.order_query(order);
+
+        match self.request(Method::Delete, "/api/v3/order", &parameters, true) {
+            Ok(body) => self.apply_queried_state(order, &body),
+            Err(error) => {
+                self.last_error = Some(error);
+
+                // it may have filled in the meantime
+                self.update_order_status(order);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use crate::account::trade::TradeSide;
+    use crate::live::http::{HttpResponse, TcpTransport};
+
+    // answers each request with the next response and returns the request
+    // lines it received
+    fn mock_server(responses: Vec<(u16, &'static str)>) -> (u16, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let handle = thread::spawn(move || {
+            let mut requests = vec![];
+
+            for (status, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+
+                let mut api_key = String::new();
+
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+
+                    if line.trim().is_empty() {
+                        break;
+                    }
+
+                    if let Some(value) = line.strip_prefix("X-MBX-APIKEY:") {
+                        api_key = value.trim().to_string();
+                    }
+                }
+
+                requests.push(format!("{} {}", request_line.trim(), api_key));
+
+                let mut stream = reader.into_inner();
+
+                write!(
+                    stream,
+                    "HTTP/1.1 {} OK\r\nContent-Type: application/json\r\nRetry-After: 2\r\nContent-Length: {}\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body,
+                ).unwrap();
+            }
+
+            requests
+        });
+
+        (port, handle)
+    }
+
+    struct Failing;
+
+    impl Transport for Failing {
+        fn send(&mut self, _request: &HttpRequest) -> io::Result<HttpResponse> {
+            Err(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"))
+        }
+    }
+
+    #[test]
+    fn places_and_queries_orders() {
+        let (port, server) = mock_server(vec![
+            (200, r#"{"symbol":"BTCUSDT","orderId":28,"status":"FILLED","executedQty":"0.50000000","cummulativeQuoteQty":"10000.00000000","fills":[{"price":"20000.00","qty":"0.5","commission":"10.0","commissionAsset":"USDT"}]}"#),
+            (200, r#"{"balances":[{"asset":"BTC","free":"0.50000000","locked":"0.00000000"},{"asset":"USDT","free":"990.00","locked":"0"}]}"#),
+            (200, r#"{"lastUpdateId":1,"bids":[["19999.00","2.0"]],"asks":[["20001.00","1.5"]]}"#),
+            (400, r#"{"code":-2010,"msg":"Account has insufficient balance for requested action."}"#),
+            (429, r#"{"code":-1003,"msg":"Too many requests."}"#),
+            (200, r#"{"orderListId":3,"orderReports":[{"orderId":31,"type":"STOP_LOSS_LIMIT","status":"NEW","executedQty":"0"},{"orderId":32,"type":"LIMIT_MAKER","status":"NEW","executedQty":"0"}]}"#),
+        ]);
+
+        let mut account = BinanceAccount::new(TcpTransport::new("127.0.0.1", port), "key", "secret");
+
+        account.set_clock(|| 1499827319559);
+        account.set_pair_name("XBT", "USDT", "BTCUSDT");
+
+        let mut order_request = OrderRequest::new(
+            "XBT".to_string(),
+            "USDT".to_string(),
+            OrderSide::Buy,
+            TradeSide::LONG,
+            0,
+            None,
+        );
+
+        order_request.set_order_type(OrderType::Market);
+        order_request.set_quantity(Decimal::new(50, 2));
+
+        let order = account.execute_order(order_request.clone());
+
+        assert_eq!(order.order_id(), "28");
+        assert_eq!(order.status(), OrderStatus::Filled);
+        assert_eq!(order.average_price(), Decimal::from(20000));
+        assert_eq!(order.fees_paid(), Decimal::from(10));
+
+        let balances = account.update_balances(true);
+
+        assert_eq!(balances["USDT"].free, Decimal::from(990));
+
+        let order_book = account.order_book("XBT", "USDT", 5).unwrap();
+
+        assert_eq!(order_book.best_ask(), Some(Decimal::from(20001)));
+        assert_eq!(order_book.last_update_id(), 1);
+
+        let rejected = account.execute_order(order_request.clone());
+
+        assert!(rejected.is_cancelled());
+        assert_eq!(account.take_error().and_then(|error| error.code), Some(-2010));
+        assert_eq!(account.retry_after(), None);
+
+        assert!(account.update_balances(false).is_empty());
+        assert_eq!(account.retry_after(), Some(2000));
+        assert_eq!(account.retry_after(), None);
+
+        let mut exit = order_request.clone();
+
+        exit.set_price(Decimal::from(20000));
+        exit.attach_to_price_change(OrderType::Limit, Decimal::from(-1000));
+        exit.attach_to_price_change(OrderType::Limit, Decimal::from(2000));
+
+        let legs = account.execute_oco(exit.attached_order_requests());
+
+        assert_eq!(legs[0].order_id(), "31");
+        assert_eq!(legs[1].order_id(), "32");
+        assert!(legs.iter().all(|leg| leg.status() == OrderStatus::New));
+
+        let requests = server.join().unwrap();
+
+        let query = "symbol=BTCUSDT&side=BUY&type=MARKET&quantity=0.50&newOrderRespType=FULL&recvWindow=5000&timestamp=1499827319559";
+
+        assert_eq!(
+            requests[0],
+            format!("POST /api/v3/order?{}&signature={} HTTP/1.1 key", query, account.sign(query)),
+        );
+        assert_eq!(requests[2], "GET /api/v3/depth?symbol=BTCUSDT&limit=5 HTTP/1.1 key");
+        assert!(requests[5].starts_with("POST /api/v3/order/oco?symbol=BTCUSDT&side=SELL&quantity=0.50&price=22000&stopPrice=19000&stopLimitPrice=19000&stopLimitTimeInForce=GTC&"));
+
+        let mut failing = BinanceAccount::new(Failing, "key", "secret");
+
+        assert!(failing.update_balances(false).is_empty());
+        assert_eq!(failing.take_error().map(|error| error.status), Some(0));
+    }
+
+    #[test]
+    fn reads_fees_of_queried_orders_from_their_trades() {
+        let state = r#"{"symbol":"BTCUSDT","orderId":9007199254740993,"status":"PARTIALLY_FILLED","executedQty":"0.2","cummulativeQuoteQty":"4000"}"#;
+
+        let (port, server) = mock_server(vec![
+            (200, state),
+            (500, r#"{"code":-1001,"msg":"Internal error."}"#),
+            (200, state),
+            (200, r#"[{"price":"20000","qty":"0.1","commission":"2","commissionAsset":"USDT"},{"price":"20000","qty":"0.1","commission":"0.0001","commissionAsset":"XBT"}]"#),
+            (200, state),
+        ]);
+
+        let mut account = BinanceAccount::new(TcpTransport::new("127.0.0.1", port), "key", "secret");
+
+        account.set_pair_name("XBT", "USDT", "BTCUSDT");
+
+        let mut order_request = OrderRequest::new(
+            "XBT".to_string(),
+            "USDT".to_string(),
+            OrderSide::Buy,
+            TradeSide::LONG,
+            0,
+            None,
+        );
+
+        order_request.set_price(Decimal::from(20000));
+        order_request.set_quantity(Decimal::ONE);
+
+        let mut order = Order::new_from_order_request(1, order_request);
+
+        order.set_order_id("9007199254740993".to_string());
+
+        // the trades of the fill fail to be queried
+        account.update_order_status(&mut order);
+
+        assert_eq!(order.order_id(), "9007199254740993");
+        assert_eq!(order.executed_quantity(), Decimal::new(2, 1));
+        assert!(account.has_unknown_fees(&order));
+        assert_eq!(account.take_error().map(|error| error.status), Some(500));
+
+        account.update_order_status(&mut order);
+
+        assert!(!account.has_unknown_fees(&order));
+        assert_eq!(order.fees_paid(), Decimal::from(4));
+
+        // nothing more was executed
+        account.update_order_status(&mut order);
+
+        let requests = server.join().unwrap();
+
+        assert_eq!(requests.len(), 5);
+        assert!(requests[1].starts_with("GET /api/v3/myTrades?symbol=BTCUSDT&orderId=9007199254740993&"));
+        assert!(requests[4].starts_with("GET /api/v3/order?"));
+    }
+}
@@ -0,0 +1,228 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Method {
+    Get,
+    Post,
+    Delete,
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Method::Get => write!(f, "GET"),
+            Method::Post => write!(f, "POST"),
+            Method::Delete => write!(f, "DELETE"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub path: String,
+
+    // already encoded
+    pub query: String,
+
+    pub headers: Vec<(String, String)>,
+}
+
+impl HttpRequest {
+    pub fn new(method: Method, path: &str, query: String) -> HttpRequest {
+        HttpRequest {
+            method,
+            path: path.to_string(),
+
+            query,
+
+            headers: vec![],
+        }
+    }
+
+    pub fn header(&mut self, name: &str, value: &str) {
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+
+    /// Path with the query string, as sent on the request line.
+    pub fn target(&self) -> String {
+        if self.query.is_empty() {
+            self.path.clone()
+        } else {
+            format!("{}?{}", self.path, self.query)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, body: &str) -> HttpResponse {
+        HttpResponse {
+            status,
+            headers: vec![],
+            body: body.to_string(),
+        }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Sends requests to an exchange. Replaced by a mock in tests.
+pub trait Transport {
+    fn send(&mut self, request: &HttpRequest) -> io::Result<HttpResponse>;
+}
+
+/// Plain HTTP/1.1 over TCP, one connection per request.
+///
+/// There is no TLS in the standard library, so reaching an exchange over
+/// HTTPS takes a local TLS-terminating proxy, or another `Transport`.
+pub struct TcpTransport {
+    host: String,
+    port: u16,
+
+    timeout: Duration,
+}
+
+impl TcpTransport {
+    pub fn new(host: &str, port: u16) -> TcpTransport {
+        TcpTransport {
+            host: host.to_string(),
+            port,
+
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+}
+
+impl Transport for TcpTransport {
+    fn send(&mut self, request: &HttpRequest) -> io::Result<HttpResponse> {
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port))?;
+
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+
+        let mut head = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: 0\r\n",
+            request.method,
+            request.target(),
+            self.host,
+        );
+
+        for (name, value) in request.headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+
+        head.push_str("\r\n");
+
+        stream.write_all(head.as_bytes())?;
+        stream.flush()?;
+
+        let mut raw = vec![];
+
+        stream.read_to_end(&mut raw)?;
+
+        parse_response(&raw)
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+pub fn parse_response(raw: &[u8]) -> io::Result<HttpResponse> {
+    let text = String::from_utf8_lossy(raw);
+
+    let (head, body) = text.split_once("\r\n\r\n")
+        .ok_or_else(|| invalid("incomplete HTTP response"))?;
+
+    let mut lines = head.split("\r\n");
+
+    let status = lines.next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(|| invalid("invalid HTTP status line"))?;
+
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    let mut response = HttpResponse {
+        status,
+        headers,
+        body: body.to_string(),
+    };
+
+    if response.header("Transfer-Encoding").map(|value| value.eq_ignore_ascii_case("chunked")).unwrap_or(false) {
+        response.body = decode_chunked(body)?;
+    }
+
+    Ok(response)
+}
+
+fn decode_chunked(mut body: &str) -> io::Result<String> {
+    let mut decoded = String::new();
+
+    loop {
+        let (size, rest) = body.split_once("\r\n")
+            .ok_or_else(|| invalid("invalid chunk"))?;
+
+        let size = usize::from_str_radix(size.split(';').next().unwrap_or("").trim(), 16)
+            .map_err(|_| invalid("invalid chunk size"))?;
+
+        if size == 0 {
+            return Ok(decoded);
+        }
+
+        let chunk = rest.get(..size)
+            .ok_or_else(|| invalid("truncated chunk"))?;
+
+        decoded.push_str(chunk);
+
+        body = rest[size..].trim_start_matches("\r\n");
+    }
+}
+
+/// Percent-encodes a query string value.
+pub fn encode(value: &str) -> String {
+    let mut encoded = String::new();
+
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+pub fn query_string(parameters: &[(&str, String)]) -> String {
+    parameters
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, encode(value)))
+        .collect::<Vec<String>>()
+        .join("&")
+}
@@ -0,0 +1,93 @@
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender, channel};
+use std::time::Duration;
+use crate::account::orderbook::OrderBook;
+use crate::candles::candle::Candle;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MarketEvent {
+    /// Candle of a symbol, `closed` once its interval is over. Open candles
+    /// are updates of the one in progress.
+    Candle {
+        symbol: String,
+        candle: Candle,
+        closed: bool,
+    },
+
+    /// Single trade, as a tick candle.
+    Trade {
+        symbol: String,
+        tick: Candle,
+    },
+
+    OrderBook(OrderBook),
+
+    /// The exchange reported a change of the order, by order id.
+    OrderUpdate(String),
+}
+
+/// Where a runtime gets its market data and order updates from.
+pub trait MarketDataSource {
+    fn subscribe(&mut self, symbol: &str);
+
+    /// Next event, waiting for at most `timeout`.
+    fn poll(&mut self, timeout: Duration) -> Option<MarketEvent>;
+
+    /// False once the source will not produce events anymore.
+    fn is_open(&self) -> bool {
+        true
+    }
+}
+
+/// Source fed from other threads through a channel, e.g. by a websocket
+/// client or by tests.
+pub struct ChannelMarketData {
+    receiver: Receiver<MarketEvent>,
+
+    symbols: Vec<String>,
+
+    open: bool,
+}
+
+impl ChannelMarketData {
+    pub fn new() -> (ChannelMarketData, Sender<MarketEvent>) {
+        let (sender, receiver) = channel();
+
+        let market_data = ChannelMarketData {
+            receiver,
+
+            symbols: vec![],
+
+            open: true,
+        };
+
+        (market_data, sender)
+    }
+
+    pub fn symbols(&self) -> &Vec<String> {
+        &self.symbols
+    }
+}
+
+impl MarketDataSource for ChannelMarketData {
+    fn subscribe(&mut self, symbol: &str) {
+        if !self.symbols.iter().any(|subscribed| subscribed == symbol) {
+            self.symbols.push(symbol.to_string());
+        }
+    }
+
+    fn poll(&mut self, timeout: Duration) -> Option<MarketEvent> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(event) => Some(event),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => {
+                self.open = false;
+
+                None
+            }
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.open
+    }
+}
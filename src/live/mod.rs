@@ -0,0 +1,7 @@
+pub mod binance;
+pub mod http;
+pub mod market_data;
+pub mod paper_account;
+pub mod runtime;
+pub mod signing;
+pub mod websocket;
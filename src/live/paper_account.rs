@@ -0,0 +1,480 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::account::balance::Balance;
+use crate::account::order::{Order, OrderSide, OrderType, TriggerCondition};
+use crate::account::order_request::OrderRequest;
+use crate::account::orderbook::OrderBook;
+use crate::candles::candle::Candle;
+use crate::client_account::ClientAccount;
+use crate::decimal::{Decimal, DEFAULT_SCALE};
+use crate::live::runtime::now_ms;
+use crate::simulation::simulated_account::{SimulatedAccount, fill_price};
+use crate::trading_fees::TradingFees;
+
+static BALANCE_HEADERS: [&str; 4] = [
+    "SYMBOL",
+    "FREE",
+    "SHORTED",
+    "MARGIN_RESERVES",
+];
+
+/// Account trading on live market data without sending anything to an
+/// exchange. Orders are handled by a `SimulatedAccount`:
+///
+/// - an order can only fill `latency` milliseconds after it was submitted,
+/// - market orders fill against the latest order book of the symbol if it
+///   is deep enough, at the latest price otherwise,
+/// - market and triggered stop orders lose `slippage_percentage` of the
+///   price on top of that.
+///
+/// Limit and stop prices are checked against the move of the price between
+/// two updates. Balances are saved to `path` after every fill and reloaded
+/// by `open`, as a CSV file with the amount locked by open orders counted
+/// as free, since those orders don't survive a restart.
+pub struct PaperAccount {
+    simulator: SimulatedAccount,
+
+    // time each open order can fill from, by order id
+    fillable_from: HashMap<String, u64>,
+
+    latency: u64,
+    slippage_percentage: Decimal,
+
+    order_books: HashMap<String, OrderBook>,
+    latest_prices: HashMap<String, Decimal>,
+
+    clock: Box<dyn Fn() -> u64>,
+
+    path: Option<String>,
+    save_error: Option<io::Error>,
+}
+
+impl PaperAccount {
+    pub fn new(
+        trading_fees: impl TradingFees + 'static,
+    ) -> PaperAccount {
+        PaperAccount {
+            simulator: SimulatedAccount::new(trading_fees),
+
+            fillable_from: HashMap::new(),
+
+            latency: 250,
+            slippage_percentage: Decimal::new(5, 2),
+
+            order_books: HashMap::new(),
+            latest_prices: HashMap::new(),
+
+            clock: Box::new(now_ms),
+
+            path: None,
+            save_error: None,
+        }
+    }
+
+    /// Account keeping its balances in `path`, starting from the ones saved
+    /// there if the file exists.
+    pub fn open(
+        path: &str,
+        trading_fees: impl TradingFees + 'static,
+    ) -> io::Result<PaperAccount> {
+        let mut account = PaperAccount::new(trading_fees);
+
+        if Path::new(path).exists() {
+            let input = fs::read_to_string(path)?;
+
+            let balances = parse_balances(&input)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+            for balance in balances {
+                let symbol = balance.symbol().to_string();
+
+                *account.simulator.balance_mut(&symbol) = balance;
+            }
+        }
+
+        account.path = Some(path.to_string());
+
+        Ok(account)
+    }
+
+    pub fn simulator(&self) -> &SimulatedAccount {
+        &self.simulator
+    }
+
+    pub fn latency(&self) -> u64 {
+        self.latency
+    }
+
+    pub fn set_latency(&mut self, latency: u64) {
+        self.latency = latency;
+    }
+
+    pub fn slippage_percentage(&self) -> Decimal {
+        self.slippage_percentage
+    }
+
+    pub fn set_slippage_percentage(&mut self, slippage_percentage: Decimal) {
+        self.slippage_percentage = slippage_percentage;
+    }
+
+    /// Replaces the wall clock, in milliseconds, latencies are measured with.
+    pub fn set_clock(&mut self, clock: impl Fn() -> u64 + 'static) {
+        self.clock = Box::new(clock);
+    }
+
+    pub fn set_amount(&mut self, symbol: &str, free: Decimal) {
+        self.simulator.set_amount(symbol, free);
+
+        self.save_quietly();
+    }
+
+    /// Error of the last save done after a fill, if it failed.
+    pub fn take_save_error(&mut self) -> Option<io::Error> {
+        self.save_error.take()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let mut balances: Vec<&Balance> = self.simulator.balances().values().collect();
+
+        balances.sort_by(|a, b| a.symbol().cmp(b.symbol()));
+
+        // written next to the file first so that a crash never leaves it
+        // half written
+        let temporary = format!("{}.tmp", path);
+
+        fs::write(&temporary, format_balances(&balances))?;
+        fs::rename(&temporary, path)
+    }
+
+    fn save_quietly(&mut self) {
+        if let Err(error) = self.save() {
+            self.save_error = Some(error);
+        }
+    }
+
+    fn slipped(&self, side: OrderSide, price: Decimal) -> Decimal {
+        let slippage = price * self.slippage_percentage / Decimal::from(100);
+
+        match side {
+            OrderSide::Buy => price + slippage,
+            OrderSide::Sell => price - slippage,
+        }
+    }
+
+    // average price of a market order walking the book, if deep enough
+    fn book_price(&self, order: &Order) -> Option<Decimal> {
+        let order_book = self.order_books.get(&order.order_request.symbol())?;
+
+        let quantity = order.order_request.quantity();
+
+        let (levels, amount) = if order.order_request.is_buy() {
+            (order_book.asks(), order_book.get_average_ask_amount_by_quantity(quantity))
+        } else {
+            (order_book.bids(), order_book.get_average_bid_amount_by_quantity(quantity))
+        };
+
+        let depth: Decimal = levels.values().copied().sum();
+
+        if !quantity.is_positive() || depth < quantity {
+            return None;
+        }
+
+        Some(amount / quantity)
+    }
+
+    // fills the orders of `symbol` reached by the price moving from
+    // `previous` to `price`
+    fn fill_orders(&mut self, symbol: &str, previous: Decimal, price: Decimal) {
+        let now = (self.clock)();
+
+        let mut orders: Vec<Order> = self.simulator
+            .open_orders()
+            .filter(|order| order.order_request.symbol() == symbol)
+            .filter(|order| self.fillable_from.get(&order.order_id()).map(|time| *time <= now).unwrap_or(true))
+            .cloned()
+            .collect();
+
+        orders.sort_by_key(|order| order.internal_id());
+
+        let tick = Candle::new(
+            now,
+            now,
+            previous.to_f64(),
+            previous.max(price).to_f64(),
+            previous.min(price).to_f64(),
+            price.to_f64(),
+            0.0,
+        );
+
+        let mut filled = false;
+
+        for order in orders {
+            let side = *order.order_request.side();
+
+            let fill = match (order.order_request.trigger_condition(), *order.order_request.order_type()) {
+                (TriggerCondition::None, OrderType::Market) => {
+                    let price = self.book_price(&order).unwrap_or(price);
+
+                    Some(self.slipped(side, price))
+                }
+                (TriggerCondition::None, OrderType::Limit) => fill_price(&order, &tick),
+                _ => fill_price(&order, &tick).map(|price| self.slipped(side, price)),
+            };
+
+            if let Some(fill) = fill {
+                self.simulator.fill_order(&order.order_id(), fill);
+
+                filled = true;
+            }
+        }
+
+        if filled {
+            self.save_quietly();
+        }
+    }
+
+    fn on_price(&mut self, symbol: &str, price: Decimal) {
+        if !price.is_positive() {
+            return;
+        }
+
+        let previous = self.latest_prices
+            .insert(symbol.to_string(), price)
+            .unwrap_or(price);
+
+        self.fill_orders(symbol, previous, price);
+    }
+}
+
+fn format_balances(balances: &[&Balance]) -> String {
+    let mut output = BALANCE_HEADERS.join(",");
+
+    output.push('\n');
+
+    for balance in balances {
+        let mut reserves: Vec<String> = balance.margin_reserves
+            .iter()
+            .filter(|(_, amount)| !amount.is_zero())
+            .map(|(asset, amount)| format!("{}:{}", asset, amount))
+            .collect();
+
+        reserves.sort();
+
+        output.push_str(&format!(
+            "{},{},{},{}\n",
+            balance.symbol(),
+            balance.free + balance.locked,
+            balance.shorted,
+            reserves.join(";"),
+        ));
+    }
+
+    output
+}
+
+fn parse_balances(input: &str) -> Result<Vec<Balance>, String> {
+    let mut balances = vec![];
+
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let columns: Vec<&str> = line.split(',').map(|column| column.trim()).collect();
+
+        if i == 0 && columns[0].eq_ignore_ascii_case(BALANCE_HEADERS[0]) {
+            continue;
+        }
+
+        if columns.len() < 3 {
+            return Err(format!("line {}: expected at least 3 columns", i + 1));
+        }
+
+        let mut balance = Balance::new(columns[0].to_string());
+
+        let free = columns[1].parse::<Decimal>()
+            .map_err(|_| format!("line {}: invalid free amount {}", i + 1, columns[1]))?;
+
+        let shorted = columns[2].parse::<Decimal>()
+            .map_err(|_| format!("line {}: invalid shorted amount {}", i + 1, columns[2]))?;
+
+        balance.free(free);
+        balance.shorted(shorted);
+
+        for reserve in columns.get(3).unwrap_or(&"").split(';').filter(|reserve| !reserve.is_empty()) {
+            let (asset, amount) = reserve.split_once(':')
+                .ok_or_else(|| format!("line {}: invalid margin reserve {}", i + 1, reserve))?;
+
+            let amount = amount.parse::<Decimal>()
+                .map_err(|_| format!("line {}: invalid margin reserve {}", i + 1, reserve))?;
+
+            balance.margin_reserve(asset.to_string(), amount);
+            balance.shorted_asset_symbols.insert(asset.to_string());
+        }
+
+        balances.push(balance);
+    }
+
+    Ok(balances)
+}
+
+impl ClientAccount for PaperAccount {
+    fn execute_order(&mut self, order_details: OrderRequest) -> Order {
+        let order = self.simulator.execute_order(order_details);
+
+        if !order.is_finalized() {
+            self.fillable_from.insert(order.order_id(), (self.clock)() + self.latency);
+        }
+
+        order
+    }
+
+    fn execute_oco(&mut self, order_details: Vec<OrderRequest>) -> Vec<Order> {
+        let orders = self.simulator.execute_oco(order_details);
+
+        for order in orders.iter().filter(|order| !order.is_finalized()) {
+            self.fillable_from.insert(order.order_id(), (self.clock)() + self.latency);
+        }
+
+        orders
+    }
+
+    fn update_balances(&mut self, force: bool) -> HashMap<String, Balance> {
+        self.simulator.update_balances(force)
+    }
+
+    fn update_order_status(&mut self, order: &mut Order) {
+        self.simulator.update_order_status(order);
+
+        if order.is_finalized() {
+            self.fillable_from.remove(&order.order_id());
+        }
+    }
+
+    fn cancel(&mut self, order: &mut Order) {
+        self.simulator.cancel(order);
+
+        if order.is_finalized() {
+            self.fillable_from.remove(&order.order_id());
+        }
+    }
+
+    fn liquidate(&mut self, order_details: OrderRequest, liquidation_fee: Decimal) -> Order {
+        let order = self.simulator.liquidate(order_details, liquidation_fee);
+
+        self.save_quietly();
+
+        order
+    }
+
+    fn is_simulated(&self) -> bool {
+        true
+    }
+
+    fn on_candle(&mut self, symbol: &str, candle: &Candle) {
+        if let Some(close) = Decimal::from_f64(candle.close, DEFAULT_SCALE) {
+            self.on_price(symbol, close);
+        }
+    }
+
+    fn on_order_book(&mut self, order_book: &OrderBook) {
+        self.order_books.insert(order_book.symbol().clone(), order_book.clone());
+
+        if let (Some(bid), Some(ask)) = (order_book.best_bid(), order_book.best_ask()) {
+            self.on_price(order_book.symbol(), (bid + ask) / Decimal::from(2));
+        }
+    }
+
+    fn margin_reserve_percentage(&self) -> u64 {
+        self.simulator.margin_reserve_percentage()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use crate::account::trade::TradeSide;
+    use crate::account::order::OrderStatus;
+    use crate::trading_fees::PercentageFee;
+
+    fn market_buy(quantity: i64) -> OrderRequest {
+        let mut order_request = OrderRequest::new(
+            "BTC".to_string(),
+            "USDT".to_string(),
+            OrderSide::Buy,
+            TradeSide::LONG,
+            0,
+            None,
+        );
+
+        order_request.set_order_type(OrderType::Market);
+        order_request.set_quantity(Decimal::from(quantity));
+
+        order_request
+    }
+
+    #[test]
+    fn fills_after_latency_and_keeps_balances() {
+        let path = std::env::temp_dir().join(format!("paper_account_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+
+        let _ = fs::remove_file(&path);
+
+        let time = Rc::new(Cell::new(1000));
+
+        let mut account = PaperAccount::open(&path, PercentageFee::new(Decimal::ZERO)).unwrap();
+
+        let clock = time.clone();
+        account.set_clock(move || clock.get());
+        account.set_latency(500);
+        account.set_slippage_percentage(Decimal::ONE);
+        account.set_amount("USDT", Decimal::from(1000));
+
+        let candle = Candle::new(0, 999, 100.0, 100.0, 100.0, 100.0, 1.0);
+
+        account.on_candle("BTCUSDT", &candle);
+
+        let mut order = account.execute_order(market_buy(2));
+
+        // still on its way to the simulated exchange
+        time.set(1200);
+        account.on_candle("BTCUSDT", &candle);
+        account.update_order_status(&mut order);
+
+        assert_eq!(order.status(), OrderStatus::New);
+
+        let mut order_book = OrderBook::new("BTCUSDT".to_string(), 10);
+        order_book.add_bid(Decimal::from(99), Decimal::from(5));
+        order_book.add_ask(Decimal::from(101), Decimal::ONE);
+        order_book.add_ask(Decimal::from(103), Decimal::from(5));
+
+        time.set(1500);
+        account.on_order_book(&order_book);
+        account.update_order_status(&mut order);
+
+        // walks the book to 102, then slips 1%
+        assert_eq!(order.status(), OrderStatus::Filled);
+        assert_eq!(order.average_price(), Decimal::new(10302, 2));
+
+        let reopened = PaperAccount::open(&path, PercentageFee::new(Decimal::ZERO)).unwrap();
+
+        assert_eq!(reopened.simulator().balance("BTC").unwrap().free, Decimal::from(2));
+        assert_eq!(
+            reopened.simulator().balance("USDT").unwrap().free,
+            Decimal::from(1000) - Decimal::new(20604, 2),
+        );
+
+        let _ = fs::remove_file(&path);
+    }
+}
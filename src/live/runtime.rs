@@ -0,0 +1,426 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::account::account_manager::AccountManager;
+use crate::account::order::Order;
+use crate::account::orderbook::OrderBook;
+use crate::account::strategy_runner::StrategyRunner;
+use crate::account::trade_switch::TradeSwitcher;
+use crate::candles::candle::Candle;
+use crate::candles::candle_feed::CandleSnapshot;
+use crate::client_account::ClientAccount;
+use crate::live::market_data::{MarketDataSource, MarketEvent};
+use crate::strategies::cross_asset_strategy::CrossAssetStrategy;
+use crate::strategies::strategy::Strategy;
+
+/// What happens to the orders still open when the runtime stops.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ShutdownPolicy {
+    CancelAll,
+    KeepAll,
+
+    // cancels orders that open or add to positions, keeps the ones that
+    // reduce them, e.g. stop losses
+    KeepExits,
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Runs a strategy on live market data, through the same `StrategyRunner`
+/// the backtester uses. Closed candles of the runner's symbol drive the
+/// strategy, every event is also handed to the account so that simulated
+/// accounts can fill their orders.
+///
+/// Open orders are reconciled with `ClientAccount::update_order_status`
+/// whenever the market data reports an order update, and at least every
+/// `reconcile_interval` milliseconds.
+///
+/// Trades are switched to other symbols when a trade switch is set: the
+/// closed candles of its targets are handed to a cross-asset strategy,
+/// whose signals the runner's switcher compares.
+pub struct LiveRuntime<A: ClientAccount, M: MarketDataSource> {
+    account_manager: AccountManager<A>,
+    market_data: M,
+
+    strategy: Box<dyn Strategy>,
+    runner: StrategyRunner,
+
+    order_books: HashMap<String, OrderBook>,
+
+    // latest closed candles of the subscribed symbols, and the strategy
+    // signalling them for trade switches
+    snapshot: CandleSnapshot,
+    switch_strategy: Option<Box<dyn CrossAssetStrategy>>,
+
+    reconcile_interval: u64,
+    last_reconcile: u64,
+
+    poll_timeout: Duration,
+
+    shutdown_policy: ShutdownPolicy,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl<A: ClientAccount, M: MarketDataSource> LiveRuntime<A, M> {
+    pub fn new(
+        account_manager: AccountManager<A>,
+        mut market_data: M,
+        strategy: Box<dyn Strategy>,
+        runner: StrategyRunner,
+    ) -> LiveRuntime<A, M> {
+        market_data.subscribe(&runner.trader().symbol());
+
+        LiveRuntime {
+            account_manager,
+            market_data,
+
+            strategy,
+            runner,
+
+            order_books: HashMap::new(),
+
+            snapshot: CandleSnapshot::default(),
+            switch_strategy: None,
+
+            reconcile_interval: 5000,
+            last_reconcile: 0,
+
+            poll_timeout: Duration::from_millis(250),
+
+            shutdown_policy: ShutdownPolicy::CancelAll,
+            shutdown: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn account_manager(&self) -> &AccountManager<A> {
+        &self.account_manager
+    }
+
+    pub fn account_manager_mut(&mut self) -> &mut AccountManager<A> {
+        &mut self.account_manager
+    }
+
+    pub fn market_data(&self) -> &M {
+        &self.market_data
+    }
+
+    pub fn runner(&self) -> &StrategyRunner {
+        &self.runner
+    }
+
+    /// Lets the runner switch its trades to the targets of the switcher,
+    /// which get subscribed to, when `switch_strategy` signals them.
+    pub fn set_trade_switch(&mut self, trade_switcher: TradeSwitcher, switch_strategy: Box<dyn CrossAssetStrategy>) {
+        for target in trade_switcher.targets() {
+            self.market_data.subscribe(&target.symbol());
+        }
+
+        self.runner.set_trade_switcher(trade_switcher);
+        self.switch_strategy = Some(switch_strategy);
+    }
+
+    pub fn order_book(&self, symbol: &str) -> Option<&OrderBook> {
+        self.order_books.get(symbol)
+    }
+
+    pub fn reconcile_interval(&self) -> u64 {
+        self.reconcile_interval
+    }
+
+    pub fn set_reconcile_interval(&mut self, reconcile_interval: u64) {
+        self.reconcile_interval = reconcile_interval;
+    }
+
+    pub fn set_poll_timeout(&mut self, poll_timeout: Duration) {
+        self.poll_timeout = poll_timeout;
+    }
+
+    pub fn shutdown_policy(&self) -> ShutdownPolicy {
+        self.shutdown_policy
+    }
+
+    pub fn set_shutdown_policy(&mut self, shutdown_policy: ShutdownPolicy) {
+        self.shutdown_policy = shutdown_policy;
+    }
+
+    /// Flag stopping `run` once set, e.g. from a signal handler or another
+    /// thread.
+    pub fn shutdown_handle(&self) -> Arc<AtomicBool> {
+        self.shutdown.clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.shutdown.load(Ordering::SeqCst) && self.market_data.is_open()
+    }
+
+    /// Processes events until shut down or until the market data closes,
+    /// then applies the shutdown policy. Returns the cancelled orders.
+    pub fn run(&mut self) -> Vec<Order> {
+        while self.is_running() {
+            self.step(now_ms());
+        }
+
+        self.shutdown()
+    }
+
+    /// Waits for one event and handles it, then reconciles the open orders
+    /// if they are due.
+    pub fn step(&mut self, now: u64) {
+        if let Some(event) = self.market_data.poll(self.poll_timeout) {
+            self.handle_event(event);
+        }
+
+        if now >= self.last_reconcile + self.reconcile_interval {
+            self.reconcile(now);
+        }
+    }
+
+    pub fn handle_event(&mut self, event: MarketEvent) {
+        match event {
+            MarketEvent::Candle { symbol, candle, closed } => {
+                self.account_manager.account_mut().on_candle(&symbol, &candle);
+
+                if closed && symbol == self.runner.trader().symbol() {
+                    let order_book = self.order_books.get(&symbol);
+
+                    self.runner.on_candle(
+                        &mut self.account_manager,
+                        self.strategy.as_ref(),
+                        &candle,
+                        order_book,
+                    );
+                }
+
+                if closed {
+                    self.check_trade_switch(&symbol, candle);
+                }
+            }
+            MarketEvent::Trade { symbol, tick } => {
+                self.account_manager.account_mut().on_candle(&symbol, &tick);
+            }
+            MarketEvent::OrderBook(order_book) => {
+                self.account_manager.account_mut().on_order_book(&order_book);
+
+                self.order_books.insert(order_book.symbol().clone(), order_book);
+            }
+            MarketEvent::OrderUpdate(_) => self.runner.process_orders(&mut self.account_manager),
+        }
+    }
+
+    fn check_trade_switch(&mut self, symbol: &str, candle: Candle) {
+        let switch_strategy = match self.switch_strategy.as_ref() {
+            Some(switch_strategy) => switch_strategy,
+            None => return,
+        };
+
+        self.snapshot.accept(symbol, candle);
+
+        let signals = switch_strategy.signals(&self.snapshot);
+
+        if signals.is_empty() {
+            return;
+        }
+
+        let _ = self.runner.check_trade_switch(&mut self.account_manager, &self.snapshot, &signals, &[]);
+    }
+
+    /// Polls the status of the open orders.
+    pub fn reconcile(&mut self, now: u64) {
+        self.runner.process_orders(&mut self.account_manager);
+
+        self.last_reconcile = now;
+    }
+
+    /// Stops the runtime and cancels the open orders the policy doesn't
+    /// keep. Returns the cancelled orders.
+    pub fn shutdown(&mut self) -> Vec<Order> {
+        self.shutdown.store(true, Ordering::SeqCst);
+
+        self.runner.process_orders(&mut self.account_manager);
+
+        let order_ids: Vec<String> = self.account_manager.open_orders()
+            .values()
+            .filter(|order| match self.shutdown_policy {
+                ShutdownPolicy::CancelAll => true,
+                ShutdownPolicy::KeepAll => false,
+                ShutdownPolicy::KeepExits => {
+                    order.order_request.is_long_buy() || order.order_request.is_short_sell()
+                }
+            })
+            .map(|order| order.order_id())
+            .collect();
+
+        let cancelled: Vec<Order> = order_ids
+            .iter()
+            .filter_map(|order_id| self.account_manager.cancel_order(order_id))
+            .collect();
+
+        // whatever filled before the cancellation still counts
+        for order in cancelled.iter() {
+            self.runner.trader_mut().process_finalized_order(&mut self.account_manager, order);
+        }
+
+        cancelled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use crate::account::trading_manager::TradingManager;
+    use crate::decimal::Decimal;
+    use crate::indicators::signal::Signal;
+    use crate::live::market_data::ChannelMarketData;
+    use crate::simulation::simulated_account::SimulatedAccount;
+    use crate::strategies::declarative::StrategyDefinition;
+    use crate::trading_fees::PercentageFee;
+
+    struct Range(f64, f64);
+
+    impl Strategy for Range {
+        fn get_signal(&self, candle: Candle) -> Signal {
+            if candle.close < self.0 {
+                Signal::BUY
+            } else if candle.close > self.1 {
+                Signal::SELL
+            } else {
+                Signal::NEUTRAL
+            }
+        }
+    }
+
+    // buys ETH above 60
+    struct EthBreakout;
+
+    impl CrossAssetStrategy for EthBreakout {
+        fn symbols(&self) -> Vec<String> {
+            vec!["BTCUSDT".to_string(), "ETHUSDT".to_string()]
+        }
+
+        fn get_signals(&self, snapshot: &CandleSnapshot) -> BTreeMap<String, Signal> {
+            let mut signals = BTreeMap::new();
+
+            if snapshot.get("ETHUSDT").unwrap().close > 60.0 {
+                signals.insert("ETHUSDT".to_string(), Signal::BUY);
+            }
+
+            signals
+        }
+    }
+
+    fn candle_event(i: u64, close: f64) -> MarketEvent {
+        symbol_candle_event("BTCUSDT", i, close)
+    }
+
+    fn symbol_candle_event(symbol: &str, i: u64, close: f64) -> MarketEvent {
+        MarketEvent::Candle {
+            symbol: symbol.to_string(),
+            candle: Candle::new(i * 60_000, i * 60_000 + 59_999, close, close, close, close, 1.0),
+            closed: true,
+        }
+    }
+
+    #[test]
+    fn trades_and_cancels_on_shutdown() {
+        let mut account = SimulatedAccount::new(PercentageFee::new(Decimal::ZERO));
+
+        account.set_amount("USDT", Decimal::from(1000));
+
+        let (market_data, sender) = ChannelMarketData::new();
+
+        let runner = StrategyRunner::new(
+            TradingManager::new("BTC".to_string(), "USDT".to_string()),
+            50.0,
+            Decimal::ZERO,
+            100,
+        );
+
+        let mut runtime = LiveRuntime::new(
+            AccountManager::new(account, 0, 0, 1.5),
+            market_data,
+            Box::new(Range(95.0, 105.0)),
+            runner,
+        );
+
+        runtime.set_poll_timeout(Duration::from_millis(1));
+
+        assert_eq!(runtime.market_data().symbols(), &vec!["BTCUSDT".to_string()]);
+
+        for (i, close) in [100.0, 90.0, 92.0, 110.0].iter().enumerate() {
+            sender.send(candle_event(i as u64, *close)).unwrap();
+        }
+
+        sender.send(MarketEvent::OrderUpdate("1".to_string())).unwrap();
+
+        drop(sender);
+
+        let cancelled = runtime.run();
+
+        // bought at the open of the candle after the buy signal, the exit
+        // placed on the last candle never filled
+        assert_eq!(runtime.runner().trader().trades().len(), 1);
+        assert_eq!(cancelled.len(), 1);
+        assert!(runtime.account_manager().open_orders().is_empty());
+        assert!(!runtime.is_running());
+    }
+
+    #[test]
+    fn switches_trades_to_stronger_symbols() {
+        let mut account = SimulatedAccount::new(PercentageFee::new(Decimal::ZERO));
+
+        account.set_amount("USDT", Decimal::from(1000));
+
+        let (market_data, sender) = ChannelMarketData::new();
+
+        let btc = TradingManager::new("BTC".to_string(), "USDT".to_string());
+        let eth = TradingManager::new("ETH".to_string(), "USDT".to_string());
+
+        let runner = StrategyRunner::new(btc.clone(), 50.0, Decimal::ZERO, 100);
+
+        let strategy = StrategyDefinition::parse(r#"{
+            "name": "dip",
+            "entry": { "lt": ["close", 95] },
+            "monitor": { "allow_trade_switch": true }
+        }"#).unwrap().build();
+
+        let mut runtime = LiveRuntime::new(
+            AccountManager::new(account, 0, 0, 1.5),
+            market_data,
+            Box::new(strategy),
+            runner,
+        );
+
+        runtime.set_poll_timeout(Duration::from_millis(1));
+        runtime.set_trade_switch(TradeSwitcher::new(vec![btc, eth]), Box::new(EthBreakout));
+
+        assert_eq!(runtime.market_data().symbols().len(), 2);
+
+        // bought below 95, switched once ETH breaks out, the exit filling at
+        // 100 and the entry at 70
+        for (i, (btc, eth)) in [(100.0, 50.0), (90.0, 50.0), (90.0, 50.0), (100.0, 70.0), (100.0, 70.0), (100.0, 70.0)].iter().enumerate() {
+            sender.send(candle_event(i as u64, *btc)).unwrap();
+            sender.send(symbol_candle_event("ETHUSDT", i as u64, *eth)).unwrap();
+        }
+
+        drop(sender);
+
+        runtime.run();
+
+        let trader = runtime.runner().trader();
+
+        assert_eq!(trader.symbol(), "ETHUSDT");
+        assert_eq!(trader.finalized_trades()[0].assets_symbol(), Some("BTC"));
+        assert_eq!(trader.trades()[0].strategy_name(), Some("dip"));
+        assert_eq!(trader.trades()[0].monitors.len(), 1);
+
+        // the proceeds of 5.55555555 BTC at 100, less the 0.5% fee reserve, at 70
+        assert_eq!(trader.trades()[0].total_units(), "7.89682538".parse().unwrap());
+    }
+}
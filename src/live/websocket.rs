@@ -0,0 +1,1002 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::account::orderbook::OrderBook;
+use crate::candles::candle::Candle;
+use crate::decimal::Decimal;
+use crate::json::JsonValue;
+use crate::live::market_data::{MarketDataSource, MarketEvent};
+use crate::live::runtime::now_ms;
+use crate::simulation::random::Random;
+
+static HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+// diffs kept for each order book to seed
+const MAX_DEPTH_UPDATES: usize = 1000;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+fn sha1(message: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+    let mut padded = message.to_vec();
+
+    padded.push(0x80);
+
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+
+    padded.extend_from_slice(&((message.len() as u64) * 8).to_be_bytes());
+
+    for block in padded.chunks(64) {
+        let mut w = [0u32; 80];
+
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+
+            let temp = a.rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (word, value) in state.iter_mut().zip([a, b, c, d, e].iter()) {
+            *word = word.wrapping_add(*value);
+        }
+    }
+
+    let mut digest = [0; 20];
+
+    for (i, word) in state.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+
+    digest
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::new();
+
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(alphabet[(n >> (18 - i * 6)) as usize & 0x3f] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Value of `Sec-WebSocket-Accept` a server answers to `key` with.
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key, HANDSHAKE_GUID).as_bytes()))
+}
+
+fn encode_frame(opcode: u8, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+    let mut frame = vec![0x80 | opcode];
+
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+
+    if payload.len() < 126 {
+        frame.push(mask_bit | payload.len() as u8);
+    } else if payload.len() <= u16::MAX as usize {
+        frame.push(mask_bit | 126);
+        frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    } else {
+        frame.push(mask_bit | 127);
+        frame.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+    }
+
+    match mask {
+        Some(mask) => {
+            frame.extend_from_slice(&mask);
+            frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ mask[i % 4]));
+        }
+        None => frame.extend_from_slice(payload),
+    }
+
+    frame
+}
+
+// first complete frame of the buffer and its length, if there is one
+fn decode_frame(buffer: &[u8]) -> Option<(Frame, usize)> {
+    if buffer.len() < 2 {
+        return None;
+    }
+
+    let fin = buffer[0] & 0x80 != 0;
+    let opcode = buffer[0] & 0x0f;
+    let masked = buffer[1] & 0x80 != 0;
+
+    let (length, mut offset) = match buffer[1] & 0x7f {
+        126 => {
+            let bytes = buffer.get(2..4)?;
+
+            (u16::from_be_bytes([bytes[0], bytes[1]]) as usize, 4)
+        }
+        127 => {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(buffer.get(2..10)?);
+
+            (u64::from_be_bytes(bytes) as usize, 10)
+        }
+        length => (length as usize, 2),
+    };
+
+    let mask = if masked {
+        let mask = buffer.get(offset..offset + 4)?;
+        offset += 4;
+
+        Some([mask[0], mask[1], mask[2], mask[3]])
+    } else {
+        None
+    };
+
+    // a length read from the frame can be anything
+    let end = offset.checked_add(length)?;
+
+    let payload = buffer.get(offset..end)?;
+
+    let payload = match mask {
+        Some(mask) => payload.iter().enumerate().map(|(i, byte)| byte ^ mask[i % 4]).collect(),
+        None => payload.to_vec(),
+    };
+
+    Some((Frame { fin, opcode, payload }, end))
+}
+
+/// Client side of a WebSocket connection, over plain TCP (`ws://`).
+pub struct WebSocketConnection {
+    stream: TcpStream,
+
+    // received bytes not making a complete frame yet
+    buffer: Vec<u8>,
+
+    // payload of a fragmented message
+    fragments: Vec<u8>,
+
+    random: Random,
+}
+
+impl WebSocketConnection {
+    pub fn connect(host: &str, port: u16, path: &str, timeout: Duration) -> io::Result<WebSocketConnection> {
+        let mut stream = TcpStream::connect((host, port))?;
+
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        stream.set_nodelay(true)?;
+
+        let mut random = Random::new(now_ms() ^ ((port as u64) << 32));
+
+        let mut nonce = [0u8; 16];
+
+        for byte in nonce.iter_mut() {
+            *byte = random.next_u64() as u8;
+        }
+
+        let key = base64(&nonce);
+
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: {}:{}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            path,
+            host,
+            port,
+            key,
+        )?;
+
+        // the answer ends with an empty line, frames may follow right away
+        let mut response = vec![];
+        let mut byte = [0; 1];
+
+        while !response.ends_with(b"\r\n\r\n") {
+            if stream.read(&mut byte)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "handshake interrupted"));
+            }
+
+            response.push(byte[0]);
+        }
+
+        let response = String::from_utf8_lossy(&response);
+
+        if response.split_whitespace().nth(1) != Some("101") {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "websocket upgrade refused"));
+        }
+
+        let accepted = response
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .any(|(name, value)| {
+                name.trim().eq_ignore_ascii_case("Sec-WebSocket-Accept") && value.trim() == accept_key(&key)
+            });
+
+        if !accepted {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid Sec-WebSocket-Accept"));
+        }
+
+        Ok(WebSocketConnection {
+            stream,
+
+            buffer: vec![],
+
+            fragments: vec![],
+
+            random,
+        })
+    }
+
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        // clients mask everything they send
+        let mask = (self.random.next_u64() as u32).to_be_bytes();
+
+        self.stream.write_all(&encode_frame(opcode, payload, Some(mask)))
+    }
+
+    pub fn send_text(&mut self, text: &str) -> io::Result<()> {
+        self.send_frame(OPCODE_TEXT, text.as_bytes())
+    }
+
+    pub fn close(&mut self) -> io::Result<()> {
+        self.send_frame(OPCODE_CLOSE, &[])
+    }
+
+    /// Next text message, waiting for at most `timeout`. Pings are answered
+    /// on the way, a closed connection is an error.
+    pub fn read_message(&mut self, timeout: Duration) -> io::Result<Option<String>> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            while let Some((frame, length)) = decode_frame(&self.buffer) {
+                self.buffer.drain(..length);
+
+                match frame.opcode {
+                    OPCODE_PING => self.send_frame(OPCODE_PONG, &frame.payload)?,
+                    OPCODE_PONG => {}
+                    OPCODE_CLOSE => {
+                        let _ = self.send_frame(OPCODE_CLOSE, &frame.payload);
+
+                        return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "closed by the server"));
+                    }
+                    OPCODE_TEXT | OPCODE_BINARY | OPCODE_CONTINUATION => {
+                        self.fragments.extend_from_slice(&frame.payload);
+
+                        if frame.fin {
+                            let message = String::from_utf8_lossy(&self.fragments).to_string();
+
+                            self.fragments.clear();
+
+                            return Ok(Some(message));
+                        }
+                    }
+                    _ => {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown websocket opcode"));
+                    }
+                }
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+
+            self.stream.set_read_timeout(Some(remaining))?;
+
+            let mut chunk = [0; 4096];
+
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed")),
+                Ok(read) => self.buffer.extend_from_slice(&chunk[..read]),
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock || error.kind() == io::ErrorKind::TimedOut => {
+                    return Ok(None);
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+/// Market data from the WebSocket streams of a Binance-style exchange:
+/// klines of one interval, individual trades and order book diffs of each
+/// subscribed symbol.
+///
+/// Klines become candle events, closed once the exchange marks them so.
+/// Trades become tick candles. Diffs are applied to a local order book,
+/// seeded with a REST snapshot by `set_order_book`, and the book is sent
+/// after each of them. Diffs the snapshot already includes are dropped,
+/// and a diff that doesn't follow the last one applied invalidates the
+/// book. Diffs of books to seed, listed by `unseeded_order_books`, are
+/// kept until they are, and no book is sent meanwhile.
+///
+/// A dropped connection is reopened on the next poll, waiting
+/// `reconnect_delay` between attempts and doubling it up to
+/// `max_reconnect_delay` while they fail, and every stream is subscribed
+/// again. Local order books must be seeded again then, since diffs were
+/// missed.
+pub struct WebSocketMarketData {
+    host: String,
+    port: u16,
+    path: String,
+
+    connection: Option<WebSocketConnection>,
+
+    symbols: Vec<String>,
+
+    kline_interval: Option<String>,
+    trades: bool,
+    depth: Option<u64>,
+
+    // seeded order books, and the diffs of those to seed
+    order_books: HashMap<String, OrderBook>,
+    depth_updates: HashMap<String, VecDeque<DepthUpdate>>,
+
+    pending: VecDeque<MarketEvent>,
+
+    reconnect_delay: Duration,
+    max_reconnect_delay: Duration,
+    current_delay: Duration,
+    next_attempt: Option<Instant>,
+    connections: u64,
+
+    request_id: u64,
+
+    open: bool,
+}
+
+impl WebSocketMarketData {
+    pub fn new(host: &str, port: u16, path: &str) -> WebSocketMarketData {
+        WebSocketMarketData {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+
+            connection: None,
+
+            symbols: vec![],
+
+            kline_interval: Some("1m".to_string()),
+            trades: false,
+            depth: None,
+
+            order_books: HashMap::new(),
+            depth_updates: HashMap::new(),
+
+            pending: VecDeque::new(),
+
+            reconnect_delay: Duration::from_millis(500),
+            max_reconnect_delay: Duration::from_secs(30),
+            current_delay: Duration::from_millis(500),
+            next_attempt: None,
+            connections: 0,
+
+            request_id: 0,
+
+            open: true,
+        }
+    }
+
+    /// Kline interval, e.g. `1m` or `1h`, none to not receive klines.
+    pub fn set_kline_interval(&mut self, kline_interval: Option<&str>) {
+        self.kline_interval = kline_interval.map(|interval| interval.to_string());
+    }
+
+    pub fn set_trades(&mut self, trades: bool) {
+        self.trades = trades;
+    }
+
+    /// Levels of the order books kept on each side, none to not receive
+    /// order book diffs.
+    pub fn set_depth(&mut self, depth: Option<u64>) {
+        self.depth = depth;
+    }
+
+    pub fn set_reconnect_delay(&mut self, reconnect_delay: Duration, max_reconnect_delay: Duration) {
+        self.reconnect_delay = reconnect_delay;
+        self.max_reconnect_delay = max_reconnect_delay;
+        self.current_delay = reconnect_delay;
+    }
+
+    /// Seeds the order book of a symbol with a snapshot, to which the diffs
+    /// received since are applied.
+    pub fn set_order_book(&mut self, order_book: OrderBook) {
+        let symbol = order_book.symbol().clone();
+
+        self.order_books.insert(symbol.clone(), order_book);
+
+        let updates = self.depth_updates.remove(&symbol).unwrap_or_default();
+
+        for update in updates {
+            self.apply_depth_update(&symbol, update);
+        }
+    }
+
+    /// Subscribed symbols whose order book must be seeded, none if diffs
+    /// aren't received.
+    pub fn unseeded_order_books(&self) -> Vec<String> {
+        if self.depth.is_none() {
+            return vec![];
+        }
+
+        self.symbols
+            .iter()
+            .filter(|symbol| !self.order_books.contains_key(*symbol))
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_some()
+    }
+
+    /// Number of times a connection was opened.
+    pub fn connections(&self) -> u64 {
+        self.connections
+    }
+
+    pub fn close(&mut self) {
+        if let Some(mut connection) = self.connection.take() {
+            let _ = connection.close();
+        }
+
+        self.open = false;
+    }
+
+    pub fn streams(&self, symbol: &str) -> Vec<String> {
+        let name = symbol.to_lowercase();
+
+        let mut streams = vec![];
+
+        if let Some(interval) = &self.kline_interval {
+            streams.push(format!("{}@kline_{}", name, interval));
+        }
+
+        if self.trades {
+            streams.push(format!("{}@trade", name));
+        }
+
+        if self.depth.is_some() {
+            streams.push(format!("{}@depth@100ms", name));
+        }
+
+        streams
+    }
+
+    fn send_subscription(&mut self, symbols: &[String]) -> io::Result<()> {
+        let streams: Vec<String> = symbols
+            .iter()
+            .flat_map(|symbol| self.streams(symbol))
+            .map(|stream| format!("\"{}\"", stream))
+            .collect();
+
+        if streams.is_empty() || self.connection.is_none() {
+            return Ok(());
+        }
+
+        self.request_id += 1;
+
+        let message = format!(
+            "{{\"method\":\"SUBSCRIBE\",\"params\":[{}],\"id\":{}}}",
+            streams.join(","),
+            self.request_id,
+        );
+
+        match &mut self.connection {
+            Some(connection) => connection.send_text(&message),
+            None => Ok(()),
+        }
+    }
+
+    fn connect(&mut self, timeout: Duration) {
+        if let Some(next_attempt) = self.next_attempt {
+            let now = Instant::now();
+
+            if now < next_attempt {
+                thread::sleep(timeout.min(next_attempt - now));
+
+                return;
+            }
+        }
+
+        match WebSocketConnection::connect(&self.host, self.port, &self.path, timeout.max(Duration::from_secs(1))) {
+            Ok(connection) => {
+                self.connection = Some(connection);
+                self.connections += 1;
+
+                self.current_delay = self.reconnect_delay;
+                self.next_attempt = None;
+
+                let symbols = self.symbols.clone();
+
+                if self.send_subscription(&symbols).is_err() {
+                    self.disconnect();
+                }
+            }
+            Err(_) => {
+                self.next_attempt = Some(Instant::now() + self.current_delay);
+
+                self.current_delay = (self.current_delay * 2).min(self.max_reconnect_delay);
+            }
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.connection = None;
+
+        // diffs will be missed until the connection is reopened
+        self.order_books.clear();
+        self.depth_updates.clear();
+
+        self.next_attempt = Some(Instant::now() + self.current_delay);
+    }
+
+    // exchange symbol of a message back to the subscribed symbol
+    fn local_symbol(&self, symbol: &str) -> String {
+        self.symbols
+            .iter()
+            .find(|subscribed| subscribed.eq_ignore_ascii_case(symbol))
+            .cloned()
+            .unwrap_or_else(|| symbol.to_string())
+    }
+
+    /// Events of one message. Answers to subscriptions and unknown events
+    /// give none.
+    pub fn parse_message(&mut self, message: &str) -> Vec<MarketEvent> {
+        let message = match JsonValue::parse(message) {
+            Ok(message) => message,
+            Err(_) => return vec![],
+        };
+
+        // messages of combined streams are wrapped with the stream name
+        let data = message.get("data").unwrap_or(&message);
+
+        let symbol = match data.get("s").and_then(|symbol| symbol.as_str()) {
+            Some(symbol) => self.local_symbol(symbol),
+            None => return vec![],
+        };
+
+        match data.get("e").and_then(|event| event.as_str()) {
+            Some("kline") => {
+                let kline = match data.get("k") {
+                    Some(kline) => kline,
+                    None => return vec![],
+                };
+
+                let candle = Candle::new(
+                    integer(kline.get("t")),
+                    integer(kline.get("T")),
+                    number(kline.get("o")),
+                    number(kline.get("h")),
+                    number(kline.get("l")),
+                    number(kline.get("c")),
+                    number(kline.get("v")),
+                );
+
+                let closed = kline.get("x").and_then(|closed| closed.as_bool()).unwrap_or(false);
+
+                vec![MarketEvent::Candle { symbol, candle, closed }]
+            }
+            Some("trade") | Some("aggTrade") => {
+                let time = integer(data.get("T"));
+                let price = number(data.get("p"));
+
+                let tick = Candle::new(time, time, price, price, price, price, number(data.get("q")));
+
+                vec![MarketEvent::Trade { symbol, tick }]
+            }
+            Some("depthUpdate") => {
+                let levels = |side: &str| -> Vec<(Decimal, Decimal)> {
+                    data.get(side)
+                        .and_then(|levels| levels.as_array())
+                        .unwrap_or(&vec![])
+                        .iter()
+                        .filter_map(|level| match level.as_array() {
+                            Some(level) if level.len() >= 2 => Some((decimal(&level[0])?, decimal(&level[1])?)),
+                            _ => None,
+                        })
+                        .collect()
+                };
+
+                let update = DepthUpdate {
+                    first_id: integer(data.get("U")),
+                    last_id: integer(data.get("u")),
+
+                    bids: levels("b"),
+                    asks: levels("a"),
+                };
+
+                if self.apply_depth_update(&symbol, update) {
+                    vec![MarketEvent::OrderBook(self.order_books[&symbol].clone())]
+                } else {
+                    vec![]
+                }
+            }
+            _ => vec![],
+        }
+    }
+
+    // applies a diff to the order book of a symbol, or keeps it until the
+    // book is seeded, and returns whether the book changed
+    fn apply_depth_update(&mut self, symbol: &str, update: DepthUpdate) -> bool {
+        let order_book = match self.order_books.get_mut(symbol) {
+            Some(order_book) => order_book,
+            None => {
+                let updates = self.depth_updates.entry(symbol.to_string()).or_default();
+
+                if updates.len() == MAX_DEPTH_UPDATES {
+                    updates.pop_front();
+                }
+
+                updates.push_back(update);
+
+                return false;
+            }
+        };
+
+        let last_id = order_book.last_update_id();
+
+        // already included in the snapshot or applied
+        if update.last_id <= last_id {
+            return false;
+        }
+
+        // updates were missed, the book must be seeded again
+        if update.first_id > last_id + 1 {
+            self.order_books.remove(symbol);
+
+            return self.apply_depth_update(symbol, update);
+        }
+
+        for (price, quantity) in update.bids {
+            // a zero quantity removes the level
+            if quantity.is_zero() {
+                order_book.remove_bid(price);
+            } else {
+                order_book.add_bid(price, quantity);
+            }
+        }
+
+        for (price, quantity) in update.asks {
+            if quantity.is_zero() {
+                order_book.remove_ask(price);
+            } else {
+                order_book.add_ask(price, quantity);
+            }
+        }
+
+        order_book.set_last_update_id(update.last_id);
+
+        true
+    }
+}
+
+// levels of an order book changed by updates `first_id` to `last_id`
+struct DepthUpdate {
+    first_id: u64,
+    last_id: u64,
+
+    bids: Vec<(Decimal, Decimal)>,
+    asks: Vec<(Decimal, Decimal)>,
+}
+
+fn decimal(value: &JsonValue) -> Option<Decimal> {
+    match value {
+        JsonValue::String(value) => value.parse::<Decimal>().ok(),
+        // exactly as written, unless in exponent notation
+        JsonValue::Number(value, text) => text.parse::<Decimal>()
+            .or_else(|_| format!("{}", value).parse::<Decimal>())
+            .ok(),
+        _ => None,
+    }
+}
+
+// prices and quantities come as strings
+fn number(value: Option<&JsonValue>) -> f64 {
+    match value {
+        Some(JsonValue::String(value)) => value.parse::<f64>().unwrap_or(0.0),
+        Some(JsonValue::Number(value, _)) => *value,
+        _ => 0.0,
+    }
+}
+
+// times and update ids, exactly
+fn integer(value: Option<&JsonValue>) -> u64 {
+    match value {
+        Some(JsonValue::String(value)) => value.parse::<u64>().unwrap_or(0),
+        Some(value) => value.as_u64().unwrap_or_else(|| number(Some(value)) as u64),
+        None => 0,
+    }
+}
+
+impl MarketDataSource for WebSocketMarketData {
+    fn subscribe(&mut self, symbol: &str) {
+        if self.symbols.iter().any(|subscribed| subscribed == symbol) {
+            return;
+        }
+
+        self.symbols.push(symbol.to_string());
+
+        if self.send_subscription(&[symbol.to_string()]).is_err() {
+            self.disconnect();
+        }
+    }
+
+    fn poll(&mut self, timeout: Duration) -> Option<MarketEvent> {
+        if let Some(event) = self.pending.pop_front() {
+            return Some(event);
+        }
+
+        if !self.open {
+            return None;
+        }
+
+        if self.connection.is_none() {
+            self.connect(timeout);
+        }
+
+        let message = self.connection.as_mut()?.read_message(timeout);
+
+        match message {
+            Ok(Some(message)) => {
+                let events = self.parse_message(&message);
+
+                self.pending.extend(events);
+            }
+            Ok(None) => {}
+            Err(_) => self.disconnect(),
+        }
+
+        self.pending.pop_front()
+    }
+
+    fn is_open(&self) -> bool {
+        self.open
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    // accepts a connection, answers the handshake and returns the first
+    // message of the client
+    fn accept(listener: &TcpListener) -> (TcpStream, String) {
+        let (mut stream, _) = listener.accept().unwrap();
+
+        let mut request = vec![];
+        let mut byte = [0; 1];
+
+        while !request.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            request.push(byte[0]);
+        }
+
+        let request = String::from_utf8(request).unwrap();
+
+        let key = request
+            .lines()
+            .find_map(|line| line.strip_prefix("Sec-WebSocket-Key: "))
+            .unwrap()
+            .trim()
+            .to_string();
+
+        write!(
+            stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(&key),
+        ).unwrap();
+
+        let mut buffer = vec![];
+
+        loop {
+            if let Some((frame, _)) = decode_frame(&buffer) {
+                return (stream, String::from_utf8(frame.payload).unwrap());
+            }
+
+            let mut chunk = [0; 1024];
+            let read = stream.read(&mut chunk).unwrap();
+
+            buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+
+    fn send(stream: &mut TcpStream, message: &str) {
+        stream.write_all(&encode_frame(OPCODE_TEXT, message.as_bytes(), None)).unwrap();
+    }
+
+    #[test]
+    fn streams_and_reconnects() {
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = thread::spawn(move || {
+            let (mut stream, first) = accept(&listener);
+
+            send(&mut stream, r#"{"result":null,"id":1}"#);
+            send(&mut stream, r#"{"stream":"btcusdt@kline_1m","data":{"e":"kline","s":"BTCUSDT","k":{"t":0,"T":59999,"o":"100.0","h":"101.5","l":"99.0","c":"101.0","v":"3.5","x":false}}}"#);
+            stream.write_all(&encode_frame(OPCODE_PING, b"ping", None)).unwrap();
+            send(&mut stream, r#"{"e":"trade","s":"BTCUSDT","p":"101.2","q":"0.1","T":30000}"#);
+            send(&mut stream, r#"{"e":"depthUpdate","s":"BTCUSDT","U":5,"u":8,"b":[["101.0","2.0"],["100.5","1.0"]],"a":[["101.5","1.0"]]}"#);
+            send(&mut stream, r#"{"e":"depthUpdate","s":"BTCUSDT","U":9,"u":9,"b":[["101.0","0"]],"a":[]}"#);
+
+            drop(stream);
+
+            let (mut stream, second) = accept(&listener);
+
+            send(&mut stream, r#"{"e":"kline","s":"BTCUSDT","k":{"t":0,"T":59999,"o":"100.0","h":"102.0","l":"99.0","c":"101.8","v":"5.0","x":true}}"#);
+            stream.write_all(&encode_frame(OPCODE_CLOSE, &[], None)).unwrap();
+
+            (first, second)
+        });
+
+        let mut market_data = WebSocketMarketData::new("127.0.0.1", port, "/ws");
+
+        market_data.set_trades(true);
+        market_data.set_depth(Some(10));
+        market_data.set_reconnect_delay(Duration::from_millis(10), Duration::from_millis(50));
+        market_data.subscribe("BTCUSDT");
+
+        // seeded before the connection is opened
+        let mut snapshot = OrderBook::new("BTCUSDT".to_string(), 10);
+
+        snapshot.set_last_update_id(6);
+        market_data.set_order_book(snapshot);
+
+        let mut events = vec![];
+
+        for _ in 0..500 {
+            if events.len() == 5 {
+                break;
+            }
+
+            if let Some(event) = market_data.poll(Duration::from_millis(20)) {
+                events.push(event);
+            }
+        }
+
+        let (first, second) = server.join().unwrap();
+
+        assert_eq!(first, r#"{"method":"SUBSCRIBE","params":["btcusdt@kline_1m","btcusdt@trade","btcusdt@depth@100ms"],"id":1}"#);
+        assert!(second.contains("btcusdt@kline_1m"));
+        assert_eq!(market_data.connections(), 2);
+        assert_eq!(market_data.unseeded_order_books(), ["BTCUSDT"]);
+
+        match &events[0] {
+            MarketEvent::Candle { candle, closed, .. } => {
+                assert_eq!(candle.close, 101.0);
+                assert!(!closed);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+
+        match &events[1] {
+            MarketEvent::Trade { symbol, tick } => {
+                assert_eq!(symbol, "BTCUSDT");
+                assert!(tick.is_tick());
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+
+        match &events[3] {
+            MarketEvent::OrderBook(order_book) => {
+                assert_eq!(order_book.best_bid(), Some(Decimal::new(1005, 1)));
+                assert_eq!(order_book.best_ask(), Some(Decimal::new(1015, 1)));
+                assert_eq!(order_book.last_update_id(), 9);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+
+        match &events[4] {
+            MarketEvent::Candle { candle, closed, .. } => {
+                assert_eq!(candle.close, 101.8);
+                assert!(closed);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn sequences_order_book_diffs() {
+        let mut market_data = WebSocketMarketData::new("127.0.0.1", 0, "/ws");
+
+        market_data.set_depth(Some(10));
+        market_data.subscribe("BTCUSDT");
+
+        assert_eq!(market_data.unseeded_order_books(), ["BTCUSDT"]);
+
+        let diff = |first_id: u64, last_id: u64, bid: &str| {
+            format!(r#"{{"e":"depthUpdate","s":"BTCUSDT","U":{},"u":{},"b":[["{}","1.0"]],"a":[]}}"#, first_id, last_id, bid)
+        };
+
+        let snapshot = |last_update_id: u64| {
+            let mut order_book = OrderBook::new("BTCUSDT".to_string(), 10);
+
+            order_book.add_ask(Decimal::from(110), Decimal::ONE);
+            order_book.set_last_update_id(last_update_id);
+
+            order_book
+        };
+
+        let best_bid = |events: Vec<MarketEvent>| match events.as_slice() {
+            [MarketEvent::OrderBook(order_book)] => order_book.best_bid(),
+            [] => None,
+            events => panic!("unexpected events {:?}", events),
+        };
+
+        // kept until the book is seeded, then applied from the snapshot on
+        assert_eq!(best_bid(market_data.parse_message(&diff(9, 12, "100"))), None);
+
+        market_data.set_order_book(snapshot(10));
+
+        assert!(market_data.unseeded_order_books().is_empty());
+
+        // already applied
+        assert_eq!(best_bid(market_data.parse_message(&diff(11, 12, "105"))), None);
+        assert_eq!(best_bid(market_data.parse_message(&diff(13, 14, "101"))), Some(Decimal::from(101)));
+
+        // update 15 was missed
+        assert_eq!(best_bid(market_data.parse_message(&diff(16, 17, "102"))), None);
+        assert_eq!(market_data.unseeded_order_books(), ["BTCUSDT"]);
+        assert_eq!(best_bid(market_data.parse_message(&diff(18, 18, "103"))), None);
+
+        market_data.set_order_book(snapshot(17));
+
+        assert_eq!(best_bid(market_data.parse_message(&diff(19, 19, "99"))), Some(Decimal::from(103)));
+    }
+
+    #[test]
+    fn rejects_frames_longer_than_memory() {
+        let mut buffer = vec![0x81, 127];
+
+        buffer.extend_from_slice(&u64::MAX.to_be_bytes());
+
+        assert!(decode_frame(&buffer).is_none());
+    }
+}
@@ -0,0 +1,145 @@
+use crate::account::account_manager::AccountManager;
+use crate::account::equity::EquityCurve;
+use crate::account::strategy_runner::StrategyRunner;
+use crate::account::trade::Trade;
+use crate::account::trading_manager::TradingManager;
+use crate::candles::candle::Candle;
+use crate::decimal::Decimal;
+use crate::simulation::performance_report::PerformanceReport;
+use crate::simulation::simulated_account::SimulatedAccount;
+use crate::strategies::strategy::Strategy;
+use crate::trading_fees::PercentageFee;
+
+/// Outcome of a backtest.
+pub struct BacktestResult {
+    pub trades: Vec<Trade>,
+    pub equity_curve: EquityCurve,
+    pub report: PerformanceReport,
+}
+
+/// Runs a strategy over the candles of one symbol on a simulated account.
+///
+/// Signals are acted on by a `StrategyRunner`, with market orders that fill
+/// at the open of the next candle. Each entry uses `order_percentage` of the
+/// free funds.
+#[derive(Clone, Debug)]
+pub struct Backtester {
+    assets_symbol: String,
+    funds_symbol: String,
+
+    initial_funds: Decimal,
+
+    // percentage taken by each fill
+    fee_percentage: Decimal,
+
+    order_percentage: f64,
+
+    // candles kept for the context of the strategy
+    history_capacity: usize,
+}
+
+impl Backtester {
+    pub fn new(
+        assets_symbol: String,
+        funds_symbol: String,
+        initial_funds: Decimal,
+        fee_percentage: Decimal,
+    ) -> Backtester {
+        Backtester {
+            assets_symbol,
+            funds_symbol,
+
+            initial_funds,
+
+            fee_percentage,
+
+            order_percentage: 100.0,
+
+            history_capacity: 500,
+        }
+    }
+
+    pub fn assets_symbol(&self) -> &str {
+        &self.assets_symbol
+    }
+
+    pub fn funds_symbol(&self) -> &str {
+        &self.funds_symbol
+    }
+
+    pub fn symbol(&self) -> String {
+        format!("{}{}", self.assets_symbol, self.funds_symbol)
+    }
+
+    pub fn initial_funds(&self) -> Decimal {
+        self.initial_funds
+    }
+
+    pub fn fee_percentage(&self) -> Decimal {
+        self.fee_percentage
+    }
+
+    pub fn set_fee_percentage(&mut self, fee_percentage: Decimal) {
+        self.fee_percentage = fee_percentage;
+    }
+
+    pub fn order_percentage(&self) -> f64 {
+        self.order_percentage
+    }
+
+    pub fn set_order_percentage(&mut self, order_percentage: f64) {
+        self.order_percentage = order_percentage;
+    }
+
+    pub fn history_capacity(&self) -> usize {
+        self.history_capacity
+    }
+
+    pub fn set_history_capacity(&mut self, history_capacity: usize) {
+        self.history_capacity = history_capacity;
+    }
+
+    pub fn run(
+        &self,
+        strategy: &dyn Strategy,
+        candles: &[Candle],
+    ) -> BacktestResult {
+        let symbol = self.symbol();
+
+        let mut account = SimulatedAccount::new(PercentageFee::new(self.fee_percentage));
+
+        account.set_amount(&self.funds_symbol, self.initial_funds);
+
+        let mut account_manager = AccountManager::new(account, 0, 0, 1.5);
+
+        account_manager.add_market(&self.assets_symbol, &self.funds_symbol);
+        account_manager.set_reference_symbol(&self.funds_symbol);
+
+        let mut runner = StrategyRunner::new(
+            TradingManager::new(self.assets_symbol.clone(), self.funds_symbol.clone()),
+            self.order_percentage,
+            self.fee_percentage,
+            self.history_capacity,
+        );
+
+        for candle in candles {
+            account_manager.account_mut().accept_candle(&symbol, candle);
+
+            runner.on_candle(&mut account_manager, strategy, candle, None);
+        }
+
+        let equity_curve = account_manager.equity_curve()
+            .cloned()
+            .unwrap_or_else(|| EquityCurve::new(self.funds_symbol.clone()));
+
+        let trades = runner.into_trades();
+
+        let report = PerformanceReport::new(&trades, &equity_curve);
+
+        BacktestResult {
+            trades,
+            equity_curve,
+            report,
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::ops::Bound;
+use crate::decimal::Decimal;
+
+static FUNDING_HISTORY_HEADERS: [&str; 3] = [
+    "FUNDING_TIME",
+    "FUNDING_RATE",
+    "MARK_PRICE",
+];
+
+/// Funding rate applied at one funding time. The rate is a fraction of the
+/// notional value, e.g. 0.0001 for 0.01%.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FundingRate {
+    pub time: u64,
+    pub rate: Decimal,
+
+    // mark price the payment was computed with, if recorded
+    pub mark_price: Option<Decimal>,
+}
+
+/// Funding rates of a perpetual contract, by funding time.
+///
+/// Histories are stored as CSV files with a `FUNDING_TIME,FUNDING_RATE`
+/// header and an optional `MARK_PRICE` column, times in milliseconds.
+#[derive(Clone, Debug, Default)]
+pub struct FundingHistory {
+    rates: BTreeMap<u64, FundingRate>,
+}
+
+impl FundingHistory {
+    pub fn new() -> FundingHistory {
+        FundingHistory {
+            rates: BTreeMap::new(),
+        }
+    }
+
+    pub fn load(path: &str) -> io::Result<FundingHistory> {
+        let input = fs::read_to_string(path)?;
+
+        FundingHistory::parse(&input)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    pub fn parse(input: &str) -> Result<FundingHistory, String> {
+        let mut history = FundingHistory::new();
+
+        for (i, line) in input.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let columns: Vec<&str> = line.split(',').map(|column| column.trim()).collect();
+
+            if i == 0 && columns[0].eq_ignore_ascii_case(FUNDING_HISTORY_HEADERS[0]) {
+                continue;
+            }
+
+            if columns.len() < 2 {
+                return Err(format!("line {}: expected at least 2 columns", i + 1));
+            }
+
+            let time = columns[0].parse::<u64>()
+                .map_err(|_| format!("line {}: invalid funding time {}", i + 1, columns[0]))?;
+
+            let rate = columns[1].parse::<Decimal>()
+                .map_err(|_| format!("line {}: invalid funding rate {}", i + 1, columns[1]))?;
+
+            let mark_price = match columns.get(2) {
+                Some(column) if !column.is_empty() => Some(
+                    column.parse::<Decimal>()
+                        .map_err(|_| format!("line {}: invalid mark price {}", i + 1, column))?,
+                ),
+                _ => None,
+            };
+
+            history.add(FundingRate { time, rate, mark_price });
+        }
+
+        Ok(history)
+    }
+
+    pub fn headers() -> Vec<String> {
+        FUNDING_HISTORY_HEADERS.iter().map(|s| s.to_string()).collect()
+    }
+
+    pub fn add(&mut self, funding_rate: FundingRate) {
+        self.rates.insert(funding_rate.time, funding_rate);
+    }
+
+    pub fn len(&self) -> usize {
+        self.rates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rates.is_empty()
+    }
+
+    /// Funding rates with a funding time after `from` and up to `to`.
+    pub fn between(&self, from: u64, to: u64) -> impl Iterator<Item = &FundingRate> {
+        self.rates
+            .range((Bound::Excluded(from), Bound::Included(to.max(from))))
+            .map(|(_, funding_rate)| funding_rate)
+    }
+}
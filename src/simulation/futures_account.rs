@@ -0,0 +1,827 @@
+use std::collections::HashMap;
+use crate::account::balance::Balance;
+use crate::account::order::{Order, OrderSide, OrderStatus, OrderType};
+use crate::account::order_request::OrderRequest;
+use crate::account::position::{AccountMode, FundingPayment, MarginMode, Position};
+use crate::account::trade::TradeSide;
+use crate::candles::candle::Candle;
+use crate::client_account::ClientAccount;
+use crate::decimal::{Decimal, DEFAULT_SCALE};
+use crate::simulation::funding_history::FundingHistory;
+use crate::simulation::simulated_account::fill_price;
+use crate::trading_fees::TradingFees;
+
+/// Account that simulates perpetual futures contracts settled in their funds
+/// asset.
+///
+/// Buys and sells open, increase, reduce or flip a position per symbol
+/// instead of exchanging assets. Opening a position takes its initial margin
+/// (notional over leverage) from the free funds. Orders fill at the last
+/// price, while unrealized profit, funding and liquidations use the mark
+/// price, which defaults to the last price until one is provided. Open
+/// orders lock the initial margin they would take until they fill or are
+/// cancelled.
+pub struct SimulatedFuturesAccount {
+    balances: HashMap<String, Balance>,
+    positions: HashMap<String, Position>,
+
+    orders: HashMap<String, Order>,
+    order_counter: u64,
+
+    // funds asset and initial margin locked by each open order
+    reservations: HashMap<String, (String, Decimal)>,
+
+    // ids of the orders placed together as an OCO, by order id
+    oco_groups: HashMap<String, Vec<String>>,
+
+    trading_fees: Box<dyn TradingFees>,
+
+    default_leverage: u32,
+    default_margin_mode: MarginMode,
+
+    leverages: HashMap<String, u32>,
+    margin_modes: HashMap<String, MarginMode>,
+
+    // percentages of the notional value
+    maintenance_margin_rate: Decimal,
+    liquidation_fee: Decimal,
+
+    funding_histories: HashMap<String, FundingHistory>,
+    last_funding_times: HashMap<String, u64>,
+
+    last_prices: HashMap<String, Decimal>,
+    mark_prices: HashMap<String, Decimal>,
+
+    time: u64,
+
+    // reported once through the client account
+    funding_payments: Vec<FundingPayment>,
+    liquidations: Vec<Order>,
+}
+
+impl SimulatedFuturesAccount {
+    pub fn new(
+        trading_fees: impl TradingFees + 'static,
+        default_leverage: u32,
+        default_margin_mode: MarginMode,
+    ) -> SimulatedFuturesAccount {
+        SimulatedFuturesAccount {
+            balances: HashMap::new(),
+            positions: HashMap::new(),
+
+            orders: HashMap::new(),
+            order_counter: 0,
+
+            reservations: HashMap::new(),
+
+            oco_groups: HashMap::new(),
+
+            trading_fees: Box::new(trading_fees),
+
+            default_leverage: default_leverage.max(1),
+            default_margin_mode,
+
+            leverages: HashMap::new(),
+            margin_modes: HashMap::new(),
+
+            maintenance_margin_rate: Decimal::new(5, 1),
+            liquidation_fee: Decimal::ZERO,
+
+            funding_histories: HashMap::new(),
+            last_funding_times: HashMap::new(),
+
+            last_prices: HashMap::new(),
+            mark_prices: HashMap::new(),
+
+            time: 0,
+
+            funding_payments: vec![],
+            liquidations: vec![],
+        }
+    }
+
+    pub fn balance(&self, symbol: &str) -> Option<&Balance> {
+        self.balances.get(symbol)
+    }
+
+    pub fn balance_mut(&mut self, symbol: &str) -> &mut Balance {
+        self.balances
+            .entry(symbol.to_string())
+            .or_insert_with(|| Balance::new(symbol.to_string()))
+    }
+
+    /// Sets the free amount of an asset, e.g. the initial margin funds.
+    pub fn set_amount(&mut self, symbol: &str, free: Decimal) {
+        self.balance_mut(symbol).free(free);
+    }
+
+    pub fn position(&self, symbol: &str) -> Option<&Position> {
+        self.positions.get(symbol)
+    }
+
+    /// Leverage of new positions of the symbol. Open positions keep theirs.
+    pub fn set_leverage(&mut self, symbol: &str, leverage: u32) {
+        self.leverages.insert(symbol.to_string(), leverage.max(1));
+    }
+
+    pub fn set_margin_mode(&mut self, symbol: &str, margin_mode: MarginMode) {
+        self.margin_modes.insert(symbol.to_string(), margin_mode);
+    }
+
+    pub fn maintenance_margin_rate(&self) -> Decimal {
+        self.maintenance_margin_rate
+    }
+
+    pub fn set_maintenance_margin_rate(&mut self, maintenance_margin_rate: Decimal) {
+        self.maintenance_margin_rate = maintenance_margin_rate;
+    }
+
+    pub fn set_liquidation_fee(&mut self, liquidation_fee: Decimal) {
+        self.liquidation_fee = liquidation_fee;
+    }
+
+    pub fn set_funding_history(&mut self, symbol: &str, funding_history: FundingHistory) {
+        self.funding_histories.insert(symbol.to_string(), funding_history);
+    }
+
+    pub fn last_price(&self, symbol: &str) -> Option<Decimal> {
+        self.last_prices.get(symbol).copied()
+    }
+
+    pub fn mark_price(&self, symbol: &str) -> Option<Decimal> {
+        self.mark_prices
+            .get(symbol)
+            .or_else(|| self.last_prices.get(symbol))
+            .copied()
+    }
+
+    pub fn accept_mark_price(&mut self, symbol: &str, mark_price: Decimal) {
+        self.mark_prices.insert(symbol.to_string(), mark_price);
+    }
+
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Moves the simulation to the given candle of `symbol`: fills the open
+    /// orders it reaches, pays the funding due since the previous candle and
+    /// liquidates positions that can no longer cover their maintenance
+    /// margin.
+    pub fn accept_candle(&mut self, symbol: &str, candle: &Candle) {
+        self.time = self.time.max(candle.close_time);
+
+        if let Some(close) = Decimal::from_f64(candle.close, DEFAULT_SCALE) {
+            self.last_prices.insert(symbol.to_string(), close);
+        }
+
+        let mut order_ids: Vec<String> = self.orders
+            .values()
+            .filter(|order| !order.is_finalized() && order.order_request.symbol() == symbol)
+            .map(|order| order.order_id())
+            .collect();
+
+        order_ids.sort_by_key(|order_id| self.orders[order_id].internal_id());
+
+        for order_id in order_ids {
+            let order = &self.orders[&order_id];
+
+            // cancelled by an order of its OCO group that filled first
+            if order.is_finalized() {
+                continue;
+            }
+
+            if let Some(price) = fill_price(order, candle) {
+                self.fill(&order_id, price);
+            }
+        }
+
+        self.pay_funding(symbol, candle);
+        self.check_liquidations();
+    }
+
+    fn leverage(&self, symbol: &str) -> u32 {
+        self.leverages
+            .get(symbol)
+            .copied()
+            .unwrap_or(self.default_leverage)
+    }
+
+    fn margin_mode(&self, symbol: &str) -> MarginMode {
+        self.margin_modes
+            .get(symbol)
+            .copied()
+            .unwrap_or(self.default_margin_mode)
+    }
+
+    fn signed_quantity(order_request: &OrderRequest) -> Decimal {
+        if order_request.is_buy() {
+            order_request.quantity()
+        } else {
+            -order_request.quantity()
+        }
+    }
+
+    // quantity of the request that would add to the position, or open a new
+    // one, rather than reduce it
+    fn opening_quantity(&self, order_request: &OrderRequest) -> Decimal {
+        let quantity = SimulatedFuturesAccount::signed_quantity(order_request);
+
+        let current = self.positions
+            .get(&order_request.symbol())
+            .map(|position| position.quantity())
+            .unwrap_or(Decimal::ZERO);
+
+        if current.is_zero() || current.is_positive() == quantity.is_positive() {
+            quantity.abs()
+        } else {
+            (quantity.abs() - current.abs()).max(Decimal::ZERO)
+        }
+    }
+
+    // moves the margin an order takes from free to locked, false if there
+    // isn't enough of it
+    fn reserve(&mut self, order_id: &str, symbol: &str, margin: Decimal) -> bool {
+        let balance = self.balance_mut(symbol);
+
+        if balance.free < margin {
+            return false;
+        }
+
+        let free = balance.free - margin;
+        let locked = balance.locked + margin;
+
+        balance.free(free);
+        balance.locked(locked);
+
+        self.reservations.insert(order_id.to_string(), (symbol.to_string(), margin));
+
+        true
+    }
+
+    fn release_reservation(&mut self, order_id: &str) {
+        if let Some((symbol, margin)) = self.reservations.remove(order_id) {
+            let balance = self.balance_mut(&symbol);
+
+            let locked = balance.locked - margin;
+            let free = balance.free + margin;
+
+            balance.locked(locked);
+            balance.free(free);
+        }
+    }
+
+    // releases the margin an order that filled or was cancelled locked,
+    // along with the rest of its OCO group, which gets cancelled
+    fn close_group(&mut self, order_id: &str) {
+        let group = self.oco_groups
+            .remove(order_id)
+            .unwrap_or_else(|| vec![order_id.to_string()]);
+
+        for id in group.iter() {
+            self.oco_groups.remove(id);
+            self.release_reservation(id);
+
+            if id == order_id {
+                continue;
+            }
+
+            if let Some(sibling) = self.orders.get_mut(id) {
+                sibling.cancel();
+            }
+        }
+    }
+
+    // initial margin an order would lock, at its price or the last one
+    fn margin(&self, order_request: &OrderRequest) -> Decimal {
+        let symbol = order_request.symbol();
+
+        let price = if order_request.price().is_positive() {
+            order_request.price()
+        } else {
+            self.last_price(&symbol).unwrap_or(Decimal::ZERO)
+        };
+
+        self.opening_quantity(order_request) * price / Decimal::from(self.leverage(&symbol))
+    }
+
+    fn fill(&mut self, order_id: &str, price: Decimal) {
+        let mut order = match self.orders.remove(order_id) {
+            Some(order) => order,
+            None => return,
+        };
+
+        self.close_group(order_id);
+
+        let fee = self.apply_fill(&order.order_request, price);
+
+        order.set_executed_quantity(order.order_request.quantity());
+        order.set_average_price(price);
+        order.set_fees_paid(fee);
+        order.set_status(OrderStatus::Filled);
+
+        // kept until its status is queried
+        self.orders.insert(order_id.to_string(), order);
+    }
+
+    // updates the position and the funds with a fill, returns the fee paid
+    fn apply_fill(&mut self, order_request: &OrderRequest, price: Decimal) -> Decimal {
+        let symbol = order_request.symbol();
+
+        let leverage = self.leverage(&symbol);
+        let margin_mode = self.margin_mode(&symbol);
+
+        let amount = price * order_request.quantity();
+
+        let fee = self.trading_fees.fees_on_amount(
+            amount,
+            *order_request.order_type(),
+            *order_request.side(),
+        );
+
+        let opening_quantity = self.opening_quantity(order_request);
+
+        let position = self.positions
+            .entry(symbol)
+            .or_insert_with(|| Position::new(
+                order_request.assets_symbol().to_string(),
+                order_request.funds_symbol().to_string(),
+                leverage,
+                margin_mode,
+            ));
+
+        if !position.is_open() {
+            position.set_leverage(leverage);
+            position.set_margin_mode(margin_mode);
+        }
+
+        let (profit_loss, released) = position.fill(
+            SimulatedFuturesAccount::signed_quantity(order_request),
+            price,
+        );
+
+        let margin = opening_quantity * price / Decimal::from(position.leverage());
+
+        position.add_margin(margin);
+
+        let funds_balance = self.balance_mut(order_request.funds_symbol());
+        let free = funds_balance.free + released + profit_loss - margin - fee;
+        funds_balance.free(free);
+
+        fee
+    }
+
+    fn pay_funding(&mut self, symbol: &str, candle: &Candle) {
+        let from = *self.last_funding_times
+            .entry(symbol.to_string())
+            .or_insert(candle.open_time);
+
+        self.last_funding_times.insert(symbol.to_string(), self.time);
+
+        let funding_rates: Vec<_> = match self.funding_histories.get(symbol) {
+            Some(funding_history) => funding_history.between(from, self.time).copied().collect(),
+            None => return,
+        };
+
+        let mark_price = match self.mark_price(symbol) {
+            Some(mark_price) => mark_price,
+            None => return,
+        };
+
+        for funding_rate in funding_rates {
+            let position = match self.positions.get_mut(symbol) {
+                Some(position) if position.is_open() => position,
+                _ => return,
+            };
+
+            let mark_price = funding_rate.mark_price.unwrap_or(mark_price);
+
+            // longs pay shorts when the rate is positive
+            let amount = position.quantity() * mark_price * funding_rate.rate;
+
+            position.pay_funding(amount);
+
+            let cross = position.margin_mode() == MarginMode::Cross;
+            let funds_symbol = position.funds_symbol().to_string();
+
+            if cross {
+                let funds_balance = self.balance_mut(&funds_symbol);
+                let free = funds_balance.free - amount;
+                funds_balance.free(free);
+            }
+
+            self.funding_payments.push(FundingPayment::new(
+                symbol.to_string(),
+                funding_rate.time,
+                funding_rate.rate,
+                mark_price,
+                amount,
+            ));
+        }
+    }
+
+    fn check_liquidations(&mut self) {
+        let mut liquidated = vec![];
+
+        // cross positions of each funds asset: equity, maintenance margin
+        let mut cross: HashMap<String, (Decimal, Decimal, Vec<String>)> = HashMap::new();
+
+        for (symbol, position) in self.positions.iter() {
+            if !position.is_open() {
+                continue;
+            }
+
+            let mark_price = match self.mark_price(symbol) {
+                Some(mark_price) => mark_price,
+                None => continue,
+            };
+
+            let maintenance_margin = position.maintenance_margin(mark_price, self.maintenance_margin_rate);
+
+            match position.margin_mode() {
+                MarginMode::Isolated => {
+                    if position.margin_balance(mark_price) <= maintenance_margin {
+                        liquidated.push(symbol.clone());
+                    }
+                }
+                MarginMode::Cross => {
+                    let (equity, maintenance, symbols) = cross
+                        .entry(position.funds_symbol().to_string())
+                        .or_insert((Decimal::ZERO, Decimal::ZERO, vec![]));
+
+                    *equity += position.margin_balance(mark_price);
+                    *maintenance += maintenance_margin;
+
+                    symbols.push(symbol.clone());
+                }
+            }
+        }
+
+        for (funds_symbol, (equity, maintenance, symbols)) in cross {
+            let free = self.balances
+                .get(&funds_symbol)
+                .map(|balance| balance.free)
+                .unwrap_or(Decimal::ZERO);
+
+            if free + equity <= maintenance {
+                liquidated.extend(symbols);
+            }
+        }
+
+        liquidated.sort();
+
+        for symbol in liquidated {
+            self.liquidate_position(&symbol);
+        }
+    }
+
+    // closes the position at the mark price. An isolated position never
+    // loses more than its margin, fees included
+    fn liquidate_position(&mut self, symbol: &str) {
+        let isolated = self.positions
+            .get(symbol)
+            .map(|position| position.margin_mode() == MarginMode::Isolated)
+            .unwrap_or(false);
+
+        let (order_request, mark_price) = match (self.positions.get(symbol), self.mark_price(symbol)) {
+            (Some(position), Some(mark_price)) => {
+                let (side, trade_side) = if position.is_long() {
+                    (OrderSide::Sell, TradeSide::LONG)
+                } else {
+                    (OrderSide::Buy, TradeSide::SHORT)
+                };
+
+                let mut order_request = OrderRequest::new(
+                    position.assets_symbol().to_string(),
+                    position.funds_symbol().to_string(),
+                    side,
+                    trade_side,
+                    self.time,
+                    None,
+                );
+
+                order_request.set_order_type(OrderType::Market);
+                order_request.set_quantity(position.quantity().abs());
+
+                (order_request, mark_price)
+            }
+            _ => return,
+        };
+
+        self.order_counter += 1;
+
+        let mut order = Order::new_from_order_request(
+            self.order_counter,
+            order_request,
+        );
+
+        let free_before = self.balance_mut(order.order_request.funds_symbol()).free;
+
+        let fee = self.apply_fill(&order.order_request, mark_price);
+
+        let mut liquidation_fee = mark_price * order.order_request.quantity() * self.liquidation_fee / Decimal::from(100);
+
+        let funds_balance = self.balance_mut(order.order_request.funds_symbol());
+
+        let mut free = funds_balance.free - liquidation_fee;
+
+        if isolated && free < free_before {
+            let returned = (funds_balance.free - free_before).max(Decimal::ZERO);
+
+            liquidation_fee = liquidation_fee.min(returned);
+            free = free_before + returned - liquidation_fee;
+        }
+
+        funds_balance.free(free);
+
+        order.set_executed_quantity(order.order_request.quantity());
+        order.set_average_price(mark_price);
+        order.set_fees_paid(fee + liquidation_fee);
+        order.set_status(OrderStatus::Filled);
+
+        self.liquidations.push(order);
+    }
+}
+
+impl ClientAccount for SimulatedFuturesAccount {
+    fn execute_order(&mut self, order_details: OrderRequest) -> Order {
+        self.order_counter += 1;
+
+        let mut order = Order::new_from_order_request(
+            self.order_counter,
+            order_details,
+        );
+
+        if order.order_request.is_cancelled() {
+            order.set_status(OrderStatus::Cancelled);
+
+            return order;
+        }
+
+        let margin = self.margin(&order.order_request);
+
+        let funds_symbol = order.order_request.funds_symbol().to_string();
+
+        if !self.reserve(&order.order_id(), &funds_symbol, margin) {
+            // not enough margin, the exchange would reject it
+            order.set_status(OrderStatus::Cancelled);
+
+            return order;
+        }
+
+        self.orders.insert(order.order_id(), order.clone());
+
+        order
+    }
+
+    /// Places the legs as one group that locks the largest margin of its
+    /// legs once: when a leg fills, the others get cancelled.
+    fn execute_oco(&mut self, order_details: Vec<OrderRequest>) -> Vec<Order> {
+        let funds: Vec<String> = order_details
+            .iter()
+            .filter(|order_request| !order_request.is_cancelled())
+            .map(|order_request| order_request.funds_symbol().to_string())
+            .collect();
+
+        if !funds.windows(2).all(|pair| pair[0] == pair[1]) {
+            return order_details
+                .into_iter()
+                .map(|order_request| self.execute_order(order_request))
+                .collect();
+        }
+
+        let margin = order_details
+            .iter()
+            .filter(|order_request| !order_request.is_cancelled())
+            .map(|order_request| self.margin(order_request))
+            .max()
+            .unwrap_or(Decimal::ZERO);
+
+        let mut orders = vec![];
+
+        for order_request in order_details {
+            self.order_counter += 1;
+
+            let mut order = Order::new_from_order_request(
+                self.order_counter,
+                order_request,
+            );
+
+            if order.order_request.is_cancelled() {
+                order.set_status(OrderStatus::Cancelled);
+            }
+
+            orders.push(order);
+        }
+
+        let group: Vec<String> = orders
+            .iter()
+            .filter(|order| !order.is_finalized())
+            .map(|order| order.order_id())
+            .collect();
+
+        if let (Some(first), Some(funds_symbol)) = (group.first(), funds.first()) {
+            if !self.reserve(first, funds_symbol, margin) {
+                // not enough margin, the exchange would reject it
+                for order in orders.iter_mut() {
+                    order.set_status(OrderStatus::Cancelled);
+                }
+
+                return orders;
+            }
+        }
+
+        for order in orders.iter().filter(|order| !order.is_finalized()) {
+            self.oco_groups.insert(order.order_id(), group.clone());
+            self.orders.insert(order.order_id(), order.clone());
+        }
+
+        orders
+    }
+
+    /// Balances of the funds assets, with the margin and unrealized profit
+    /// of their open positions.
+    fn update_balances(&mut self, _force: bool) -> HashMap<String, Balance> {
+        let mut balances = self.balances.clone();
+
+        for (symbol, position) in self.positions.iter() {
+            if !position.is_open() {
+                continue;
+            }
+
+            let mark_price = self.mark_price(symbol).unwrap_or(position.entry_price());
+
+            let balance = balances
+                .entry(position.funds_symbol().to_string())
+                .or_insert_with(|| Balance::new(position.funds_symbol().to_string()));
+
+            balance.position_margin(symbol.clone(), position.margin());
+
+            let unrealized_profit_loss = balance.unrealized_profit_loss
+                + position.unrealized_profit_loss(mark_price);
+
+            balance.unrealized_profit_loss(unrealized_profit_loss);
+        }
+
+        balances
+    }
+
+    fn update_order_status(&mut self, order: &mut Order) {
+        let order_id = order.order_id();
+
+        let simulated = match self.orders.get(&order_id) {
+            Some(simulated) => simulated,
+            None => return,
+        };
+
+        order.set_status(simulated.status());
+        order.set_executed_quantity(simulated.executed_quantity());
+        order.set_average_price(simulated.average_price());
+        order.set_fees_paid(simulated.fees_paid());
+
+        if simulated.is_finalized() {
+            self.orders.remove(&order_id);
+        }
+    }
+
+    fn cancel(&mut self, order: &mut Order) {
+        let order_id = order.order_id();
+
+        if let Some(simulated) = self.orders.get_mut(&order_id) {
+            if !simulated.is_finalized() {
+                simulated.cancel();
+            }
+        }
+
+        if let Some(simulated) = self.orders.get(&order_id) {
+            if simulated.is_cancelled() {
+                self.close_group(&order_id);
+            }
+        }
+
+        self.update_order_status(order);
+    }
+
+    fn is_simulated(&self) -> bool {
+        true
+    }
+
+    fn on_candle(&mut self, symbol: &str, candle: &Candle) {
+        self.accept_candle(symbol, candle);
+    }
+
+    fn account_mode(&self) -> AccountMode {
+        AccountMode::Futures
+    }
+
+    fn update_positions(&mut self) -> HashMap<String, Position> {
+        self.positions
+            .iter()
+            .filter(|(_, position)| position.is_open())
+            .map(|(symbol, position)| (symbol.clone(), position.clone()))
+            .collect()
+    }
+
+    fn funding_payments(&mut self) -> Vec<FundingPayment> {
+        self.funding_payments.drain(..).collect()
+    }
+
+    fn liquidations(&mut self) -> Vec<Order> {
+        self.liquidations.drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading_fees::PercentageFee;
+
+    #[test]
+    fn locks_the_margin_of_open_orders() {
+        let mut account = SimulatedFuturesAccount::new(PercentageFee::new(Decimal::ZERO), 10, MarginMode::Isolated);
+
+        account.set_amount("USDT", Decimal::from(1000));
+
+        let order_request = |price: i64, quantity: i64| {
+            let mut order_request = OrderRequest::new(
+                "BTC".to_string(),
+                "USDT".to_string(),
+                OrderSide::Buy,
+                TradeSide::LONG,
+                0,
+                None,
+            );
+
+            order_request.set_price(Decimal::from(price));
+            order_request.set_quantity(Decimal::from(quantity));
+
+            order_request
+        };
+
+        let mut first = account.execute_order(order_request(100, 60));
+
+        assert_eq!(account.balance("USDT").unwrap().free, Decimal::from(400));
+        assert_eq!(account.balance("USDT").unwrap().locked, Decimal::from(600));
+
+        // the first order holds the margin the second would need
+        assert!(account.execute_order(order_request(100, 50)).is_cancelled());
+
+        account.cancel(&mut first);
+
+        assert_eq!(account.balance("USDT").unwrap().free, Decimal::from(1000));
+        assert!(account.balance("USDT").unwrap().locked.is_zero());
+
+        let second = account.execute_order(order_request(100, 50));
+
+        account.accept_candle("BTCUSDT", &Candle::new(0, 59_999, 100.0, 100.0, 90.0, 95.0, 1.0));
+
+        // the margin taken by the position is no longer locked
+        assert_eq!(account.orders[&second.order_id()].status(), OrderStatus::Filled);
+        assert_eq!(account.balance("USDT").unwrap().free, Decimal::from(500));
+        assert!(account.balance("USDT").unwrap().locked.is_zero());
+        assert_eq!(account.position("BTCUSDT").unwrap().margin(), Decimal::from(500));
+    }
+
+    #[test]
+    fn closes_positions_with_one_leg_of_a_bracket() {
+        let mut account = SimulatedFuturesAccount::new(PercentageFee::new(Decimal::ZERO), 10, MarginMode::Isolated);
+
+        account.set_amount("USDT", Decimal::from(1000));
+
+        let mut entry = OrderRequest::new(
+            "BTC".to_string(),
+            "USDT".to_string(),
+            OrderSide::Buy,
+            TradeSide::LONG,
+            0,
+            None,
+        );
+
+        entry.set_price(Decimal::from(100));
+        entry.set_quantity(Decimal::ONE);
+
+        account.execute_order(entry.clone());
+        account.accept_candle("BTCUSDT", &Candle::new(0, 59_999, 100.0, 100.5, 99.5, 100.0, 1.0));
+
+        assert_eq!(account.position("BTCUSDT").unwrap().quantity(), Decimal::ONE);
+
+        entry.attach_order_request(OrderType::Market, Decimal::from(90));
+        entry.attach_order_request(OrderType::Limit, Decimal::from(110));
+
+        let mut legs = account.execute_oco(entry.attached_order_requests());
+
+        // the legs reduce the position, they take no margin
+        assert!(account.balance("USDT").unwrap().locked.is_zero());
+
+        // a candle wide enough to reach both legs
+        account.accept_candle("BTCUSDT", &Candle::new(60_000, 119_999, 100.0, 115.0, 85.0, 100.0, 1.0));
+
+        for leg in legs.iter_mut() {
+            account.update_order_status(leg);
+        }
+
+        assert_eq!(legs[0].status(), OrderStatus::Filled);
+        assert_eq!(legs[1].status(), OrderStatus::Cancelled);
+        assert!(account.position("BTCUSDT").is_none_or(|position| position.quantity().is_zero()));
+        assert!(account.balance("USDT").unwrap().locked.is_zero());
+    }
+}
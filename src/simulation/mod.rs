@@ -1 +1,10 @@
-mod signal_repository;
\ No newline at end of file
+pub mod backtester;
+pub mod funding_history;
+pub mod futures_account;
+pub mod monte_carlo;
+pub mod optimizer;
+pub mod performance_report;
+pub mod random;
+pub mod signal_repository;
+pub mod simulated_account;
+pub mod walk_forward;
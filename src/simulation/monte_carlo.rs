@@ -0,0 +1,279 @@
+use crate::account::trade::Trade;
+use crate::simulation::random::Random;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SamplingMethod {
+    /// Draws as many trades as there are, with replacement.
+    Resample,
+
+    /// Reorders the trades.
+    Shuffle,
+}
+
+/// What the simulation needs to know about a finalized trade.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TradeOutcome {
+    pub profit_loss: f64,
+
+    // value bought and sold, which fees and slippage are taken from
+    pub traded_value: f64,
+}
+
+impl TradeOutcome {
+    pub fn new(profit_loss: f64, traded_value: f64) -> TradeOutcome {
+        TradeOutcome {
+            profit_loss,
+            traded_value,
+        }
+    }
+
+    pub fn from_trade(trade: &Trade) -> TradeOutcome {
+        let quantity = trade.finalized_quantity();
+
+        TradeOutcome {
+            profit_loss: trade.actual_profit_loss().to_f64(),
+            traded_value: (quantity * (trade.average_price() + trade.exit_price())).to_f64(),
+        }
+    }
+}
+
+/// Distribution values at one percentile.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MonteCarloPercentile {
+    pub percentile: f64,
+
+    pub max_drawdown: f64,
+    pub total_return: f64,
+}
+
+/// Max drawdowns and total returns of every simulated sequence, sorted in
+/// ascending order. Both are ratios of the starting equity, as in the
+/// performance report.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MonteCarloReport {
+    pub max_drawdowns: Vec<f64>,
+    pub total_returns: Vec<f64>,
+
+    // share of the sequences that lost all the equity
+    pub ruin_probability: f64,
+}
+
+// linear interpolation between the closest ranks
+fn percentile_of(sorted: &[f64], percentile: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+
+    let rank = percentile.clamp(0.0, 100.0) / 100.0 * (sorted.len() - 1) as f64;
+
+    let low = rank.floor() as usize;
+    let high = rank.ceil() as usize;
+
+    sorted[low] + (sorted[high] - sorted[low]) * (rank - low as f64)
+}
+
+impl MonteCarloReport {
+    pub fn iterations(&self) -> usize {
+        self.total_returns.len()
+    }
+
+    pub fn max_drawdown_percentile(&self, percentile: f64) -> f64 {
+        percentile_of(&self.max_drawdowns, percentile)
+    }
+
+    pub fn total_return_percentile(&self, percentile: f64) -> f64 {
+        percentile_of(&self.total_returns, percentile)
+    }
+
+    pub fn percentiles(&self, percentiles: &[f64]) -> Vec<MonteCarloPercentile> {
+        percentiles
+            .iter()
+            .map(|percentile| MonteCarloPercentile {
+                percentile: *percentile,
+
+                max_drawdown: self.max_drawdown_percentile(*percentile),
+                total_return: self.total_return_percentile(*percentile),
+            })
+            .collect()
+    }
+
+    /// Bounds of the max drawdown holding `confidence` percent of the
+    /// simulated sequences, e.g. 95.0 for the 2.5th and 97.5th percentiles.
+    pub fn max_drawdown_interval(&self, confidence: f64) -> (f64, f64) {
+        let tail = (100.0 - confidence) / 2.0;
+
+        (
+            self.max_drawdown_percentile(tail),
+            self.max_drawdown_percentile(100.0 - tail),
+        )
+    }
+}
+
+/// Replays the outcomes of backtested trades in random orders to see how
+/// much of a result comes down to the sequence the trades happened in.
+///
+/// Fees and slippage can be perturbed: each trade then pays an extra
+/// percentage of its traded value, drawn uniformly between zero and the
+/// configured maximum.
+#[derive(Clone, Debug)]
+pub struct MonteCarlo {
+    method: SamplingMethod,
+    iterations: usize,
+    seed: u64,
+
+    // in percent of the traded value
+    max_extra_fee: f64,
+    max_slippage: f64,
+}
+
+impl MonteCarlo {
+    pub fn new(
+        method: SamplingMethod,
+        iterations: usize,
+        seed: u64,
+    ) -> MonteCarlo {
+        MonteCarlo {
+            method,
+            iterations,
+            seed,
+
+            max_extra_fee: 0.0,
+            max_slippage: 0.0,
+        }
+    }
+
+    pub fn method(&self) -> SamplingMethod {
+        self.method
+    }
+
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn set_max_extra_fee(&mut self, max_extra_fee: f64) {
+        self.max_extra_fee = max_extra_fee.max(0.0);
+    }
+
+    pub fn set_max_slippage(&mut self, max_slippage: f64) {
+        self.max_slippage = max_slippage.max(0.0);
+    }
+
+    /// Simulates the finalized trades, the others are ignored.
+    pub fn run(&self, trades: &[Trade], starting_equity: f64) -> MonteCarloReport {
+        let outcomes: Vec<TradeOutcome> = trades
+            .iter()
+            .filter(|trade| trade.is_finalized())
+            .map(TradeOutcome::from_trade)
+            .collect();
+
+        self.run_outcomes(&outcomes, starting_equity)
+    }
+
+    pub fn run_outcomes(&self, outcomes: &[TradeOutcome], starting_equity: f64) -> MonteCarloReport {
+        let mut report = MonteCarloReport::default();
+
+        if starting_equity <= 0.0 {
+            return report;
+        }
+
+        let mut random = Random::new(self.seed);
+
+        let mut sequence = outcomes.to_vec();
+        let mut ruined = 0;
+
+        for _ in 0..self.iterations {
+            match self.method {
+                SamplingMethod::Resample if !outcomes.is_empty() => {
+                    for outcome in sequence.iter_mut() {
+                        *outcome = outcomes[random.index(outcomes.len())];
+                    }
+                }
+                SamplingMethod::Resample => {}
+                SamplingMethod::Shuffle => random.shuffle(&mut sequence),
+            }
+
+            let (max_drawdown, final_equity) = self.replay(&sequence, starting_equity, &mut random);
+
+            if final_equity <= 0.0 {
+                ruined += 1;
+            }
+
+            report.max_drawdowns.push(max_drawdown);
+            report.total_returns.push(final_equity / starting_equity - 1.0);
+        }
+
+        report.max_drawdowns.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        report.total_returns.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        if self.iterations > 0 {
+            report.ruin_probability = ruined as f64 / self.iterations as f64;
+        }
+
+        report
+    }
+
+    // max drawdown and final equity of one sequence, stops once ruined
+    fn replay(&self, sequence: &[TradeOutcome], starting_equity: f64, random: &mut Random) -> (f64, f64) {
+        let mut equity = starting_equity;
+        let mut peak = starting_equity;
+        let mut max_drawdown: f64 = 0.0;
+
+        for outcome in sequence {
+            let mut costs = 0.0;
+
+            if self.max_extra_fee > 0.0 {
+                costs += outcome.traded_value * random.range(0.0, self.max_extra_fee) / 100.0;
+            }
+
+            if self.max_slippage > 0.0 {
+                costs += outcome.traded_value * random.range(0.0, self.max_slippage) / 100.0;
+            }
+
+            equity += outcome.profit_loss - costs;
+
+            if equity <= 0.0 {
+                return (1.0, 0.0);
+            }
+
+            peak = peak.max(equity);
+            max_drawdown = max_drawdown.max((peak - equity) / peak);
+        }
+
+        (max_drawdown, equity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shuffles_and_perturbs() {
+        let outcomes: Vec<TradeOutcome> = [50.0, -30.0, 20.0, -40.0, 60.0, -10.0]
+            .iter()
+            .map(|profit_loss| TradeOutcome::new(*profit_loss, 1000.0))
+            .collect();
+
+        let shuffled = MonteCarlo::new(SamplingMethod::Shuffle, 200, 42).run_outcomes(&outcomes, 1000.0);
+
+        assert_eq!(shuffled.iterations(), 200);
+        assert!(shuffled.total_returns.iter().all(|total_return| (total_return - 0.05).abs() < 1e-9));
+        assert!(shuffled.max_drawdown_percentile(0.0) < shuffled.max_drawdown_percentile(100.0));
+
+        let mut perturbed = MonteCarlo::new(SamplingMethod::Resample, 200, 42);
+
+        perturbed.set_max_slippage(0.5);
+
+        let report = perturbed.run_outcomes(&outcomes, 1000.0);
+
+        assert_eq!(report, perturbed.run_outcomes(&outcomes, 1000.0));
+
+        let (low, high) = report.max_drawdown_interval(90.0);
+
+        assert!(low <= high);
+    }
+}
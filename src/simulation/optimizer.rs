@@ -0,0 +1,305 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use crate::candles::candle::Candle;
+use crate::simulation::backtester::Backtester;
+use crate::simulation::performance_report::{Metric, PerformanceReport};
+use crate::simulation::random::Random;
+use crate::strategies::strategy::Strategy;
+
+/// Values of the named parameters a strategy is built with.
+pub type Parameters = BTreeMap<String, f64>;
+
+/// Values `start`, `start + step`, ... up to `end` included.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParameterRange {
+    name: String,
+
+    start: f64,
+    end: f64,
+    step: f64,
+}
+
+impl ParameterRange {
+    pub fn new(
+        name: &str,
+        start: f64,
+        end: f64,
+        step: f64,
+    ) -> ParameterRange {
+        ParameterRange {
+            name: name.to_string(),
+
+            start,
+            end,
+            step,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn values(&self) -> Vec<f64> {
+        if self.step <= 0.0 || self.end < self.start {
+            return vec![self.start];
+        }
+
+        let count = ((self.end - self.start) / self.step + 1e-9).floor() as usize + 1;
+
+        (0..count)
+            .map(|i| self.start + self.step * i as f64)
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct OptimizationResult {
+    pub parameters: Parameters,
+    pub report: PerformanceReport,
+    pub score: f64,
+}
+
+/// Backtests a strategy built from different parameters and ranks the runs
+/// by a metric of their performance report, best first. Runs are spread
+/// over all available cores.
+pub struct Optimizer<F>
+where
+    F: Fn(&Parameters) -> Box<dyn Strategy> + Sync,
+{
+    backtester: Backtester,
+    strategy_factory: F,
+
+    ranges: Vec<ParameterRange>,
+    metric: Metric,
+
+    threads: usize,
+}
+
+impl<F> Optimizer<F>
+where
+    F: Fn(&Parameters) -> Box<dyn Strategy> + Sync,
+{
+    pub fn new(
+        backtester: Backtester,
+        strategy_factory: F,
+        ranges: Vec<ParameterRange>,
+        metric: Metric,
+    ) -> Optimizer<F> {
+        let threads = thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(1);
+
+        Optimizer {
+            backtester,
+            strategy_factory,
+
+            ranges,
+            metric,
+
+            threads,
+        }
+    }
+
+    pub fn backtester(&self) -> &Backtester {
+        &self.backtester
+    }
+
+    pub fn ranges(&self) -> &Vec<ParameterRange> {
+        &self.ranges
+    }
+
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    /// Builds the strategy for a set of parameters.
+    pub fn strategy(&self, parameters: &Parameters) -> Box<dyn Strategy> {
+        (self.strategy_factory)(parameters)
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// Every combination of the parameter values.
+    pub fn grid(&self) -> Vec<Parameters> {
+        let mut grid = vec![Parameters::new()];
+
+        for range in self.ranges.iter() {
+            let values = range.values();
+
+            grid = grid
+                .into_iter()
+                .flat_map(|parameters| {
+                    values.iter().map(move |value| {
+                        let mut parameters = parameters.clone();
+
+                        parameters.insert(range.name().to_string(), *value);
+
+                        parameters
+                    })
+                })
+                .collect();
+        }
+
+        grid
+    }
+
+    /// Up to `samples` distinct combinations picked at random from the grid.
+    pub fn sample(&self, samples: usize, seed: u64) -> Vec<Parameters> {
+        let mut random = Random::new(seed);
+
+        let values: Vec<Vec<f64>> = self.ranges.iter().map(|range| range.values()).collect();
+
+        let combinations = values
+            .iter()
+            .fold(1usize, |combinations, values| combinations.saturating_mul(values.len()));
+
+        let mut seen = HashSet::new();
+        let mut sampled = vec![];
+
+        while sampled.len() < samples.min(combinations) {
+            let indexes: Vec<usize> = values
+                .iter()
+                .map(|values| random.index(values.len()))
+                .collect();
+
+            if !seen.insert(indexes.clone()) {
+                continue;
+            }
+
+            sampled.push(
+                self.ranges
+                    .iter()
+                    .zip(values.iter())
+                    .zip(indexes.iter())
+                    .map(|((range, values), index)| (range.name().to_string(), values[*index]))
+                    .collect(),
+            );
+        }
+
+        sampled
+    }
+
+    pub fn grid_search(&self, candles: &[Candle]) -> Vec<OptimizationResult> {
+        self.run(self.grid(), candles)
+    }
+
+    pub fn random_search(&self, candles: &[Candle], samples: usize, seed: u64) -> Vec<OptimizationResult> {
+        self.run(self.sample(samples, seed), candles)
+    }
+
+    /// Backtests each set of parameters and returns the results ranked by
+    /// the metric.
+    pub fn run(&self, parameter_sets: Vec<Parameters>, candles: &[Candle]) -> Vec<OptimizationResult> {
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(parameter_sets.len()));
+
+        let threads = self.threads.min(parameter_sets.len()).max(1);
+
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+
+                    let parameters = match parameter_sets.get(i) {
+                        Some(parameters) => parameters,
+                        None => break,
+                    };
+
+                    let strategy = (self.strategy_factory)(parameters);
+
+                    let report = self.backtester.run(strategy.as_ref(), candles).report;
+
+                    let result = OptimizationResult {
+                        parameters: parameters.clone(),
+                        score: self.metric.score(&report),
+                        report,
+                    };
+
+                    results.lock().unwrap().push((i, result));
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap();
+
+        // ties keep the order the parameters were given in
+        results.sort_by(|(i, a), (j, b)| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(i.cmp(j))
+        });
+
+        results
+            .into_iter()
+            .map(|(_, result)| result)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decimal::Decimal;
+    use crate::indicators::signal::Signal;
+
+    struct Threshold {
+        buy_below: f64,
+        sell_above: f64,
+    }
+
+    impl Strategy for Threshold {
+        fn get_signal(&self, candle: Candle) -> Signal {
+            if candle.close < self.buy_below {
+                Signal::BUY
+            } else if candle.close > self.sell_above {
+                Signal::SELL
+            } else {
+                Signal::NEUTRAL
+            }
+        }
+    }
+
+    #[test]
+    fn ranks_grid_results() {
+        let candles: Vec<Candle> = [100.0, 90.0, 95.0, 110.0, 120.0, 100.0, 90.0, 130.0]
+            .iter()
+            .enumerate()
+            .map(|(i, close)| Candle::new(i as u64 * 60_000, i as u64 * 60_000 + 59_999, *close, *close, *close, *close, 1.0))
+            .collect();
+
+        let backtester = Backtester::new("BTC".to_string(), "USDT".to_string(), Decimal::from(1000), Decimal::ZERO);
+
+        let optimizer = Optimizer::new(
+            backtester,
+            |parameters: &Parameters| -> Box<dyn Strategy> {
+                Box::new(Threshold {
+                    buy_below: parameters["buy_below"],
+                    sell_above: parameters["sell_above"],
+                })
+            },
+            vec![
+                ParameterRange::new("buy_below", 85.0, 95.0, 10.0),
+                ParameterRange::new("sell_above", 105.0, 125.0, 10.0),
+            ],
+            Metric::TotalReturn,
+        );
+
+        let results = optimizer.grid_search(&candles);
+
+        assert_eq!(results.len(), 6);
+        assert!(results.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        assert_eq!(results[0].parameters["buy_below"], 95.0);
+
+        assert_eq!(optimizer.sample(4, 7), optimizer.sample(4, 7));
+        assert_eq!(optimizer.sample(100, 7).len(), 6);
+    }
+}
@@ -0,0 +1,325 @@
+use crate::account::equity::EquityCurve;
+use crate::account::trade::{Trade, TradeSide};
+
+static YEAR_MS: f64 = 365.0 * 24.0 * 60.0 * 60.0 * 1000.0;
+
+/// Report values results can be ranked by.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Metric {
+    TotalReturn,
+    AnnualizedReturn,
+    MaxDrawdown,
+    SharpeRatio,
+    SortinoRatio,
+    CalmarRatio,
+    WinRate,
+    ProfitFactor,
+    Expectancy,
+}
+
+impl Metric {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Metric> {
+        match s.to_lowercase().as_str() {
+            "total_return" => Some(Metric::TotalReturn),
+            "annualized_return" => Some(Metric::AnnualizedReturn),
+            "max_drawdown" => Some(Metric::MaxDrawdown),
+            "sharpe_ratio" | "sharpe" => Some(Metric::SharpeRatio),
+            "sortino_ratio" | "sortino" => Some(Metric::SortinoRatio),
+            "calmar_ratio" | "calmar" => Some(Metric::CalmarRatio),
+            "win_rate" => Some(Metric::WinRate),
+            "profit_factor" => Some(Metric::ProfitFactor),
+            "expectancy" => Some(Metric::Expectancy),
+            _ => None,
+        }
+    }
+
+    pub fn value(&self, report: &PerformanceReport) -> f64 {
+        match self {
+            Metric::TotalReturn => report.total_return,
+            Metric::AnnualizedReturn => report.annualized_return,
+            Metric::MaxDrawdown => report.max_drawdown,
+            Metric::SharpeRatio => report.sharpe_ratio,
+            Metric::SortinoRatio => report.sortino_ratio,
+            Metric::CalmarRatio => report.calmar_ratio,
+            Metric::WinRate => report.win_rate,
+            Metric::ProfitFactor => report.profit_factor,
+            Metric::Expectancy => report.expectancy,
+        }
+    }
+
+    /// False for metrics where lower values are better, i.e. drawdowns.
+    pub fn higher_is_better(&self) -> bool {
+        *self != Metric::MaxDrawdown
+    }
+
+    /// Value of the report where greater always means better.
+    pub fn score(&self, report: &PerformanceReport) -> f64 {
+        let value = self.value(report);
+
+        if value.is_nan() {
+            return f64::NEG_INFINITY;
+        }
+
+        if self.higher_is_better() {
+            value
+        } else {
+            -value
+        }
+    }
+}
+
+/// Statistics of a backtest, computed from its finalized trades and its
+/// equity curve. Returns and drawdowns are ratios (0.1 is 10%), durations
+/// are in milliseconds and the ratios assume a risk free rate of zero.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PerformanceReport {
+    pub start_time: u64,
+    pub end_time: u64,
+
+    pub starting_equity: f64,
+    pub final_equity: f64,
+
+    pub total_return: f64,
+    pub annualized_return: f64,
+
+    pub max_drawdown: f64,
+    pub max_drawdown_duration: u64,
+
+    pub sharpe_ratio: f64,
+    pub sortino_ratio: f64,
+    pub calmar_ratio: f64,
+
+    pub trade_count: usize,
+    pub long_trade_count: usize,
+    pub short_trade_count: usize,
+
+    pub win_rate: f64,
+    pub profit_factor: f64,
+
+    pub average_win: f64,
+    pub average_loss: f64,
+    pub expectancy: f64,
+
+    // share of the time at least one trade was open
+    pub exposure_time: f64,
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn standard_deviation(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+
+    let mean = mean(values);
+
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>() / (values.len() - 1) as f64;
+
+    variance.sqrt()
+}
+
+// deviation of the returns below zero
+fn downside_deviation(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    let sum = values
+        .iter()
+        .map(|value| value.min(0.0).powi(2))
+        .sum::<f64>();
+
+    (sum / values.len() as f64).sqrt()
+}
+
+fn ratio(numerator: f64, denominator: f64) -> f64 {
+    if denominator == 0.0 {
+        0.0
+    } else {
+        numerator / denominator
+    }
+}
+
+impl PerformanceReport {
+    pub fn new(
+        trades: &[Trade],
+        equity_curve: &EquityCurve,
+    ) -> PerformanceReport {
+        let mut report = PerformanceReport::default();
+
+        report.add_equity_statistics(equity_curve);
+        report.add_trade_statistics(trades);
+
+        report
+    }
+
+    /// Number of finalized trades on the given side.
+    pub fn trade_count_by_side(&self, trade_side: TradeSide) -> usize {
+        match trade_side {
+            TradeSide::LONG => self.long_trade_count,
+            TradeSide::SHORT => self.short_trade_count,
+        }
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.end_time.saturating_sub(self.start_time)
+    }
+
+    fn add_equity_statistics(&mut self, equity_curve: &EquityCurve) {
+        let points = equity_curve.points();
+
+        let (first, last) = match (points.first(), points.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return,
+        };
+
+        self.start_time = first.time;
+        self.end_time = last.time;
+
+        self.starting_equity = first.equity.to_f64();
+        self.final_equity = last.equity.to_f64();
+
+        self.total_return = ratio(self.final_equity, self.starting_equity) - 1.0;
+
+        let years = self.duration() as f64 / YEAR_MS;
+
+        if years > 0.0 && self.total_return > -1.0 {
+            self.annualized_return = (1.0 + self.total_return).powf(1.0 / years) - 1.0;
+        }
+
+        let mut peak = self.starting_equity;
+        let mut peak_time = first.time;
+
+        for point in points.iter() {
+            let equity = point.equity.to_f64();
+
+            if equity >= peak {
+                peak = equity;
+                peak_time = point.time;
+            }
+
+            self.max_drawdown = self.max_drawdown.max(ratio(peak - equity, peak));
+            self.max_drawdown_duration = self.max_drawdown_duration.max(point.time - peak_time);
+        }
+
+        let returns = equity_curve.returns();
+
+        let periods_per_year = ratio(returns.len() as f64, years);
+
+        let annualization = periods_per_year.sqrt();
+
+        self.sharpe_ratio = ratio(mean(&returns), standard_deviation(&returns)) * annualization;
+        self.sortino_ratio = ratio(mean(&returns), downside_deviation(&returns)) * annualization;
+        self.calmar_ratio = ratio(self.annualized_return, self.max_drawdown);
+    }
+
+    fn add_trade_statistics(&mut self, trades: &[Trade]) {
+        let finalized: Vec<&Trade> = trades
+            .iter()
+            .filter(|trade| trade.is_finalized())
+            .collect();
+
+        self.trade_count = finalized.len();
+
+        self.long_trade_count = finalized.iter().filter(|trade| trade.is_long()).count();
+        self.short_trade_count = finalized.iter().filter(|trade| trade.is_short()).count();
+
+        let wins: Vec<f64> = finalized
+            .iter()
+            .map(|trade| trade.actual_profit_loss().to_f64())
+            .filter(|profit_loss| *profit_loss > 0.0)
+            .collect();
+
+        let losses: Vec<f64> = finalized
+            .iter()
+            .map(|trade| trade.actual_profit_loss().to_f64())
+            .filter(|profit_loss| *profit_loss <= 0.0)
+            .collect();
+
+        self.win_rate = ratio(wins.len() as f64, self.trade_count as f64);
+
+        self.average_win = mean(&wins);
+        self.average_loss = mean(&losses);
+
+        let gross_profit: f64 = wins.iter().sum();
+        let gross_loss: f64 = losses.iter().sum::<f64>().abs();
+
+        self.profit_factor = if gross_loss == 0.0 && gross_profit > 0.0 {
+            f64::INFINITY
+        } else {
+            ratio(gross_profit, gross_loss)
+        };
+
+        self.expectancy = self.win_rate * self.average_win + (1.0 - self.win_rate) * self.average_loss;
+
+        self.exposure_time = ratio(
+            exposed_time(&finalized, self.start_time, self.end_time) as f64,
+            self.duration() as f64,
+        );
+    }
+}
+
+// time within [start, end] during which at least one trade was open
+fn exposed_time(trades: &[&Trade], start: u64, end: u64) -> u64 {
+    let mut intervals: Vec<(u64, u64)> = trades
+        .iter()
+        .filter_map(|trade| Some((trade.entry_time()?, trade.exit_time()?)))
+        .map(|(entry, exit)| (entry.max(start), exit.min(end)))
+        .filter(|(entry, exit)| entry < exit)
+        .collect();
+
+    intervals.sort_unstable();
+
+    let mut exposed = 0;
+    let mut current: Option<(u64, u64)> = None;
+
+    for (entry, exit) in intervals {
+        current = match current {
+            Some((from, to)) if entry <= to => Some((from, to.max(exit))),
+            Some((from, to)) => {
+                exposed += to - from;
+
+                Some((entry, exit))
+            }
+            None => Some((entry, exit)),
+        };
+    }
+
+    if let Some((from, to)) = current {
+        exposed += to - from;
+    }
+
+    exposed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decimal::Decimal;
+
+    #[test]
+    fn drawdown_and_returns() {
+        let mut equity_curve = EquityCurve::new("USDT".to_string());
+
+        for (time, equity) in [(0, 100), (1000, 120), (2000, 90), (3000, 110), (4000, 130)].iter() {
+            equity_curve.record(*time, Decimal::from(*equity as i64));
+        }
+
+        let report = PerformanceReport::new(&[], &equity_curve);
+
+        assert!((report.total_return - 0.3).abs() < 1e-9);
+        assert!((report.max_drawdown - 0.25).abs() < 1e-9);
+        assert_eq!(report.max_drawdown_duration, 2000);
+        assert_eq!(report.trade_count, 0);
+    }
+}
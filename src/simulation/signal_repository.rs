@@ -13,13 +13,19 @@ static DEFAULT_REPO_HEADERS: [&str; 8] = [
     "SIGNAL"
 ];
 
-struct SignalRepository {
+pub struct SignalRepository {
     headers: Vec<String>,
     signals: HashMap<String, HashMap<Candle, Signal>>,
 
     repository_dir: String,
 }
 
+impl Default for SignalRepository {
+    fn default() -> Self {
+        SignalRepository::new()
+    }
+}
+
 impl SignalRepository {
     //pub fn new(repository_dir: &str) -> SignalRepository {
     pub fn new() -> SignalRepository {
@@ -36,17 +42,23 @@ impl SignalRepository {
             .entry(
                 symbol.to_string()
             )
-            .or_insert(
-                HashMap::new()
-            )
+            .or_default()
             .insert(candle, signal);
     }
 
+    pub fn headers(&self) -> &Vec<String> {
+        &self.headers
+    }
+
+    pub fn repository_dir(&self) -> &str {
+        &self.repository_dir
+    }
+
     pub fn save(&self) {
         unimplemented!()
     }
 
-    pub fn load(&mut self, symbol: &str, input: &str) {
+    pub fn load(&mut self, _symbol: &str, _input: &str) {
         unimplemented!()
     }
 }
\ No newline at end of file
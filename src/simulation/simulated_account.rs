@@ -0,0 +1,778 @@
+use std::collections::HashMap;
+use crate::account::balance::Balance;
+use crate::account::order::{Order, OrderStatus, OrderType, TriggerCondition};
+use crate::account::order_request::OrderRequest;
+use crate::candles::candle::Candle;
+use crate::client_account::ClientAccount;
+use crate::decimal::{Decimal, DEFAULT_SCALE};
+use crate::trading_fees::TradingFees;
+
+static DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Account that fills orders against candles instead of sending them to an
+/// exchange.
+///
+/// Short sells borrow the asset and keep `margin_reserve_percentage` of the
+/// sale in the funds balance as margin reserve: the proceeds plus an extra
+/// taken from the free funds. Interest on borrowed assets is charged against
+/// that reserve, and whatever is left is released when the short is covered.
+pub struct SimulatedAccount {
+    balances: HashMap<String, Balance>,
+
+    orders: HashMap<String, Order>,
+    order_counter: u64,
+
+    // symbol and amount locked by each open order, by the first order of
+    // an OCO group for the whole group
+    reservations: HashMap<String, (String, Decimal)>,
+
+    // ids of the orders of its OCO group, by order id
+    oco_groups: HashMap<String, Vec<String>>,
+
+    trading_fees: Box<dyn TradingFees>,
+
+    margin_reserve_percentage: u64,
+
+    // daily interest, in percent, charged on each borrowed asset
+    borrow_interest_rates: HashMap<String, Decimal>,
+    interest_paid: HashMap<String, Decimal>,
+
+    latest_prices: HashMap<String, Decimal>,
+
+    time: u64,
+    last_interest_accrual: u64,
+}
+
+impl SimulatedAccount {
+    pub fn new(
+        trading_fees: impl TradingFees + 'static,
+    ) -> SimulatedAccount {
+        SimulatedAccount {
+            balances: HashMap::new(),
+
+            orders: HashMap::new(),
+            order_counter: 0,
+
+            reservations: HashMap::new(),
+
+            oco_groups: HashMap::new(),
+
+            trading_fees: Box::new(trading_fees),
+
+            margin_reserve_percentage: 150,
+
+            borrow_interest_rates: HashMap::new(),
+            interest_paid: HashMap::new(),
+
+            latest_prices: HashMap::new(),
+
+            time: 0,
+            last_interest_accrual: 0,
+        }
+    }
+
+    pub fn balances(&self) -> &HashMap<String, Balance> {
+        &self.balances
+    }
+
+    pub fn balance(&self, symbol: &str) -> Option<&Balance> {
+        self.balances.get(symbol)
+    }
+
+    pub fn balance_mut(&mut self, symbol: &str) -> &mut Balance {
+        self.balances
+            .entry(symbol.to_string())
+            .or_insert_with(|| Balance::new(symbol.to_string()))
+    }
+
+    /// Sets the free amount of an asset, e.g. the initial funds.
+    pub fn set_amount(&mut self, symbol: &str, free: Decimal) {
+        self.balance_mut(symbol).free(free);
+    }
+
+    pub fn set_margin_reserve_percentage(&mut self, margin_reserve_percentage: u64) {
+        self.margin_reserve_percentage = margin_reserve_percentage;
+    }
+
+    pub fn set_borrow_interest_rate(&mut self, asset_symbol: &str, daily_percentage: Decimal) {
+        self.borrow_interest_rates.insert(
+            asset_symbol.to_string(),
+            daily_percentage,
+        );
+    }
+
+    /// Total interest charged so far on each borrowed asset, in the funds
+    /// symbol it was shorted against.
+    pub fn interest_paid(&self) -> &HashMap<String, Decimal> {
+        &self.interest_paid
+    }
+
+    pub fn latest_price(&self, symbol: &str) -> Option<Decimal> {
+        self.latest_prices.get(symbol).copied()
+    }
+
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    pub fn open_orders(&self) -> impl Iterator<Item = &Order> {
+        self.orders
+            .values()
+            .filter(|order| !order.is_finalized())
+    }
+
+    /// Moves the simulation to the given candle of `symbol` (asset and funds
+    /// symbols concatenated): fills the open orders of that symbol the candle
+    /// reaches and charges interest on borrowed assets.
+    pub fn accept_candle(&mut self, symbol: &str, candle: &Candle) {
+        self.time = self.time.max(candle.close_time);
+
+        if let Some(close) = Decimal::from_f64(candle.close, DEFAULT_SCALE) {
+            self.latest_prices.insert(symbol.to_string(), close);
+        }
+
+        let mut order_ids: Vec<String> = self.orders
+            .values()
+            .filter(|order| !order.is_finalized() && order.order_request.symbol() == symbol)
+            .map(|order| order.order_id())
+            .collect();
+
+        // fill in submission order
+        order_ids.sort_by_key(|order_id| self.orders[order_id].internal_id());
+
+        for order_id in order_ids {
+            let order = &self.orders[&order_id];
+
+            // cancelled by an order of its OCO group that filled first
+            if order.is_finalized() {
+                continue;
+            }
+
+            if let Some(price) = fill_price(order, candle) {
+                self.fill(&order_id, price);
+            }
+        }
+
+        self.accrue_interest();
+    }
+
+    /// Fills an open order right away at `price`, for callers deciding the
+    /// fill themselves, e.g. from live market data.
+    pub fn fill_order(&mut self, order_id: &str, price: Decimal) {
+        let open = self.orders
+            .get(order_id)
+            .map(|order| !order.is_finalized())
+            .unwrap_or(false);
+
+        if open {
+            self.fill(order_id, price);
+        }
+    }
+
+    fn estimated_price(&self, order_request: &OrderRequest) -> Decimal {
+        if order_request.price().is_positive() {
+            return order_request.price();
+        }
+
+        self.latest_price(&order_request.symbol())
+            .unwrap_or(Decimal::ZERO)
+    }
+
+    // what an order must lock until it fills: funds for buys, the asset for
+    // sells, and the margin added on top of the proceeds for short sells
+    fn reservation(&self, order_request: &OrderRequest) -> Option<(String, Decimal)> {
+        let amount = self.estimated_price(order_request) * order_request.quantity();
+
+        let fee = self.trading_fees.fees_on_amount(
+            amount,
+            *order_request.order_type(),
+            *order_request.side(),
+        );
+
+        if order_request.is_long_buy() {
+            Some((order_request.funds_symbol().to_string(), amount + fee))
+        } else if order_request.is_long_sell() {
+            Some((order_request.assets_symbol().to_string(), order_request.quantity()))
+        } else if order_request.is_short_sell() {
+            let extra = amount * self.extra_margin_ratio();
+
+            Some((order_request.funds_symbol().to_string(), extra + fee))
+        } else {
+            // covers are paid from the margin reserve of the short
+            None
+        }
+    }
+
+    fn extra_margin_ratio(&self) -> Decimal {
+        let percentage = Decimal::from(self.margin_reserve_percentage.max(100));
+
+        (percentage - Decimal::from(100)) / Decimal::from(100)
+    }
+
+    // moves the reserved amount from free to locked, false if there isn't
+    // enough of it
+    fn reserve(&mut self, order_id: &str, reservation: (String, Decimal)) -> bool {
+        let (symbol, amount) = reservation;
+
+        let balance = self.balance_mut(&symbol);
+
+        if balance.free < amount {
+            return false;
+        }
+
+        let free = balance.free - amount;
+        let locked = balance.locked + amount;
+
+        balance.free(free);
+        balance.locked(locked);
+
+        self.reservations.insert(order_id.to_string(), (symbol, amount));
+
+        true
+    }
+
+    fn release_reservation(&mut self, order_id: &str) {
+        if let Some((symbol, amount)) = self.reservations.remove(order_id) {
+            let balance = self.balance_mut(&symbol);
+
+            let locked = balance.locked - amount;
+            let free = balance.free + amount;
+
+            balance.locked(locked);
+            balance.free(free);
+        }
+    }
+
+    // releases what an order that filled or was cancelled locked, along with
+    // the rest of its OCO group, which gets cancelled
+    fn close_group(&mut self, order_id: &str) {
+        let group = self.oco_groups
+            .remove(order_id)
+            .unwrap_or_else(|| vec![order_id.to_string()]);
+
+        for id in group.iter() {
+            self.oco_groups.remove(id);
+            self.release_reservation(id);
+
+            if id == order_id {
+                continue;
+            }
+
+            if let Some(sibling) = self.orders.get_mut(id) {
+                sibling.cancel();
+            }
+        }
+    }
+
+    fn fill(&mut self, order_id: &str, price: Decimal) {
+        let mut order = match self.orders.remove(order_id) {
+            Some(order) => order,
+            None => return,
+        };
+
+        self.close_group(order_id);
+
+        let order_request = order.order_request.clone();
+
+        let asset = order_request.assets_symbol().to_string();
+        let funds = order_request.funds_symbol().to_string();
+
+        let quantity = order_request.quantity();
+        let amount = price * quantity;
+
+        let fee = self.trading_fees.fees_on_amount(
+            amount,
+            *order_request.order_type(),
+            *order_request.side(),
+        );
+
+        if order_request.is_long_buy() {
+            let funds_balance = self.balance_mut(&funds);
+            let free = funds_balance.free - amount - fee;
+            funds_balance.free(free);
+
+            let asset_balance = self.balance_mut(&asset);
+            let free = asset_balance.free + quantity;
+            asset_balance.free(free);
+        } else if order_request.is_long_sell() {
+            let asset_balance = self.balance_mut(&asset);
+            let free = asset_balance.free - quantity;
+            asset_balance.free(free);
+
+            let funds_balance = self.balance_mut(&funds);
+            let free = funds_balance.free + amount - fee;
+            funds_balance.free(free);
+        } else if order_request.is_short_sell() {
+            let reserve = amount * Decimal::from(self.margin_reserve_percentage) / Decimal::from(100);
+
+            let funds_balance = self.balance_mut(&funds);
+
+            let free = funds_balance.free + amount - reserve - fee;
+            let total_reserve = funds_balance.margin_reserves
+                .get(&asset)
+                .copied()
+                .unwrap_or(Decimal::ZERO) + reserve;
+
+            funds_balance.free(free);
+            funds_balance.margin_reserve(asset.clone(), total_reserve);
+            funds_balance.shorted_asset_symbols.insert(asset.clone());
+
+            let asset_balance = self.balance_mut(&asset);
+            let shorted = asset_balance.shorted + quantity;
+            asset_balance.shorted(shorted);
+        } else {
+            let shorted = self.balance_mut(&asset).shorted;
+            let covered = quantity.min(shorted);
+
+            let funds_balance = self.balance_mut(&funds);
+
+            let reserve = funds_balance.margin_reserves
+                .get(&asset)
+                .copied()
+                .unwrap_or(Decimal::ZERO);
+
+            let released = if shorted.is_positive() {
+                reserve * covered / shorted
+            } else {
+                Decimal::ZERO
+            };
+
+            let free = funds_balance.free + released - amount - fee;
+
+            funds_balance.free(free);
+            funds_balance.margin_reserve(asset.clone(), reserve - released);
+
+            if covered == shorted {
+                funds_balance.shorted_asset_symbols.remove(&asset);
+            }
+
+            // anything bought beyond the short is kept
+            let asset_balance = self.balance_mut(&asset);
+            let free = asset_balance.free + quantity - covered;
+
+            asset_balance.shorted(shorted - covered);
+            asset_balance.free(free);
+        }
+
+        order.set_executed_quantity(quantity);
+        order.set_average_price(price);
+        order.set_fees_paid(fee);
+        order.set_status(OrderStatus::Filled);
+
+        // kept until its status is queried
+        self.orders.insert(order_id.to_string(), order);
+    }
+
+    fn accrue_interest(&mut self) {
+        if self.last_interest_accrual == 0 || self.time <= self.last_interest_accrual {
+            self.last_interest_accrual = self.last_interest_accrual.max(self.time);
+
+            return;
+        }
+
+        let elapsed = Decimal::from(self.time - self.last_interest_accrual) / Decimal::from(DAY_MS);
+
+        self.last_interest_accrual = self.time;
+
+        let mut charges = vec![];
+
+        for (funds, funds_balance) in self.balances.iter() {
+            for asset in funds_balance.shorted_asset_symbols.iter() {
+                let rate = match self.borrow_interest_rates.get(asset) {
+                    Some(rate) => *rate,
+                    None => continue,
+                };
+
+                let price = match self.latest_prices.get(&format!("{}{}", asset, funds)) {
+                    Some(price) => *price,
+                    None => continue,
+                };
+
+                let shorted = self.balances
+                    .get(asset)
+                    .map(|balance| balance.shorted)
+                    .unwrap_or(Decimal::ZERO);
+
+                let interest = shorted * price * rate / Decimal::from(100) * elapsed;
+
+                charges.push((funds.clone(), asset.clone(), interest));
+            }
+        }
+
+        for (funds, asset, interest) in charges {
+            let funds_balance = self.balance_mut(&funds);
+
+            let reserve = funds_balance.margin_reserves
+                .get(&asset)
+                .copied()
+                .unwrap_or(Decimal::ZERO);
+
+            funds_balance.margin_reserve(asset.clone(), reserve - interest);
+
+            *self.interest_paid
+                .entry(asset)
+                .or_insert(Decimal::ZERO) += interest;
+        }
+    }
+}
+
+// price the order fills at within the candle, if it does
+pub(crate) fn fill_price(order: &Order, candle: &Candle) -> Option<Decimal> {
+    let order_request = &order.order_request;
+
+    let open = Decimal::from_f64(candle.open, DEFAULT_SCALE)?;
+    let high = Decimal::from_f64(candle.high, DEFAULT_SCALE)?;
+    let low = Decimal::from_f64(candle.low, DEFAULT_SCALE)?;
+
+    let price = order_request.price();
+    let trigger_price = order_request.trigger_price();
+
+    // (price, fills when the price drops to it), stop losses trigger below
+    // the price and stop gains above it, whatever the side
+    let (price, on_drop) = match order_request.trigger_condition() {
+        TriggerCondition::None => {
+            if *order_request.order_type() == OrderType::Market {
+                return Some(open);
+            }
+
+            (price, order_request.is_buy())
+        }
+        TriggerCondition::StopLoss => (trigger_price, true),
+        TriggerCondition::StopGain => (trigger_price, false),
+    };
+
+    if on_drop {
+        if low <= price {
+            return Some(price.min(open));
+        }
+    } else if high >= price {
+        return Some(price.max(open));
+    }
+
+    None
+}
+
+impl ClientAccount for SimulatedAccount {
+    fn execute_order(&mut self, order_details: OrderRequest) -> Order {
+        self.order_counter += 1;
+
+        let mut order = Order::new_from_order_request(
+            self.order_counter,
+            order_details,
+        );
+
+        if order.order_request.is_cancelled() {
+            order.set_status(OrderStatus::Cancelled);
+
+            return order;
+        }
+
+        if let Some(reservation) = self.reservation(&order.order_request) {
+            if !self.reserve(&order.order_id(), reservation) {
+                // not enough funds, the exchange would reject it
+                order.set_status(OrderStatus::Cancelled);
+
+                return order;
+            }
+        }
+
+        self.orders.insert(order.order_id(), order.clone());
+
+        order
+    }
+
+    /// Locks what the largest of the orders needs only once, as they close
+    /// the same position. Orders locking different assets are submitted on
+    /// their own.
+    fn execute_oco(&mut self, order_details: Vec<OrderRequest>) -> Vec<Order> {
+        let reservations: Vec<(String, Decimal)> = order_details
+            .iter()
+            .filter(|order_request| !order_request.is_cancelled())
+            .filter_map(|order_request| self.reservation(order_request))
+            .collect();
+
+        if !reservations.windows(2).all(|pair| pair[0].0 == pair[1].0) {
+            return order_details
+                .into_iter()
+                .map(|order_request| self.execute_order(order_request))
+                .collect();
+        }
+
+        let mut orders = vec![];
+
+        for order_request in order_details {
+            self.order_counter += 1;
+
+            let mut order = Order::new_from_order_request(
+                self.order_counter,
+                order_request,
+            );
+
+            if order.order_request.is_cancelled() {
+                order.set_status(OrderStatus::Cancelled);
+            }
+
+            orders.push(order);
+        }
+
+        let group: Vec<String> = orders
+            .iter()
+            .filter(|order| !order.is_finalized())
+            .map(|order| order.order_id())
+            .collect();
+
+        let reservation = reservations
+            .into_iter()
+            .max_by_key(|(_, amount)| *amount);
+
+        if let (Some(reservation), Some(first)) = (reservation, group.first()) {
+            if !self.reserve(first, reservation) {
+                for order in orders.iter_mut() {
+                    order.set_status(OrderStatus::Cancelled);
+                }
+
+                return orders;
+            }
+        }
+
+        for order in orders.iter().filter(|order| !order.is_finalized()) {
+            self.oco_groups.insert(order.order_id(), group.clone());
+            self.orders.insert(order.order_id(), order.clone());
+        }
+
+        orders
+    }
+
+    fn update_balances(&mut self, _force: bool) -> HashMap<String, Balance> {
+        self.balances.clone()
+    }
+
+    fn update_order_status(&mut self, order: &mut Order) {
+        let order_id = order.order_id();
+
+        let simulated = match self.orders.get(&order_id) {
+            Some(simulated) => simulated,
+            None => return,
+        };
+
+        order.set_status(simulated.status());
+        order.set_executed_quantity(simulated.executed_quantity());
+        order.set_average_price(simulated.average_price());
+        order.set_fees_paid(simulated.fees_paid());
+
+        if simulated.is_finalized() {
+            self.orders.remove(&order_id);
+        }
+    }
+
+    fn cancel(&mut self, order: &mut Order) {
+        let order_id = order.order_id();
+
+        if let Some(simulated) = self.orders.get_mut(&order_id) {
+            if !simulated.is_finalized() {
+                simulated.cancel();
+            }
+        }
+
+        if let Some(simulated) = self.orders.get(&order_id) {
+            if simulated.is_cancelled() {
+                self.close_group(&order_id);
+            }
+        }
+
+        self.update_order_status(order);
+    }
+
+    /// Fills the order right away at the latest price and takes the
+    /// liquidation fee from the free funds.
+    fn liquidate(&mut self, order_details: OrderRequest, liquidation_fee: Decimal) -> Order {
+        let price = self.latest_price(&order_details.symbol())
+            .unwrap_or(order_details.price());
+
+        self.order_counter += 1;
+
+        let order = Order::new_from_order_request(
+            self.order_counter,
+            order_details,
+        );
+
+        let order_id = order.order_id();
+
+        self.orders.insert(order_id.clone(), order);
+        self.fill(&order_id, price);
+
+        let mut order = self.orders.remove(&order_id).unwrap();
+
+        let fee = order.average_price() * order.executed_quantity() * liquidation_fee / Decimal::from(100);
+
+        let funds_balance = self.balance_mut(order.order_request.funds_symbol());
+        let free = funds_balance.free - fee;
+        funds_balance.free(free);
+
+        order.set_fees_paid(order.fees_paid() + fee);
+
+        order
+    }
+
+    fn is_simulated(&self) -> bool {
+        true
+    }
+
+    fn on_candle(&mut self, symbol: &str, candle: &Candle) {
+        self.accept_candle(symbol, candle);
+    }
+
+    fn margin_reserve_percentage(&self) -> u64 {
+        self.margin_reserve_percentage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::order::OrderSide;
+    use crate::account::trade::TradeSide;
+    use crate::trading_fees::PercentageFee;
+
+    fn order_request(side: OrderSide, trade_side: TradeSide, price: i64, quantity: i64) -> OrderRequest {
+        let mut order_request = OrderRequest::new(
+            "BTC".to_string(),
+            "USDT".to_string(),
+            side,
+            trade_side,
+            0,
+            None,
+        );
+
+        order_request.set_price(Decimal::from(price));
+        order_request.set_quantity(Decimal::from(quantity));
+
+        order_request
+    }
+
+    fn candle(time: u64, open: f64, high: f64, low: f64, close: f64) -> Candle {
+        Candle::new(time, time + 59_999, open, high, low, close, 1.0)
+    }
+
+    #[test]
+    fn borrows_charges_interest_and_repays_shorts() {
+        let mut account = SimulatedAccount::new(PercentageFee::new(Decimal::ZERO));
+
+        account.set_amount("USDT", Decimal::from(1000));
+        account.set_borrow_interest_rate("BTC", Decimal::ONE);
+
+        let mut short = account.execute_order(order_request(OrderSide::Sell, TradeSide::SHORT, 100, 1));
+
+        // the margin added on top of the proceeds is locked until it fills
+        assert_eq!(account.balance("USDT").unwrap().locked, Decimal::from(50));
+
+        account.accept_candle("BTCUSDT", &candle(0, 100.0, 101.0, 99.0, 100.0));
+        account.update_order_status(&mut short);
+
+        assert_eq!(short.status(), OrderStatus::Filled);
+
+        let usdt = account.balance("USDT").unwrap();
+
+        assert_eq!(usdt.free, Decimal::from(950));
+        assert_eq!(usdt.locked, Decimal::ZERO);
+        assert_eq!(usdt.margin_reserves["BTC"], Decimal::from(150));
+        assert_eq!(account.balance("BTC").unwrap().shorted, Decimal::ONE);
+
+        // a day later, 1% of the borrowed value is taken from the reserve
+        account.accept_candle("BTCUSDT", &candle(DAY_MS, 100.0, 100.0, 100.0, 100.0));
+
+        assert_eq!(account.interest_paid()["BTC"], Decimal::ONE);
+        assert_eq!(account.balance("USDT").unwrap().margin_reserves["BTC"], Decimal::from(149));
+
+        let mut cover = account.execute_order(order_request(OrderSide::Buy, TradeSide::SHORT, 90, 1));
+
+        account.accept_candle("BTCUSDT", &candle(DAY_MS + 60_000, 95.0, 96.0, 85.0, 88.0));
+        account.update_order_status(&mut cover);
+
+        assert_eq!(cover.average_price(), Decimal::from(90));
+
+        // what is left of the reserve is released and pays for the cover
+        let usdt = account.balance("USDT").unwrap();
+
+        assert_eq!(usdt.free, Decimal::from(1009));
+        assert!(usdt.margin_reserves.is_empty());
+        assert!(usdt.shorted_asset_symbols.is_empty());
+        assert_eq!(account.balance("BTC").unwrap().shorted, Decimal::ZERO);
+    }
+
+    #[test]
+    fn oco_orders_share_their_reservation() {
+        let mut account = SimulatedAccount::new(PercentageFee::new(Decimal::ZERO));
+
+        account.set_amount("BTC", Decimal::from(2));
+
+        let mut stop_loss = order_request(OrderSide::Sell, TradeSide::LONG, 95, 2);
+        stop_loss.set_trigger_condition(TriggerCondition::StopLoss, Decimal::from(95));
+
+        let take_profit = order_request(OrderSide::Sell, TradeSide::LONG, 110, 2);
+
+        let mut orders = account.execute_oco(vec![stop_loss, take_profit]);
+
+        assert!(orders.iter().all(|order| order.status() == OrderStatus::New));
+        assert_eq!(account.balance("BTC").unwrap().locked, Decimal::from(2));
+
+        account.accept_candle("BTCUSDT", &candle(0, 100.0, 112.0, 99.0, 111.0));
+
+        for order in orders.iter_mut() {
+            account.update_order_status(order);
+        }
+
+        assert_eq!(orders[0].status(), OrderStatus::Cancelled);
+        assert_eq!(orders[1].status(), OrderStatus::Filled);
+
+        let btc = account.balance("BTC").unwrap();
+
+        assert_eq!(btc.free, Decimal::ZERO);
+        assert_eq!(btc.locked, Decimal::ZERO);
+        assert_eq!(account.balance("USDT").unwrap().free, Decimal::from(220));
+    }
+
+    #[test]
+    fn triggers_short_brackets_on_their_side() {
+        let mut account = SimulatedAccount::new(PercentageFee::new(Decimal::ZERO));
+
+        account.set_amount("USDT", Decimal::from(1000));
+
+        let mut entry = order_request(OrderSide::Sell, TradeSide::SHORT, 100, 1);
+
+        let mut short = account.execute_order(entry.clone());
+
+        account.accept_candle("BTCUSDT", &candle(0, 100.0, 100.5, 99.5, 100.0));
+        account.update_order_status(&mut short);
+
+        assert_eq!(short.status(), OrderStatus::Filled);
+
+        // the stop loss of a short is above the entry, its take profit below
+        entry.attach_to_price_change(OrderType::Market, Decimal::from(10));
+        entry.attach_to_price_change(OrderType::Limit, Decimal::from(-10));
+
+        let mut legs = account.execute_oco(entry.attached_order_requests());
+
+        assert_eq!(*legs[0].order_request.trigger_condition(), TriggerCondition::StopGain);
+        assert_eq!(*legs[1].order_request.trigger_condition(), TriggerCondition::StopLoss);
+
+        account.accept_candle("BTCUSDT", &candle(60_000, 100.0, 100.5, 99.5, 100.0));
+
+        for leg in legs.iter_mut() {
+            account.update_order_status(leg);
+        }
+
+        assert!(legs.iter().all(|leg| leg.status() == OrderStatus::New));
+
+        account.accept_candle("BTCUSDT", &candle(120_000, 104.0, 111.0, 103.0, 109.0));
+
+        for leg in legs.iter_mut() {
+            account.update_order_status(leg);
+        }
+
+        assert_eq!(legs[0].status(), OrderStatus::Filled);
+        assert_eq!(legs[0].average_price(), Decimal::from(110));
+        assert_eq!(legs[1].status(), OrderStatus::Cancelled);
+        assert_eq!(account.balance("BTC").unwrap().shorted, Decimal::ZERO);
+    }
+}
@@ -0,0 +1,279 @@
+use std::ops::Range;
+use crate::account::equity::EquityCurve;
+use crate::account::trade::Trade;
+use crate::candles::candle::Candle;
+use crate::decimal::Decimal;
+use crate::simulation::optimizer::{Optimizer, Parameters};
+use crate::simulation::performance_report::PerformanceReport;
+use crate::strategies::strategy::Strategy;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WalkForwardMode {
+    /// In-sample windows all start at the first candle and grow.
+    Anchored,
+
+    /// In-sample windows keep their size and move forward.
+    Rolling,
+}
+
+/// One optimization on an in-sample window and its validation on the
+/// out-of-sample window right after it.
+#[derive(Clone, Debug)]
+pub struct WalkForwardStep {
+    pub in_sample: Range<usize>,
+    pub out_of_sample: Range<usize>,
+
+    pub parameters: Parameters,
+
+    pub in_sample_report: PerformanceReport,
+    pub out_of_sample_report: PerformanceReport,
+}
+
+pub struct WalkForwardResult {
+    pub steps: Vec<WalkForwardStep>,
+
+    // out-of-sample trades of every step, in order, numbered from 1 and
+    // scaled like the equity curve
+    pub trades: Vec<Trade>,
+
+    // out-of-sample equity curves, each one scaled to continue from where
+    // the previous one ended
+    pub equity_curve: EquityCurve,
+
+    pub report: PerformanceReport,
+}
+
+/// Splits candles into in-sample and out-of-sample windows, sizes in
+/// number of candles. The best parameters found by the optimizer on each
+/// in-sample window are backtested on the following out-of-sample window.
+///
+/// Each out-of-sample backtest starts from a fresh strategy and account, so
+/// strategies that need a warm up lose the first candles of each window.
+#[derive(Clone, Debug)]
+pub struct WalkForward {
+    mode: WalkForwardMode,
+
+    in_sample_size: usize,
+    out_of_sample_size: usize,
+
+    // samples and seed, grid search when not set
+    random_search: Option<(usize, u64)>,
+}
+
+impl WalkForward {
+    pub fn new(
+        mode: WalkForwardMode,
+        in_sample_size: usize,
+        out_of_sample_size: usize,
+    ) -> WalkForward {
+        WalkForward {
+            mode,
+
+            in_sample_size: in_sample_size.max(1),
+            out_of_sample_size: out_of_sample_size.max(1),
+
+            random_search: None,
+        }
+    }
+
+    pub fn mode(&self) -> WalkForwardMode {
+        self.mode
+    }
+
+    pub fn in_sample_size(&self) -> usize {
+        self.in_sample_size
+    }
+
+    pub fn out_of_sample_size(&self) -> usize {
+        self.out_of_sample_size
+    }
+
+    /// Optimizes each window with a random search of `samples` parameter
+    /// sets instead of the whole grid.
+    pub fn set_random_search(&mut self, samples: usize, seed: u64) {
+        self.random_search = Some((samples, seed));
+    }
+
+    /// In-sample and out-of-sample index ranges for `len` candles. The last
+    /// out-of-sample window may be shorter than the others.
+    pub fn windows(&self, len: usize) -> Vec<(Range<usize>, Range<usize>)> {
+        let mut windows = vec![];
+
+        let mut out_of_sample_start = self.in_sample_size;
+
+        while out_of_sample_start < len {
+            let in_sample_start = match self.mode {
+                WalkForwardMode::Anchored => 0,
+                WalkForwardMode::Rolling => out_of_sample_start - self.in_sample_size,
+            };
+
+            let out_of_sample_end = (out_of_sample_start + self.out_of_sample_size).min(len);
+
+            windows.push((
+                in_sample_start..out_of_sample_start,
+                out_of_sample_start..out_of_sample_end,
+            ));
+
+            out_of_sample_start = out_of_sample_end;
+        }
+
+        windows
+    }
+
+    pub fn run<F>(
+        &self,
+        optimizer: &Optimizer<F>,
+        candles: &[Candle],
+    ) -> WalkForwardResult
+    where
+        F: Fn(&Parameters) -> Box<dyn Strategy> + Sync,
+    {
+        let backtester = optimizer.backtester();
+
+        let mut steps = vec![];
+        let mut trades = vec![];
+
+        let mut equity_curve = EquityCurve::new(backtester.funds_symbol().to_string());
+
+        for (i, (in_sample, out_of_sample)) in self.windows(candles.len()).into_iter().enumerate() {
+            let in_sample_candles = &candles[in_sample.clone()];
+
+            let results = match self.random_search {
+                Some((samples, seed)) => optimizer.random_search(in_sample_candles, samples, seed.wrapping_add(i as u64)),
+                None => optimizer.grid_search(in_sample_candles),
+            };
+
+            let best = match results.into_iter().next() {
+                Some(best) => best,
+                None => continue,
+            };
+
+            let strategy = optimizer.strategy(&best.parameters);
+
+            let result = backtester.run(strategy.as_ref(), &candles[out_of_sample.clone()]);
+
+            let scale = stitch(&mut equity_curve, &result.equity_curve);
+
+            for mut trade in result.trades {
+                trade.scale(scale);
+                trade.set_id(trades.len() as u64 + 1);
+
+                trades.push(trade);
+            }
+
+            steps.push(WalkForwardStep {
+                in_sample,
+                out_of_sample,
+
+                parameters: best.parameters,
+
+                in_sample_report: best.report,
+                out_of_sample_report: result.report,
+            });
+        }
+
+        let report = PerformanceReport::new(&trades, &equity_curve);
+
+        WalkForwardResult {
+            steps,
+            trades,
+            equity_curve,
+            report,
+        }
+    }
+}
+
+// appends a segment to the curve, scaled so that it starts at the equity the
+// curve ended with, and returns the scale
+fn stitch(equity_curve: &mut EquityCurve, segment: &EquityCurve) -> Decimal {
+    let first = match segment.points().first() {
+        Some(first) => first.equity,
+        None => return Decimal::ONE,
+    };
+
+    let scale = match equity_curve.last() {
+        Some(last) if first.is_positive() => last.equity / first,
+        _ => Decimal::ONE,
+    };
+
+    for point in segment.points() {
+        equity_curve.record(point.time, point.equity * scale);
+    }
+
+    scale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::signal::Signal;
+    use crate::simulation::backtester::Backtester;
+    use crate::simulation::optimizer::ParameterRange;
+    use crate::simulation::performance_report::Metric;
+
+    struct Threshold;
+
+    impl Strategy for Threshold {
+        fn get_signal(&self, candle: Candle) -> Signal {
+            if candle.close < 95.0 {
+                Signal::BUY
+            } else if candle.close > 105.0 {
+                Signal::SELL
+            } else {
+                Signal::NEUTRAL
+            }
+        }
+    }
+
+    #[test]
+    fn splits_windows() {
+        let rolling = WalkForward::new(WalkForwardMode::Rolling, 4, 2);
+
+        assert_eq!(rolling.windows(9), vec![(0..4, 4..6), (2..6, 6..8), (4..8, 8..9)]);
+
+        let anchored = WalkForward::new(WalkForwardMode::Anchored, 4, 3);
+
+        assert_eq!(anchored.windows(10), vec![(0..4, 4..7), (0..7, 7..10)]);
+    }
+
+    #[test]
+    fn stitches_trades_of_each_window() {
+        // each out-of-sample window buys at 90 and sells at 110
+        let candles: Vec<Candle> = [100.0, 100.0, 100.0, 100.0, 100.0, 90.0, 90.0, 110.0, 110.0, 100.0, 90.0, 90.0, 110.0, 110.0]
+            .iter()
+            .enumerate()
+            .map(|(i, close)| Candle::new(i as u64 * 60_000, i as u64 * 60_000 + 59_999, *close, *close, *close, *close, 1.0))
+            .collect();
+
+        let optimizer = Optimizer::new(
+            Backtester::new("BTC".to_string(), "USDT".to_string(), Decimal::from(1000), Decimal::ZERO),
+            |_: &Parameters| -> Box<dyn Strategy> { Box::new(Threshold) },
+            vec![ParameterRange::new("unused", 1.0, 1.0, 1.0)],
+            Metric::TotalReturn,
+        );
+
+        let result = WalkForward::new(WalkForwardMode::Rolling, 4, 5).run(&optimizer, &candles);
+
+        assert_eq!(result.steps.len(), 2);
+
+        let ids: Vec<u64> = result.trades.iter().map(|trade| trade.id()).collect();
+
+        assert_eq!(ids, [1, 2]);
+        assert!(result.trades.iter().all(|trade| trade.is_finalized()));
+
+        assert_eq!(result.equity_curve.points().len(), 10);
+
+        // the second window starts from the equity the first one ended with
+        let first_end = result.equity_curve.points()[4].equity;
+        let scale = first_end / Decimal::from(1000);
+
+        assert!(scale > Decimal::ONE);
+        assert_eq!(result.trades[1].actual_profit_loss(), result.trades[0].actual_profit_loss() * scale);
+        assert_eq!(result.trades[1].total_units(), result.trades[0].total_units() * scale);
+
+        let profit_loss: Decimal = result.trades.iter().map(|trade| trade.actual_profit_loss()).sum();
+        let last = result.equity_curve.last().unwrap().equity;
+
+        assert!((last - Decimal::from(1000) - profit_loss).abs() < Decimal::new(1, 4));
+    }
+}
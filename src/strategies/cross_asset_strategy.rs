@@ -0,0 +1,33 @@
+use std::collections::BTreeMap;
+use crate::account::trade::TradeSide;
+use crate::candles::candle_feed::CandleSnapshot;
+use crate::indicators::signal::Signal;
+
+/// Strategy looking at several symbols at once, e.g. pair trading, spread
+/// trading or relative strength. It gets the latest candles of all its
+/// symbols each time one of them closes a candle and may signal any of
+/// them.
+pub trait CrossAssetStrategy {
+    fn symbols(&self) -> Vec<String>;
+
+    /// Signals by symbol, symbols left out are neutral.
+    fn get_signals(&self, snapshot: &CandleSnapshot) -> BTreeMap<String, Signal>;
+
+    fn trade_side(&self) -> Option<TradeSide> {
+        None
+    }
+
+    /// Whether signals are only asked for once every symbol has a candle.
+    fn requires_all_symbols(&self) -> bool {
+        true
+    }
+
+    /// Signals for the snapshot, or none when the strategy is not ready.
+    fn signals(&self, snapshot: &CandleSnapshot) -> BTreeMap<String, Signal> {
+        if self.requires_all_symbols() && !snapshot.has_all(&self.symbols()) {
+            return BTreeMap::new();
+        }
+
+        self.get_signals(snapshot)
+    }
+}
@@ -0,0 +1,829 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::fs;
+use std::io;
+use crate::account::context::Context;
+use crate::account::order::OrderType;
+use crate::account::order_request::OrderRequest;
+use crate::account::trade::{Trade, TradeSide};
+use crate::candles::candle::Candle;
+use crate::indicators::average_true_range::AverageTrueRange;
+use crate::indicators::moving_average::{ExponentialMovingAverage, SimpleMovingAverage};
+use crate::indicators::relative_strength_index::RelativeStrengthIndex;
+use crate::indicators::signal::Signal;
+use crate::json::{JsonError, JsonValue};
+use crate::strategies::expression::{Expression, ExpressionError};
+use crate::strategies::indicator::Indicator;
+use crate::strategies::indicator_group::IndicatorGroup;
+use crate::strategies::strategy::Strategy;
+use crate::strategies::strategy_monitor::StrategyMonitor;
+
+static DEFAULT_INTERVAL: u64 = 60 * 1000;
+
+pub(crate) static PRICE_FIELDS: [&str; 5] = ["open", "high", "low", "close", "volume"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigError {
+    Json(JsonError),
+    Expression(ExpressionError),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Json(error) => write!(f, "invalid JSON: {}", error),
+            ConfigError::Expression(error) => write!(f, "invalid rule expression: {}", error),
+            ConfigError::Invalid(message) => write!(f, "invalid strategy: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<JsonError> for ConfigError {
+    fn from(error: JsonError) -> ConfigError {
+        ConfigError::Json(error)
+    }
+}
+
+impl From<ExpressionError> for ConfigError {
+    fn from(error: ExpressionError) -> ConfigError {
+        ConfigError::Expression(error)
+    }
+}
+
+fn invalid<T>(message: String) -> Result<T, ConfigError> {
+    Err(ConfigError::Invalid(message))
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum IndicatorKind {
+    SimpleMovingAverage,
+    ExponentialMovingAverage,
+    RelativeStrengthIndex,
+    AverageTrueRange,
+}
+
+impl IndicatorKind {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<IndicatorKind> {
+        match s.to_lowercase().as_str() {
+            "sma" => Some(IndicatorKind::SimpleMovingAverage),
+            "ema" => Some(IndicatorKind::ExponentialMovingAverage),
+            "rsi" => Some(IndicatorKind::RelativeStrengthIndex),
+            "atr" => Some(IndicatorKind::AverageTrueRange),
+            _ => None,
+        }
+    }
+
+    pub fn short_name(&self) -> &str {
+        match self {
+            IndicatorKind::SimpleMovingAverage => "sma",
+            IndicatorKind::ExponentialMovingAverage => "ema",
+            IndicatorKind::RelativeStrengthIndex => "rsi",
+            IndicatorKind::AverageTrueRange => "atr",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndicatorDefinition {
+    pub name: String,
+    pub kind: IndicatorKind,
+
+    pub length: usize,
+    pub interval: u64,
+}
+
+impl IndicatorDefinition {
+    pub fn new(
+        name: &str,
+        kind: IndicatorKind,
+        length: usize,
+        interval: u64,
+    ) -> IndicatorDefinition {
+        IndicatorDefinition {
+            name: name.to_string(),
+            kind,
+
+            length,
+            interval,
+        }
+    }
+
+    pub fn build(&self) -> Box<dyn Indicator> {
+        match self.kind {
+            IndicatorKind::SimpleMovingAverage => Box::new(SimpleMovingAverage::new(self.length, self.interval)),
+            IndicatorKind::ExponentialMovingAverage => Box::new(ExponentialMovingAverage::new(self.length, self.interval)),
+            IndicatorKind::RelativeStrengthIndex => Box::new(RelativeStrengthIndex::new(self.length, self.interval)),
+            IndicatorKind::AverageTrueRange => Box::new(AverageTrueRange::new(self.length, self.interval)),
+        }
+    }
+
+    /// Candles needed before the value can be used.
+    pub fn warm_up(&self) -> u64 {
+        match self.kind {
+            // needs one more close to get `length` changes
+            IndicatorKind::RelativeStrengthIndex => self.length as u64 + 1,
+            _ => self.length as u64,
+        }
+    }
+}
+
+/// Value a rule compares: the slot of an indicator or a field of the
+/// candle, or a number.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operand {
+    Indicator(usize),
+    Price(usize),
+    Constant(f64),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Comparison {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+impl Comparison {
+    pub fn holds(&self, left: f64, right: f64) -> bool {
+        match self {
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rule {
+    All(Vec<Rule>),
+    Any(Vec<Rule>),
+    Not(Box<Rule>),
+
+    Compare(Operand, Comparison, Operand),
+
+    // the first operand goes from at or below the second to above it
+    CrossesAbove(Operand, Operand),
+    CrossesBelow(Operand, Operand),
+
+    // condition written in the rule expression language
+    Expression(Expression),
+}
+
+/// Values of the candle fields, in the order of `PRICE_FIELDS`, followed
+/// by those of the indicators of a definition.
+pub type Values = Vec<f64>;
+
+/// Slot of the value of a candle field or of one of `indicators`.
+pub fn value_slot(name: &str, indicators: &[IndicatorDefinition]) -> Option<usize> {
+    PRICE_FIELDS
+        .iter()
+        .position(|field| *field == name)
+        .or_else(|| indicators
+            .iter()
+            .position(|indicator| indicator.name == name)
+            .map(|index| PRICE_FIELDS.len() + index))
+}
+
+impl Operand {
+    fn value(&self, values: &[f64]) -> Option<f64> {
+        match self {
+            Operand::Indicator(slot) | Operand::Price(slot) => values.get(*slot).copied(),
+            Operand::Constant(value) => Some(*value),
+        }
+    }
+}
+
+impl Rule {
+    /// Whether the rule holds for the current values, `previous` being the
+    /// values of the candle before, if any. False when a value is missing.
+    pub fn evaluate(&self, values: &[f64], previous: Option<&[f64]>) -> bool {
+        self.holds(values, previous).unwrap_or(false)
+    }
+
+    // none when the rule depends on a missing value
+    fn holds(&self, values: &[f64], previous: Option<&[f64]>) -> Option<bool> {
+        let pair = |left: &Operand, right: &Operand, values: &[f64]| {
+            Some((left.value(values)?, right.value(values)?))
+        };
+
+        let crossing = |left: &Operand, right: &Operand| {
+            Some((pair(left, right, previous?)?, pair(left, right, values)?))
+        };
+
+        match self {
+            Rule::All(rules) => {
+                let results: Vec<Option<bool>> = rules.iter().map(|rule| rule.holds(values, previous)).collect();
+
+                if results.contains(&Some(false)) {
+                    Some(false)
+                } else {
+                    results.into_iter().collect::<Option<Vec<bool>>>().map(|_| true)
+                }
+            }
+            Rule::Any(rules) => {
+                let results: Vec<Option<bool>> = rules.iter().map(|rule| rule.holds(values, previous)).collect();
+
+                if results.contains(&Some(true)) {
+                    Some(true)
+                } else {
+                    results.into_iter().collect::<Option<Vec<bool>>>().map(|_| false)
+                }
+            }
+            Rule::Not(rule) => rule.holds(values, previous).map(|holds| !holds),
+            Rule::Compare(left, comparison, right) => pair(left, right, values)
+                .map(|(left, right)| comparison.holds(left, right)),
+            Rule::CrossesAbove(left, right) => crossing(left, right)
+                .map(|((previous_left, previous_right), (left, right))| previous_left <= previous_right && left > right),
+            Rule::CrossesBelow(left, right) => crossing(left, right)
+                .map(|((previous_left, previous_right), (left, right))| previous_left >= previous_right && left < right),
+            Rule::Expression(expression) => expression.condition(values, previous),
+        }
+    }
+
+    // indicators called from rule expressions are added to `indicators`
+    fn parse(value: &JsonValue, indicators: &mut Vec<IndicatorDefinition>, interval: u64) -> Result<Rule, ConfigError> {
+        if let JsonValue::String(source) = value {
+            return Ok(Rule::Expression(Expression::parse_condition(source, indicators, interval)?));
+        }
+
+        let members = match value.as_object() {
+            Some(members) if members.len() == 1 => members,
+            _ => return invalid(format!("a rule must be an object with a single member, got {:?}", value)),
+        };
+
+        let (name, argument) = &members[0];
+
+        let mut rules = |argument: &JsonValue| -> Result<Vec<Rule>, ConfigError> {
+            match argument.as_array() {
+                Some(values) => values.iter().map(|value| Rule::parse(value, indicators, interval)).collect(),
+                None => invalid(format!("'{}' expects an array of rules", name)),
+            }
+        };
+
+        match name.as_str() {
+            "all" => return Ok(Rule::All(rules(argument)?)),
+            "any" => return Ok(Rule::Any(rules(argument)?)),
+            "not" => return Ok(Rule::Not(Box::new(Rule::parse(argument, indicators, interval)?))),
+            _ => (),
+        }
+
+        let operands = |argument: &JsonValue| -> Result<(Operand, Operand), ConfigError> {
+            match argument.as_array() {
+                Some(values) if values.len() == 2 => Ok((
+                    parse_operand(&values[0], indicators)?,
+                    parse_operand(&values[1], indicators)?,
+                )),
+                _ => invalid(format!("'{}' expects an array of two operands", name)),
+            }
+        };
+
+        let compare = |comparison: Comparison| -> Result<Rule, ConfigError> {
+            let (left, right) = operands(argument)?;
+
+            Ok(Rule::Compare(left, comparison, right))
+        };
+
+        match name.as_str() {
+            "gt" => compare(Comparison::Greater),
+            "ge" => compare(Comparison::GreaterOrEqual),
+            "lt" => compare(Comparison::Less),
+            "le" => compare(Comparison::LessOrEqual),
+            "crosses_above" => {
+                let (left, right) = operands(argument)?;
+
+                Ok(Rule::CrossesAbove(left, right))
+            }
+            "crosses_below" => {
+                let (left, right) = operands(argument)?;
+
+                Ok(Rule::CrossesBelow(left, right))
+            }
+            _ => invalid(format!("unknown rule '{}'", name)),
+        }
+    }
+}
+
+fn parse_operand(value: &JsonValue, indicators: &[IndicatorDefinition]) -> Result<Operand, ConfigError> {
+    match value {
+        JsonValue::Number(number, _) => Ok(Operand::Constant(*number)),
+        JsonValue::String(name) => match value_slot(name, indicators) {
+            Some(slot) if slot < PRICE_FIELDS.len() => Ok(Operand::Price(slot)),
+            Some(slot) => Ok(Operand::Indicator(slot)),
+            None => invalid(format!("unknown indicator '{}'", name)),
+        },
+        _ => invalid(format!("invalid operand {:?}", value)),
+    }
+}
+
+/// Options of the monitor built for a strategy.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonitorDefinition {
+    pub allow_trade_switch: bool,
+    pub allow_mixed_strategies: bool,
+}
+
+impl Default for MonitorDefinition {
+    fn default() -> MonitorDefinition {
+        MonitorDefinition {
+            allow_trade_switch: false,
+            allow_mixed_strategies: true,
+        }
+    }
+}
+
+/// Strategy described by a JSON document:
+///
+/// ```json
+/// {
+///     "name": "ema_cross",
+///     "trade_side": "long",
+///     "interval": 60000,
+///     "indicators": {
+///         "fast": { "type": "ema", "length": 9 },
+///         "slow": { "type": "ema", "length": 21 },
+///         "rsi": { "type": "rsi", "length": 14 }
+///     },
+///     "entry": { "all": [{ "crosses_above": ["fast", "slow"] }, { "lt": ["rsi", 70] }] },
+///     "exit": { "crosses_below": ["fast", "slow"] },
+///     "stop_loss": 2.0,
+///     "take_profit": 5.0,
+///     "monitor": { "allow_trade_switch": false }
+/// }
+/// ```
+///
+/// Rules are `all`, `any`, `not`, the comparisons `gt`, `ge`, `lt`, `le`
+/// and `crosses_above`, `crosses_below`. Operands are indicator names, the
+/// candle fields `open`, `high`, `low`, `close` and `volume`, or numbers.
+/// A rule can also be a string in the rule expression language, e.g.
+/// `"crosses_above(ema(9), ema(21)) and rsi < 70"`, whose indicator calls
+/// are added to the indicators. Stop loss and take profit are percentages
+/// of the entry price.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrategyDefinition {
+    pub name: String,
+    pub trade_side: TradeSide,
+
+    pub indicators: Vec<IndicatorDefinition>,
+
+    pub entry: Rule,
+    pub exit: Option<Rule>,
+
+    pub stop_loss: Option<f64>,
+    pub take_profit: Option<f64>,
+
+    pub exit_on_opposite_signal: bool,
+
+    pub monitor: MonitorDefinition,
+}
+
+fn positive_number(value: Option<&JsonValue>, name: &str) -> Result<Option<f64>, ConfigError> {
+    match value {
+        None | Some(JsonValue::Null) => Ok(None),
+        Some(JsonValue::Number(number, _)) if *number > 0.0 => Ok(Some(*number)),
+        Some(value) => invalid(format!("'{}' must be a positive number, got {:?}", name, value)),
+    }
+}
+
+fn boolean(value: Option<&JsonValue>, name: &str, default: bool) -> Result<bool, ConfigError> {
+    match value {
+        None => Ok(default),
+        Some(JsonValue::Bool(value)) => Ok(*value),
+        Some(value) => invalid(format!("'{}' must be a boolean, got {:?}", name, value)),
+    }
+}
+
+impl StrategyDefinition {
+    pub fn load(path: &str) -> io::Result<StrategyDefinition> {
+        let input = fs::read_to_string(path)?;
+
+        StrategyDefinition::parse(&input)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    pub fn parse(input: &str) -> Result<StrategyDefinition, ConfigError> {
+        StrategyDefinition::from_json(&JsonValue::parse(input)?)
+    }
+
+    pub fn from_json(json: &JsonValue) -> Result<StrategyDefinition, ConfigError> {
+        let name = json.get("name")
+            .and_then(|name| name.as_str())
+            .unwrap_or("declarative")
+            .to_string();
+
+        let trade_side = match json.get("trade_side").and_then(|side| side.as_str()) {
+            None => TradeSide::LONG,
+            Some(side) if side.eq_ignore_ascii_case("long") => TradeSide::LONG,
+            Some(side) if side.eq_ignore_ascii_case("short") => TradeSide::SHORT,
+            Some(side) => return invalid(format!("unknown trade side '{}'", side)),
+        };
+
+        let interval = positive_number(json.get("interval"), "interval")?
+            .map(|interval| interval as u64)
+            .unwrap_or(DEFAULT_INTERVAL);
+
+        let mut indicators = match json.get("indicators") {
+            None => vec![],
+            Some(JsonValue::Object(members)) => members
+                .iter()
+                .map(|(name, definition)| parse_indicator(name, definition, interval))
+                .collect::<Result<Vec<IndicatorDefinition>, ConfigError>>()?,
+            Some(_) => return invalid("'indicators' must be an object".to_string()),
+        };
+
+        let entry = match json.get("entry") {
+            Some(entry) => Rule::parse(entry, &mut indicators, interval)?,
+            None => return invalid("missing entry rule".to_string()),
+        };
+
+        let exit = match json.get("exit") {
+            Some(exit) if !exit.is_null() => Some(Rule::parse(exit, &mut indicators, interval)?),
+            _ => None,
+        };
+
+        let monitor = match json.get("monitor") {
+            None => MonitorDefinition::default(),
+            Some(monitor) => MonitorDefinition {
+                allow_trade_switch: boolean(monitor.get("allow_trade_switch"), "allow_trade_switch", false)?,
+                allow_mixed_strategies: boolean(monitor.get("allow_mixed_strategies"), "allow_mixed_strategies", true)?,
+            },
+        };
+
+        Ok(StrategyDefinition {
+            name,
+            trade_side,
+
+            indicators,
+
+            entry,
+            exit,
+
+            stop_loss: positive_number(json.get("stop_loss"), "stop_loss")?,
+            take_profit: positive_number(json.get("take_profit"), "take_profit")?,
+
+            exit_on_opposite_signal: boolean(json.get("exit_on_opposite_signal"), "exit_on_opposite_signal", true)?,
+
+            monitor,
+        })
+    }
+
+    /// Definition of a strategy whose entry and exit are written in the
+    /// rule expression language, e.g. `crosses_above(ema(9), ema(21))`.
+    pub fn from_expressions(
+        name: &str,
+        trade_side: TradeSide,
+        entry: &str,
+        exit: Option<&str>,
+    ) -> Result<StrategyDefinition, ConfigError> {
+        let mut indicators = vec![];
+
+        let entry = Rule::Expression(Expression::parse_condition(entry, &mut indicators, DEFAULT_INTERVAL)?);
+
+        let exit = match exit {
+            Some(exit) => Some(Rule::Expression(Expression::parse_condition(exit, &mut indicators, DEFAULT_INTERVAL)?)),
+            None => None,
+        };
+
+        Ok(StrategyDefinition {
+            name: name.to_string(),
+            trade_side,
+
+            indicators,
+
+            entry,
+            exit,
+
+            stop_loss: None,
+            take_profit: None,
+
+            exit_on_opposite_signal: true,
+
+            monitor: MonitorDefinition::default(),
+        })
+    }
+
+    pub fn build(&self) -> DeclarativeStrategy {
+        DeclarativeStrategy::new(self.clone())
+    }
+
+    pub fn build_monitor(&self) -> DeclarativeMonitor {
+        DeclarativeMonitor::new(self.monitor.clone())
+    }
+
+    /// Attaches the stop loss and take profit to an entry request, which
+    /// must have its price set.
+    pub fn attach_exits(&self, order_request: &mut OrderRequest) {
+        // price moves that lose money go down for longs and up for shorts
+        let direction = match self.trade_side {
+            TradeSide::LONG => 1.0,
+            TradeSide::SHORT => -1.0,
+        };
+
+        if let Some(stop_loss) = self.stop_loss {
+            order_request.attach_to_percentage_change(OrderType::Market, -stop_loss * direction);
+        }
+
+        if let Some(take_profit) = self.take_profit {
+            order_request.attach_to_percentage_change(OrderType::Limit, take_profit * direction);
+        }
+    }
+}
+
+fn parse_indicator(name: &str, definition: &JsonValue, interval: u64) -> Result<IndicatorDefinition, ConfigError> {
+    if PRICE_FIELDS.contains(&name) {
+        return invalid(format!("indicator name '{}' is reserved", name));
+    }
+
+    let kind = definition.get("type")
+        .and_then(|kind| kind.as_str())
+        .and_then(IndicatorKind::from_str);
+
+    let kind = match kind {
+        Some(kind) => kind,
+        None => return invalid(format!("indicator '{}' has no valid type", name)),
+    };
+
+    let length = match positive_number(definition.get("length"), "length")? {
+        Some(length) => length as usize,
+        None => return invalid(format!("indicator '{}' has no length", name)),
+    };
+
+    let interval = positive_number(definition.get("interval"), "interval")?
+        .map(|interval| interval as u64)
+        .unwrap_or(interval);
+
+    Ok(IndicatorDefinition::new(name, kind, length, interval))
+}
+
+/// Strategy built from a definition. Each candle given to `get_signal` is
+/// accumulated by the indicators, so it must see every candle once.
+/// Signals stay neutral until all indicators are warmed up.
+pub struct DeclarativeStrategy {
+    definition: StrategyDefinition,
+
+    indicators: Vec<(IndicatorDefinition, Box<dyn Indicator>)>,
+
+    // values of the last candle and of the one before, none until the
+    // indicators are warmed up
+    values: RefCell<Values>,
+    previous: RefCell<Values>,
+}
+
+impl DeclarativeStrategy {
+    pub fn new(definition: StrategyDefinition) -> DeclarativeStrategy {
+        let indicators = definition.indicators
+            .iter()
+            .map(|indicator| (indicator.clone(), indicator.build()))
+            .collect();
+
+        DeclarativeStrategy {
+            definition,
+
+            indicators,
+
+            values: RefCell::new(vec![]),
+            previous: RefCell::new(vec![]),
+        }
+    }
+
+    pub fn definition(&self) -> &StrategyDefinition {
+        &self.definition
+    }
+
+    pub fn indicator(&self, name: &str) -> Option<&dyn Indicator> {
+        self.indicators
+            .iter()
+            .find(|(definition, _)| definition.name == name)
+            .map(|(_, indicator)| indicator.as_ref())
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.indicators
+            .iter()
+            .all(|(definition, indicator)| indicator.get_accumulation_count() >= definition.warm_up() as f64)
+    }
+
+    fn fill_values(&self, candle: &Candle, values: &mut Values) {
+        values.clear();
+        values.extend_from_slice(&[candle.open, candle.high, candle.low, candle.close, candle.volume]);
+        values.extend(self.indicators.iter().map(|(_, indicator)| indicator.get_value()));
+    }
+}
+
+impl Strategy for DeclarativeStrategy {
+    fn get_signal(&self, candle: Candle) -> Signal {
+        for (_, indicator) in self.indicators.iter() {
+            indicator.accumulate(&candle);
+        }
+
+        if !self.is_ready() {
+            return Signal::NEUTRAL;
+        }
+
+        let mut values = self.values.borrow_mut();
+        let mut previous = self.previous.borrow_mut();
+
+        // the values of the last candle become the previous ones, and the
+        // buffer of the candle before is reused
+        std::mem::swap(&mut *values, &mut *previous);
+
+        self.fill_values(&candle, &mut values);
+
+        let previous = Some(previous.as_slice()).filter(|previous| !previous.is_empty());
+
+        let exit = self.definition.exit
+            .as_ref()
+            .map(|exit| exit.evaluate(&values, previous))
+            .unwrap_or(false);
+
+        let entry = self.definition.entry.evaluate(&values, previous);
+
+        let (entry_signal, exit_signal) = match self.definition.trade_side {
+            TradeSide::LONG => (Signal::BUY, Signal::SELL),
+            TradeSide::SHORT => (Signal::SELL, Signal::BUY),
+        };
+
+        if exit {
+            exit_signal
+        } else if entry {
+            entry_signal
+        } else {
+            Signal::NEUTRAL
+        }
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.definition.name)
+    }
+
+    fn prepare_entry(&self, order_request: &mut OrderRequest) {
+        self.definition.attach_exits(order_request);
+    }
+
+    fn monitors(&self) -> Vec<Box<dyn StrategyMonitor>> {
+        vec![Box::new(self.definition.build_monitor())]
+    }
+
+    fn trade_side(&self) -> Option<TradeSide> {
+        Some(self.definition.trade_side)
+    }
+
+    fn exit_on_opposite_signal(&self) -> bool {
+        self.definition.exit_on_opposite_signal
+    }
+}
+
+/// Monitor built from the `monitor` section of a definition.
+pub struct DeclarativeMonitor {
+    definition: MonitorDefinition,
+}
+
+impl DeclarativeMonitor {
+    pub fn new(definition: MonitorDefinition) -> DeclarativeMonitor {
+        DeclarativeMonitor {
+            definition,
+        }
+    }
+
+    pub fn definition(&self) -> &MonitorDefinition {
+        &self.definition
+    }
+}
+
+impl IndicatorGroup for DeclarativeMonitor {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn get_indicators(&self) -> Vec<Box<dyn Indicator>> {
+        vec![]
+    }
+
+    fn candle_accumulated(&self, _candle: &Candle) {}
+}
+
+impl StrategyMonitor for DeclarativeMonitor {
+    fn allow_mixed_strategies(&self) -> bool {
+        self.definition.allow_mixed_strategies
+    }
+
+    fn allow_trade_switch(
+        &self,
+        _trade: &Trade,
+        _exit_symbol: String,
+        _candle: Candle,
+        _candle_ticker: String,
+        _context: &Context,
+    ) -> bool {
+        self.definition.allow_trade_switch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::order::{OrderSide, TriggerCondition};
+    use crate::decimal::Decimal;
+
+    #[test]
+    fn builds_strategy_from_json() {
+        let definition = StrategyDefinition::parse(r#"{
+            "name": "sma_cross",
+            "indicators": {
+                "fast": { "type": "sma", "length": 2 },
+                "slow": { "type": "sma", "length": 4 }
+            },
+            "entry": { "all": [{ "crosses_above": ["fast", "slow"] }, { "gt": ["close", 0] }] },
+            "exit": { "crosses_below": ["fast", "slow"] },
+            "stop_loss": 2,
+            "monitor": { "allow_trade_switch": true }
+        }"#).unwrap();
+
+        assert_eq!(definition.indicators.len(), 2);
+        assert_eq!(definition.stop_loss, Some(2.0));
+        assert!(definition.build_monitor().definition().allow_trade_switch);
+
+        let strategy = definition.build();
+
+        let signals: Vec<Signal> = [10.0, 9.0, 8.0, 7.0, 9.0, 12.0, 8.0, 5.0]
+            .iter()
+            .enumerate()
+            .map(|(i, close)| {
+                let time = i as u64 * 60_000;
+
+                strategy.get_signal(Candle::new(time, time + 59_999, *close, *close, *close, *close, 1.0))
+            })
+            .collect();
+
+        assert_eq!(signals[5], Signal::BUY);
+        assert_eq!(signals[7], Signal::SELL);
+        assert_eq!(signals.iter().filter(|signal| **signal != Signal::NEUTRAL).count(), 2);
+
+        let error = StrategyDefinition::parse(r#"{ "entry": { "gt": ["fast", 1] } }"#).unwrap_err();
+
+        assert_eq!(error, ConfigError::Invalid("unknown indicator 'fast'".to_string()));
+
+        let definition = StrategyDefinition::parse(r#"{ "entry": "crosses_above(sma(2), sma(4)) and close > 0" }"#).unwrap();
+
+        assert_eq!(definition.indicators.len(), 2);
+        assert_eq!(definition.indicators[1].name, "sma(4)");
+    }
+
+    #[test]
+    fn attaches_stop_loss_and_take_profit_to_entries() {
+        let strategy = StrategyDefinition::parse(r#"{
+            "name": "dip",
+            "entry": { "lt": ["close", 95] },
+            "stop_loss": 2,
+            "take_profit": 4
+        }"#).unwrap().build();
+
+        let mut order_request = OrderRequest::new(
+            "BTC".to_string(),
+            "USDT".to_string(),
+            OrderSide::Buy,
+            TradeSide::LONG,
+            0,
+            None,
+        );
+
+        order_request.set_price(Decimal::from(100));
+        order_request.set_quantity(Decimal::ONE);
+
+        strategy.prepare_entry(&mut order_request);
+
+        let attachments = order_request.attached_order_requests();
+
+        assert_eq!(attachments.len(), 2);
+
+        assert!(attachments.iter().all(|attachment| attachment.is_sell() && attachment.quantity() == Decimal::ONE));
+        assert_eq!(*attachments[0].trigger_condition(), TriggerCondition::StopLoss);
+        assert_eq!(attachments[0].trigger_price(), Decimal::from(98));
+        assert_eq!(*attachments[1].trigger_condition(), TriggerCondition::StopGain);
+        assert_eq!(attachments[1].price(), Decimal::from(104));
+    }
+
+    #[test]
+    fn negated_rules_of_missing_values_do_not_hold() {
+        let strategy = StrategyDefinition::parse(r#"{
+            "indicators": { "slow": { "type": "sma", "length": 2 } },
+            "entry": { "not": { "crosses_above": ["close", "slow"] } }
+        }"#).unwrap().build();
+
+        let signals: Vec<Signal> = (0..3)
+            .map(|i| {
+                let time = i * 60_000;
+
+                strategy.get_signal(Candle::new(time, time + 59_999, 10.0, 10.0, 10.0, 10.0, 1.0))
+            })
+            .collect();
+
+        // nothing to cross from on the first candle the indicators are ready
+        assert_eq!(signals, [Signal::NEUTRAL, Signal::NEUTRAL, Signal::BUY]);
+    }
+}
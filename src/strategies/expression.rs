@@ -0,0 +1,759 @@
+use std::fmt;
+use crate::strategies::declarative::{value_slot, IndicatorDefinition, IndicatorKind};
+
+/// Error of an expression, positioned at the character it was found at.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExpressionError {
+    pub message: String,
+
+    // character offset in the source
+    pub position: usize,
+
+    pub line: usize,
+    pub column: usize,
+}
+
+impl ExpressionError {
+    fn new(source: &str, position: usize, message: String) -> ExpressionError {
+        let before: Vec<char> = source.chars().take(position).collect();
+
+        let line = before.iter().filter(|c| **c == '\n').count() + 1;
+        let column = before.iter().rev().take_while(|c| **c != '\n').count() + 1;
+
+        ExpressionError {
+            message,
+
+            position,
+
+            line,
+            column,
+        }
+    }
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}, column {}", self.message, self.line, self.column)
+    }
+}
+
+impl std::error::Error for ExpressionError {}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Type {
+    Number,
+    Bool,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+
+    And,
+    Or,
+}
+
+impl BinaryOperator {
+    fn symbol(&self) -> &str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterOrEqual => ">=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::LessOrEqual => "<=",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::And => "and",
+            BinaryOperator::Or => "or",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExpressionKind {
+    Number(f64),
+    Bool(bool),
+
+    // candle field or named indicator
+    Identifier(String),
+
+    Call(String, Vec<Expression>),
+
+    Negate(Box<Expression>),
+    Not(Box<Expression>),
+
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
+}
+
+/// Node of a parsed expression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+
+    // character offset of the node in the source
+    pub position: usize,
+
+    // slot of the value of an identifier or indicator call, set by `check`
+    pub slot: Option<usize>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Value {
+    Number(f64),
+    Bool(bool),
+}
+
+impl Value {
+    fn number(self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(number),
+            Value::Bool(_) => None,
+        }
+    }
+
+    fn bool(self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(value),
+            Value::Number(_) => None,
+        }
+    }
+}
+
+/// Name the value of an indicator call is stored under, e.g. `ema(9)`.
+pub fn indicator_key(kind: IndicatorKind, length: usize) -> String {
+    format!("{}({})", kind.short_name(), length)
+}
+
+impl Expression {
+    /// Parses expressions such as
+    /// `crosses_above(ema(9), ema(21)) and rsi(14) < 70`.
+    ///
+    /// Operators are, from the loosest to the tightest: `or`, `and`, `not`,
+    /// the comparisons `<`, `<=`, `>`, `>=`, `==`, `!=`, then `+`, `-` and
+    /// `*`, `/`. Indicators are called with their length (`sma`, `ema`,
+    /// `rsi`, `atr`), the other functions are `crosses_above`,
+    /// `crosses_below`, `abs`, `min` and `max`. Identifiers are candle
+    /// fields or indicators named in a strategy definition.
+    pub fn parse(source: &str) -> Result<Expression, ExpressionError> {
+        let tokens = tokenize(source)?;
+
+        let mut parser = Parser {
+            source,
+            tokens,
+            position: 0,
+        };
+
+        let expression = parser.or()?;
+
+        match parser.peek() {
+            Token::End => Ok(expression),
+            token => Err(parser.error(format!("unexpected {}", token.describe()))),
+        }
+    }
+
+    /// Parses and checks a condition, i.e. an expression of type bool.
+    /// Indicators it calls are added to `indicators`, on `interval`.
+    pub fn parse_condition(
+        source: &str,
+        indicators: &mut Vec<IndicatorDefinition>,
+        interval: u64,
+    ) -> Result<Expression, ExpressionError> {
+        let mut expression = Expression::parse(source)?;
+
+        match expression.check(indicators, interval) {
+            Ok(Type::Bool) => Ok(expression),
+            Ok(Type::Number) => Err(ExpressionError::new(source, 0, "expected a condition, found a number".to_string())),
+            Err((position, message)) => Err(ExpressionError::new(source, position, message)),
+        }
+    }
+
+    /// Type of the expression, or the position and message of the first
+    /// error. Identifiers must be candle fields or one of `indicators`, and
+    /// indicator calls are added to them unless already there. Resolves
+    /// the slots of the values the expression reads.
+    pub fn check(&mut self, indicators: &mut Vec<IndicatorDefinition>, interval: u64) -> Result<Type, (usize, String)> {
+        let position = self.position;
+
+        match &mut self.kind {
+            ExpressionKind::Number(_) => Ok(Type::Number),
+            ExpressionKind::Bool(_) => Ok(Type::Bool),
+            ExpressionKind::Identifier(name) => match value_slot(name, indicators) {
+                Some(slot) => {
+                    self.slot = Some(slot);
+
+                    Ok(Type::Number)
+                }
+                None => Err((position, format!("unknown indicator '{}'", name))),
+            },
+            ExpressionKind::Call(name, arguments) => {
+                let count = arguments.len();
+
+                let arity = |expected: usize| -> Result<(), (usize, String)> {
+                    if count != expected {
+                        return Err((position, format!("'{}' takes {} arguments, got {}", name, expected, count)));
+                    }
+
+                    Ok(())
+                };
+
+                if let Some(kind) = IndicatorKind::from_str(name) {
+                    arity(1)?;
+
+                    let length = match arguments[0].kind {
+                        ExpressionKind::Number(length) if length >= 1.0 && length.fract() == 0.0 => length as usize,
+                        _ => return Err((arguments[0].position, format!("the length of '{}' must be a positive integer", name))),
+                    };
+
+                    let key = indicator_key(kind, length);
+
+                    if !indicators.iter().any(|indicator| indicator.name == key) {
+                        indicators.push(IndicatorDefinition::new(&key, kind, length, interval));
+                    }
+
+                    self.slot = value_slot(&key, indicators);
+
+                    return Ok(Type::Number);
+                }
+
+                let (count, operands, result) = match name.as_str() {
+                    "crosses_above" | "crosses_below" => (2, Type::Number, Type::Bool),
+                    "abs" => (1, Type::Number, Type::Number),
+                    "min" | "max" => (2, Type::Number, Type::Number),
+                    _ => return Err((position, format!("unknown function '{}'", name))),
+                };
+
+                arity(count)?;
+
+                for argument in arguments.iter_mut() {
+                    argument.expect(operands, indicators, interval)?;
+                }
+
+                Ok(result)
+            }
+            ExpressionKind::Negate(operand) => {
+                operand.expect(Type::Number, indicators, interval)?;
+
+                Ok(Type::Number)
+            }
+            ExpressionKind::Not(operand) => {
+                operand.expect(Type::Bool, indicators, interval)?;
+
+                Ok(Type::Bool)
+            }
+            ExpressionKind::Binary(operator, left, right) => {
+                let (operands, result) = match operator {
+                    BinaryOperator::Add
+                    | BinaryOperator::Subtract
+                    | BinaryOperator::Multiply
+                    | BinaryOperator::Divide => (Type::Number, Type::Number),
+                    BinaryOperator::And | BinaryOperator::Or => (Type::Bool, Type::Bool),
+                    _ => (Type::Number, Type::Bool),
+                };
+
+                left.expect(operands, indicators, interval)?;
+                right.expect(operands, indicators, interval)?;
+
+                Ok(result)
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: Type, indicators: &mut Vec<IndicatorDefinition>, interval: u64) -> Result<(), (usize, String)> {
+        let found = self.check(indicators, interval)?;
+
+        if found != expected {
+            return Err((self.position, format!("expected {:?}, found {:?}", expected, found).to_lowercase()));
+        }
+
+        Ok(())
+    }
+
+    /// Value of a checked condition, false when a value is missing.
+    pub fn is_true(&self, values: &[f64], previous: Option<&[f64]>) -> bool {
+        self.condition(values, previous).unwrap_or(false)
+    }
+
+    /// Value of a checked condition, none when it depends on a missing
+    /// value.
+    pub fn condition(&self, values: &[f64], previous: Option<&[f64]>) -> Option<bool> {
+        self.evaluate(values, previous)?.bool()
+    }
+
+    /// Value of a checked numeric expression.
+    pub fn number(&self, values: &[f64], previous: Option<&[f64]>) -> Option<f64> {
+        self.evaluate(values, previous)?.number()
+    }
+
+    fn evaluate(&self, values: &[f64], previous: Option<&[f64]>) -> Option<Value> {
+        let number = |expression: &Expression| expression.number(values, previous);
+        let condition = |expression: &Expression| expression.condition(values, previous);
+
+        let value = match &self.kind {
+            ExpressionKind::Number(number) => Value::Number(*number),
+            ExpressionKind::Bool(value) => Value::Bool(*value),
+            ExpressionKind::Identifier(_) => Value::Number(*values.get(self.slot?)?),
+            ExpressionKind::Call(name, arguments) => {
+                if self.slot.is_some() {
+                    return Some(Value::Number(*values.get(self.slot?)?));
+                }
+
+                match name.as_str() {
+                    "crosses_above" | "crosses_below" => {
+                        let previous = previous?;
+
+                        let (left, right) = (number(&arguments[0])?, number(&arguments[1])?);
+
+                        let previous_left = arguments[0].number(previous, None)?;
+                        let previous_right = arguments[1].number(previous, None)?;
+
+                        if name == "crosses_above" {
+                            Value::Bool(previous_left <= previous_right && left > right)
+                        } else {
+                            Value::Bool(previous_left >= previous_right && left < right)
+                        }
+                    }
+                    "abs" => Value::Number(number(&arguments[0])?.abs()),
+                    "min" => Value::Number(number(&arguments[0])?.min(number(&arguments[1])?)),
+                    "max" => Value::Number(number(&arguments[0])?.max(number(&arguments[1])?)),
+                    _ => return None,
+                }
+            }
+            ExpressionKind::Negate(operand) => Value::Number(-number(operand)?),
+            ExpressionKind::Not(operand) => Value::Bool(!condition(operand)?),
+            // a missing value is unknown, which is decided by the other
+            // operand when it is false for `and` or true for `or`
+            ExpressionKind::Binary(BinaryOperator::And, left, right) => match (condition(left), condition(right)) {
+                (Some(false), _) | (_, Some(false)) => Value::Bool(false),
+                (Some(true), Some(true)) => Value::Bool(true),
+                _ => return None,
+            },
+            ExpressionKind::Binary(BinaryOperator::Or, left, right) => match (condition(left), condition(right)) {
+                (Some(true), _) | (_, Some(true)) => Value::Bool(true),
+                (Some(false), Some(false)) => Value::Bool(false),
+                _ => return None,
+            },
+            ExpressionKind::Binary(operator, left, right) => {
+                let (left, right) = (number(left)?, number(right)?);
+
+                match operator {
+                    BinaryOperator::Add => Value::Number(left + right),
+                    BinaryOperator::Subtract => Value::Number(left - right),
+                    BinaryOperator::Multiply => Value::Number(left * right),
+                    BinaryOperator::Divide if right == 0.0 => return None,
+                    BinaryOperator::Divide => Value::Number(left / right),
+                    BinaryOperator::Greater => Value::Bool(left > right),
+                    BinaryOperator::GreaterOrEqual => Value::Bool(left >= right),
+                    BinaryOperator::Less => Value::Bool(left < right),
+                    BinaryOperator::LessOrEqual => Value::Bool(left <= right),
+                    BinaryOperator::Equal => Value::Bool(left == right),
+                    BinaryOperator::NotEqual => Value::Bool(left != right),
+                    BinaryOperator::And | BinaryOperator::Or => return None,
+                }
+            }
+        };
+
+        Some(value)
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ExpressionKind::Number(number) => write!(f, "{}", number),
+            ExpressionKind::Bool(value) => write!(f, "{}", value),
+            ExpressionKind::Identifier(name) => write!(f, "{}", name),
+            ExpressionKind::Call(name, arguments) => {
+                let arguments: Vec<String> = arguments.iter().map(|argument| argument.to_string()).collect();
+
+                write!(f, "{}({})", name, arguments.join(", "))
+            }
+            ExpressionKind::Negate(operand) => write!(f, "-{}", operand),
+            ExpressionKind::Not(operand) => write!(f, "not {}", operand),
+            ExpressionKind::Binary(operator, left, right) => write!(f, "({} {} {})", left, operator.symbol(), right),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+
+    LeftParenthesis,
+    RightParenthesis,
+    Comma,
+
+    Operator(BinaryOperator),
+    Minus,
+    Not,
+
+    End,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Number(number) => format!("number {}", number),
+            Token::Identifier(name) => format!("'{}'", name),
+            Token::LeftParenthesis => "'('".to_string(),
+            Token::RightParenthesis => "')'".to_string(),
+            Token::Comma => "','".to_string(),
+            Token::Operator(operator) => format!("'{}'", operator.symbol()),
+            Token::Minus => "'-'".to_string(),
+            Token::Not => "'not'".to_string(),
+            Token::End => "end of input".to_string(),
+        }
+    }
+}
+
+// tokens with the character offset they start at
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ExpressionError> {
+    let chars: Vec<char> = source.chars().collect();
+
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+
+        if c.is_whitespace() {
+            i += 1;
+
+            continue;
+        }
+
+        let next = chars.get(i + 1).copied();
+
+        let token = if c.is_ascii_digit() || (c == '.' && next.map(|next| next.is_ascii_digit()).unwrap_or(false)) {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+
+            let text: String = chars[start..i].iter().collect();
+
+            match text.parse::<f64>() {
+                Ok(number) => Token::Number(number),
+                Err(_) => return Err(ExpressionError::new(source, start, format!("invalid number '{}'", text))),
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+
+            let word: String = chars[start..i].iter().collect();
+
+            match word.as_str() {
+                "and" => Token::Operator(BinaryOperator::And),
+                "or" => Token::Operator(BinaryOperator::Or),
+                "not" => Token::Not,
+                _ => Token::Identifier(word),
+            }
+        } else {
+            let (token, length) = match (c, next) {
+                ('(', _) => (Token::LeftParenthesis, 1),
+                (')', _) => (Token::RightParenthesis, 1),
+                (',', _) => (Token::Comma, 1),
+                ('+', _) => (Token::Operator(BinaryOperator::Add), 1),
+                ('-', _) => (Token::Minus, 1),
+                ('*', _) => (Token::Operator(BinaryOperator::Multiply), 1),
+                ('/', _) => (Token::Operator(BinaryOperator::Divide), 1),
+                ('>', Some('=')) => (Token::Operator(BinaryOperator::GreaterOrEqual), 2),
+                ('>', _) => (Token::Operator(BinaryOperator::Greater), 1),
+                ('<', Some('=')) => (Token::Operator(BinaryOperator::LessOrEqual), 2),
+                ('<', _) => (Token::Operator(BinaryOperator::Less), 1),
+                ('=', Some('=')) => (Token::Operator(BinaryOperator::Equal), 2),
+                ('!', Some('=')) => (Token::Operator(BinaryOperator::NotEqual), 2),
+                _ => return Err(ExpressionError::new(source, start, format!("unexpected character '{}'", c))),
+            };
+
+            i += length;
+
+            token
+        };
+
+        tokens.push((token, start));
+    }
+
+    tokens.push((Token::End, chars.len()));
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens[self.position].1
+    }
+
+    fn advance(&mut self) -> (Token, usize) {
+        let token = self.tokens[self.position].clone();
+
+        if token.0 != Token::End {
+            self.position += 1;
+        }
+
+        token
+    }
+
+    fn error(&self, message: String) -> ExpressionError {
+        ExpressionError::new(self.source, self.offset(), message)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ExpressionError> {
+        if *self.peek() != expected {
+            return Err(self.error(format!("expected {}, found {}", expected.describe(), self.peek().describe())));
+        }
+
+        self.advance();
+
+        Ok(())
+    }
+
+    fn binary(
+        &mut self,
+        operators: &[BinaryOperator],
+        operand: fn(&mut Parser<'a>) -> Result<Expression, ExpressionError>,
+        repeat: bool,
+    ) -> Result<Expression, ExpressionError> {
+        let mut left = operand(self)?;
+
+        while let Token::Operator(operator) = *self.peek() {
+            if !operators.contains(&operator) {
+                break;
+            }
+
+            self.advance();
+
+            let right = operand(self)?;
+            let position = left.position;
+
+            left = Expression {
+                kind: ExpressionKind::Binary(operator, Box::new(left), Box::new(right)),
+                position,
+                slot: None,
+            };
+
+            if !repeat {
+                break;
+            }
+        }
+
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expression, ExpressionError> {
+        self.binary(&[BinaryOperator::Or], Parser::and, true)
+    }
+
+    fn and(&mut self) -> Result<Expression, ExpressionError> {
+        self.binary(&[BinaryOperator::And], Parser::not, true)
+    }
+
+    fn not(&mut self) -> Result<Expression, ExpressionError> {
+        if *self.peek() != Token::Not {
+            return self.comparison();
+        }
+
+        let (_, position) = self.advance();
+
+        Ok(Expression {
+            kind: ExpressionKind::Not(Box::new(self.not()?)),
+            position,
+            slot: None,
+        })
+    }
+
+    fn comparison(&mut self) -> Result<Expression, ExpressionError> {
+        self.binary(
+            &[
+                BinaryOperator::Greater,
+                BinaryOperator::GreaterOrEqual,
+                BinaryOperator::Less,
+                BinaryOperator::LessOrEqual,
+                BinaryOperator::Equal,
+                BinaryOperator::NotEqual,
+            ],
+            Parser::additive,
+            false,
+        )
+    }
+
+    fn additive(&mut self) -> Result<Expression, ExpressionError> {
+        let mut left = self.multiplicative()?;
+
+        loop {
+            let operator = match self.peek() {
+                Token::Operator(BinaryOperator::Add) => BinaryOperator::Add,
+                Token::Minus => BinaryOperator::Subtract,
+                _ => return Ok(left),
+            };
+
+            self.advance();
+
+            let right = self.multiplicative()?;
+            let position = left.position;
+
+            left = Expression {
+                kind: ExpressionKind::Binary(operator, Box::new(left), Box::new(right)),
+                position,
+                slot: None,
+            };
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expression, ExpressionError> {
+        self.binary(&[BinaryOperator::Multiply, BinaryOperator::Divide], Parser::unary, true)
+    }
+
+    fn unary(&mut self) -> Result<Expression, ExpressionError> {
+        if *self.peek() != Token::Minus {
+            return self.primary();
+        }
+
+        let (_, position) = self.advance();
+
+        Ok(Expression {
+            kind: ExpressionKind::Negate(Box::new(self.unary()?)),
+            position,
+            slot: None,
+        })
+    }
+
+    fn primary(&mut self) -> Result<Expression, ExpressionError> {
+        let (token, position) = self.advance();
+
+        let kind = match token {
+            Token::Number(number) => ExpressionKind::Number(number),
+            Token::Identifier(name) if name == "true" => ExpressionKind::Bool(true),
+            Token::Identifier(name) if name == "false" => ExpressionKind::Bool(false),
+            Token::Identifier(name) if *self.peek() == Token::LeftParenthesis => {
+                self.advance();
+
+                let mut arguments = vec![];
+
+                if *self.peek() != Token::RightParenthesis {
+                    loop {
+                        arguments.push(self.or()?);
+
+                        if *self.peek() != Token::Comma {
+                            break;
+                        }
+
+                        self.advance();
+                    }
+                }
+
+                self.expect(Token::RightParenthesis)?;
+
+                ExpressionKind::Call(name, arguments)
+            }
+            Token::Identifier(name) => ExpressionKind::Identifier(name),
+            Token::LeftParenthesis => {
+                let expression = self.or()?;
+
+                self.expect(Token::RightParenthesis)?;
+
+                return Ok(Expression {
+                    position,
+                    ..expression
+                });
+            }
+            token => {
+                self.position -= usize::from(token != Token::End);
+
+                return Err(self.error(format!("unexpected {}", token.describe())));
+            }
+        };
+
+        Ok(Expression {
+            kind,
+            position,
+            slot: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_checks_and_evaluates() {
+        let mut indicators = vec![];
+
+        let expression = Expression::parse_condition(
+            "crosses_above(ema(9), ema(21)) and rsi(14) < 70 - 5",
+            &mut indicators,
+            60_000,
+        ).unwrap();
+
+        assert_eq!(expression.to_string(), "(crosses_above(ema(9), ema(21)) and (rsi(14) < (70 - 5)))");
+
+        let names: Vec<&str> = indicators.iter().map(|indicator| indicator.name.as_str()).collect();
+
+        assert_eq!(names, ["ema(9)", "ema(21)", "rsi(14)"]);
+
+        // candle fields, then the indicators in the order they were added
+        let values = |fast: f64, slow: f64, rsi: f64| -> Vec<f64> {
+            vec![0.0, 0.0, 0.0, 0.0, 0.0, fast, slow, rsi]
+        };
+
+        assert!(expression.is_true(&values(11.0, 10.0, 60.0), Some(&values(9.0, 10.0, 50.0))));
+        assert!(!expression.is_true(&values(11.0, 10.0, 66.0), Some(&values(9.0, 10.0, 50.0))));
+        assert!(!expression.is_true(&values(11.0, 10.0, 60.0), None));
+
+        let error = Expression::parse("rsi(14) <\n  and close").unwrap_err();
+
+        assert_eq!((error.position, error.line, error.column), (12, 2, 3));
+
+        let error = Expression::parse_condition("close > sma(0) or volume", &mut vec![], 60_000).unwrap_err();
+
+        assert_eq!(error.column, 13);
+        assert!(Expression::parse_condition("close + 1", &mut vec![], 60_000).is_err());
+    }
+
+    #[test]
+    fn missing_values_are_unknown() {
+        let mut indicators = vec![];
+
+        let not = Expression::parse_condition("not crosses_above(close, sma(2))", &mut indicators, 60_000).unwrap();
+        let or = Expression::parse_condition("close > 1 or not close < sma(2)", &mut indicators, 60_000).unwrap();
+        let and = Expression::parse_condition("close < 1 and not close < sma(2)", &mut indicators, 60_000).unwrap();
+
+        assert_eq!(indicators.len(), 1);
+
+        let values = [0.0, 0.0, 0.0, 3.0, 0.0];
+
+        // no previous candle to cross from, and no value of sma(2)
+        assert_eq!(not.condition(&values, None), None);
+        assert!(!not.is_true(&values, None));
+
+        assert_eq!(or.condition(&values, None), Some(true));
+        assert_eq!(and.condition(&values, None), Some(false));
+        assert_eq!(and.condition(&[0.0, 0.0, 0.0, 0.5, 0.0], None), None);
+    }
+}
@@ -14,7 +14,7 @@ pub trait Indicator {
     fn get_signal(&self, candle: &Candle) -> Signal;
 
     fn signal_description(&self) -> String {
-        return String::new();
+        String::new()
     }
 
     fn initialize(
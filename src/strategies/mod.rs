@@ -1,3 +1,6 @@
+pub mod cross_asset_strategy;
+pub mod declarative;
+pub mod expression;
 pub mod indicator;
 pub mod indicator_group;
 pub mod strategy;
@@ -1,16 +1,38 @@
-use std::cmp::Ordering;
 use crate::candles::candle::Candle;
 use crate::indicators::signal::Signal;
+use crate::account::context::Context;
+use crate::account::order_request::OrderRequest;
 use crate::account::trade::TradeSide;
+use crate::strategies::strategy_monitor::StrategyMonitor;
 
 pub trait Strategy {
     fn get_signal(&self, candle: Candle) -> Signal;
 
+    /// Signal for strategies that look at more than the candle. Defaults to
+    /// `get_signal`.
+    fn get_signal_with_context(&self, candle: Candle, _context: &Context) -> Signal {
+        self.get_signal(candle)
+    }
+
+    /// Name the trades opened for the strategy are attributed to.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Called with each entry before it is submitted, priced at the close
+    /// it was decided on, e.g. to attach a stop loss and a take profit.
+    fn prepare_entry(&self, _order_request: &mut OrderRequest) {}
+
+    /// Monitors of a trade opened for the strategy, built for each trade.
+    fn monitors(&self) -> Vec<Box<dyn StrategyMonitor>> {
+        vec![]
+    }
+
     fn trade_side(&self) -> Option<TradeSide> {
         None
     }
 
     fn exit_on_opposite_signal(&self) -> bool {
-        return true;
+        true
     }
 }
@@ -5,19 +5,18 @@ use crate::account::order::Order;
 use crate::candles::candle::Candle;
 use crate::account::context::Context;
 
+/// Decisions a monitor makes about the trades of a strategy. The context
+/// the trade is decided in is passed along with it.
 pub trait StrategyMonitor: IndicatorGroup {
-    //protected Context context;
-    //protected Trader trader;
-
-    fn handle_stop(&self, trade: Trade) -> Option<String> {
+    fn handle_stop(&self, _trade: &Trade, _context: &Context) -> Option<String> {
         None
     }
 
-    fn discard_buy(&self, strategy: impl Strategy) -> bool {
+    fn discard_buy(&self, _strategy: &dyn Strategy, _context: &Context) -> bool {
         false
     }
 
-    fn discard_short_sell(&self, strategy: impl Strategy) -> bool {
+    fn discard_short_sell(&self, _strategy: &dyn Strategy, _context: &Context) -> bool {
         false
     }
 
@@ -25,27 +24,26 @@ pub trait StrategyMonitor: IndicatorGroup {
         true
     }
 
-    fn highest_profit(&self, trade: Trade, change: f64) {}
+    fn highest_profit(&self, _trade: Trade, _change: f64) {}
 
-    fn worst_loss(&self, trade: Trade, change: f64) {}
+    fn worst_loss(&self, _trade: Trade, _change: f64) {}
 
-    fn bought(&self, trade: Trade, order: Order) {}
+    fn bought(&self, _trade: Trade, _order: Order) {}
 
-    fn sold(&self, trade: Trade, order: Order) {}
+    fn sold(&self, _trade: Trade, _order: Order) {}
 
-    fn allow_exit(&self, trade: Trade) -> bool {
+    fn allow_exit(&self, _trade: &Trade, _context: &Context) -> bool {
         true
     }
 
     fn allow_trade_switch(
         &self,
-        trade: Trade,
-        exit_symbol: String,
-        candle: Candle,
-        candle_ticker: String,
+        _trade: &Trade,
+        _exit_symbol: String,
+        _candle: Candle,
+        _candle_ticker: String,
+        _context: &Context,
     ) -> bool {
         false
     }
-
-    fn set_context(&self, context: Context);
 }
@@ -1,71 +1,91 @@
 use crate::account::order_request::OrderRequest;
 use crate::account::order::{Order, OrderType, OrderSide};
-use crate::account::balance::Balance;
-use std::collections::HashMap;
+use crate::decimal::Decimal;
 
 pub trait TradingFees {
-    fn take_fee(amount: f64, order_type: OrderType, order_side: OrderSide) -> f64;
+    /// Amount left after the fee is taken from `amount`.
+    fn take_fee(&self, amount: Decimal, order_type: OrderType, order_side: OrderSide) -> Decimal;
 
-    fn fees_on_order(&self, order: Order) -> f64 {
-        // final double amount = order.getTotalOrderAmount();
-        // return feesOnAmount(amount, order.getType(), order.getSide());
+    fn fees_on_order(&self, order: &Order) -> Decimal {
+        self.fees_on_total_order_amount(order)
+    }
 
-        1.0
+    fn fees_on_order_request(&self, order_request: &OrderRequest) -> Decimal {
+        self.fees_on_amount(
+            order_request.total_order_amount(),
+            *order_request.order_type(),
+            *order_request.side(),
+        )
     }
 
-    fn fees_on_order_request(&self, order_request: OrderRequest) -> f64 {
-        // final double amount = order.getTotalOrderAmount();
-        // return feesOnAmount(amount, order.getType(), order.getSide());
+    fn fees_on_traded_amount(&self, order: &Order) -> Decimal {
+        let amount = order.get_total_traded_amount();
+
+        if amount.is_zero() {
+            return Decimal::ZERO;
+        }
 
-        1.0
+        self.fees_on_amount(amount, *order.order_request.order_type(), *order.order_request.side())
     }
 
-    fn fees_on_traded_amount(&self, order: Order) -> f64 {
-        // final double amount = order.getTotalTraded();
-        // if (amount == 0.0) {
-        //     return 0.0;
-        // }
-        // return feesOnAmount(amount, order.getType(), order.getSide());
+    fn fees_on_partial_fill(&self, order: &Order) -> Decimal {
+        let amount = order.get_partial_fill_total_price();
+
+        if amount.is_zero() {
+            return Decimal::ZERO;
+        }
 
-        1.0
+        self.fees_on_amount(amount, *order.order_request.order_type(), *order.order_request.side())
     }
 
-    fn fees_on_partial_fill(&self, order: Order) -> f64 {
-        // final double amount = order.get_partial_fill_total_price();
-        // if (amount == 0.0) {
-        //     return 0.0;
-        // }
-        // return feesOnAmount(amount, order.getType(), order.getSide());
+    fn fees_on_amount(&self, amount: Decimal, order_type: OrderType, order_side: OrderSide) -> Decimal {
+        amount - self.take_fee(amount, order_type, order_side)
+    }
+
+    fn fees_on_total_order_amount(&self, order: &Order) -> Decimal {
+        let amount = order.total_order_amount_at_average_price();
 
-        1.0
+        self.fees_on_amount(amount, *order.order_request.order_type(), *order.order_request.side())
     }
 
-    fn fees_on_amount(&self, order: Order) -> f64 {
-        return order.get_partial_fill_total_price() - self.take_fee(order.get_partial_fill_total_price(), orderType, side);
+    fn get_break_even_amount(&self, amount: Decimal) -> Decimal {
+        let mut out = self.take_fee(amount, OrderType::Limit, OrderSide::Buy);
+        out = self.take_fee(out, OrderType::Limit, OrderSide::Buy);
+
+        amount + (amount - out)
     }
 
-    fn fees_on_total_order_amount(&self, order: Order) -> f64 {
-        // double amount = order.getTotalOrderAmount();
-        // return feesOnAmount(amount, order.getType(), order.getSide());
+    /// Percentage the price has to move for a round trip to break even.
+    fn get_break_even_change(&self, amount: Decimal) -> f64 {
+        if amount.is_zero() {
+            return 0.0;
+        }
 
-        1.0
+        let break_even_amount = self.get_break_even_amount(amount);
+
+        ((break_even_amount / amount).to_f64() - 1.0) * 100.0
     }
+}
 
-    fn get_break_even_amount(&self, order: Order) -> f64 {
-        // double out = takeFee(amount, Order.Type.LIMIT, Order.Side.BUY);
-        // out = takeFee(out, Order.Type.LIMIT, Order.Side.BUY);
-        // return amount + (amount - out);
+/// Charges the same percentage on every order, e.g. 0.1 for 0.1%.
+pub struct PercentageFee {
+    percentage: Decimal,
+}
 
-        1.0
+impl PercentageFee {
+    pub fn new(percentage: Decimal) -> PercentageFee {
+        PercentageFee {
+            percentage,
+        }
     }
 
-    fn get_break_even_change(&self, order: Order) -> f64 {
-        // if (amount == 0.0) {
-        //     return 0.0;
-        // }
-        // double breakEvenAmount = getBreakEvenAmount(amount);
-        // return ((breakEvenAmount / amount) - 1) * 100.0;
+    pub fn percentage(&self) -> Decimal {
+        self.percentage
+    }
+}
 
-        1.0
+impl TradingFees for PercentageFee {
+    fn take_fee(&self, amount: Decimal, _order_type: OrderType, _order_side: OrderSide) -> Decimal {
+        amount - (amount * self.percentage / Decimal::from(100))
     }
 }